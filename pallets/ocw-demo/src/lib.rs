@@ -8,15 +8,16 @@ mod tests;
 
 use core::{convert::TryInto, fmt};
 use frame_support::{
-	debug, decl_error, decl_event, decl_module, decl_storage, dispatch::DispatchResult,
+	debug, decl_error, decl_event, decl_module, decl_storage, dispatch::DispatchResult, ensure,
+	traits::Get, weights::Weight,
 };
 use parity_scale_codec::{Decode, Encode};
 
 use frame_system::{
-	ensure_none, ensure_signed,
+	ensure_none, ensure_root, ensure_signed,
 	offchain::{
 		AppCrypto, CreateSignedTransaction, SendSignedTransaction, SendUnsignedTransaction,
-		SignedPayload, Signer, SigningTypes, SubmitTransaction,
+		SignedPayload, Signer, SigningTypes,
 	},
 };
 use sp_core::crypto::KeyTypeId;
@@ -27,10 +28,12 @@ use sp_runtime::{
 		storage::StorageValueRef,
 		storage_lock::{BlockAndTime, StorageLock},
 	},
+	traits::{IdentifyAccount, One, Saturating, UniqueSaturatedInto, Zero},
 	transaction_validity::{
-		InvalidTransaction, TransactionSource, TransactionValidity, ValidTransaction,
+		InvalidTransaction, TransactionSource, TransactionValidity, TransactionValidityError,
+		ValidTransaction,
 	},
-	RuntimeDebug,
+	Perbill, RuntimeAppPublic, RuntimeDebug,
 };
 use sp_std::{collections::vec_deque::VecDeque, prelude::*, str};
 
@@ -44,64 +47,128 @@ use serde::{Deserialize, Deserializer};
 /// `KeyTypeId` via the keystore to sign the transaction.
 /// The keys can be inserted manually via RPC (see `author_insertKey`).
 pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"demo");
-const NUM_VEC_LEN: usize = 10;
 /// The type to sign and send transactions.
 const UNSIGNED_TXS_PRIORITY: u64 = 100;
+/// How many blocks after the block it was made for a submitted number is still accepted
+const UNSIGNED_TXS_MAX_AGE: u32 = 3;
 
-// We are fetching information from the github public API about organization`substrate-developer-hub`.
+// We are fetching information from the github public API about organization`substrate-developer-hub`,
+//   unless another URL is set in `RemoteUrl`.
 const HTTP_REMOTE_REQUEST: &str = "https://api.github.com/orgs/substrate-developer-hub";
+// The user agent sent with every request, unless another is set in `UserAgent`
 const HTTP_HEADER_USER_AGENT: &str = "jimmychu0807";
 
-const FETCH_TIMEOUT_PERIOD: u64 = 3000; // in milli-seconds
-const LOCK_TIMEOUT_EXPIRATION: u64 = FETCH_TIMEOUT_PERIOD + 1000; // in milli-seconds
+const FETCH_BACKOFF_PERIOD: u64 = 100; // in milli-seconds, before the first retry
+const LOCK_TIMEOUT_MARGIN: u64 = 1000; // in milli-seconds, on top of `HttpTimeout`
 const LOCK_BLOCK_EXPIRATION: u32 = 3; // in block number
 
 const ONCHAIN_TX_KEY: &[u8] = b"ocw-demo::storage::tx";
+const GH_INFO_INDEX_KEY: &[u8] = b"ocw-demo::indexed-gh-info";
+const LAST_SUBMITTED_KEY: &[u8] = b"ocw-demo::last-submitted";
+const LAST_HOST_KEY: &[u8] = b"ocw-demo::last-host";
+const PENDING_NUMBERS_KEY: &[u8] = b"ocw-demo::pending-numbers";
 
 /// Based on the above `KeyTypeId` we need to generate a pallet-specific crypto type wrapper.
 /// We can utilize the supported crypto kinds (`sr25519`, `ed25519` and `ecdsa`) and augment
 /// them with the pallet-specific identifier.
+///
+/// A runtime picks the kind of key its off-chain workers sign with through `Config::AuthorityId`:
+/// `crypto::TestAuthId` for sr25519 keys, `crypto::ed25519::TestAuthId` or
+/// `crypto::ecdsa::TestAuthId` for the others. The signing code only relies on `AppCrypto`, so it
+/// works the same with any of them.
 pub mod crypto {
 	use crate::KEY_TYPE;
-	use sp_core::sr25519::Signature as Sr25519Signature;
 	use sp_runtime::app_crypto::{app_crypto, sr25519};
-	use sp_runtime::{traits::Verify, MultiSignature, MultiSigner};
+	use sp_runtime::{MultiSignature, MultiSigner};
 
 	app_crypto!(sr25519, KEY_TYPE);
 
 	pub struct TestAuthId;
-	// implemented for ocw-runtime
+	// implemented for ocw-runtime and the mock runtime in test
 	impl frame_system::offchain::AppCrypto<MultiSigner, MultiSignature> for TestAuthId {
 		type RuntimeAppPublic = Public;
 		type GenericSignature = sp_core::sr25519::Signature;
 		type GenericPublic = sp_core::sr25519::Public;
 	}
 
-	// implemented for mock runtime in test
-	impl frame_system::offchain::AppCrypto<<Sr25519Signature as Verify>::Signer, Sr25519Signature>
-		for TestAuthId
-	{
-		type RuntimeAppPublic = Public;
-		type GenericSignature = sp_core::sr25519::Signature;
-		type GenericPublic = sp_core::sr25519::Public;
+	pub mod ed25519 {
+		use crate::KEY_TYPE;
+		use sp_runtime::app_crypto::{app_crypto, ed25519};
+		use sp_runtime::{MultiSignature, MultiSigner};
+
+		app_crypto!(ed25519, KEY_TYPE);
+
+		pub struct TestAuthId;
+		impl frame_system::offchain::AppCrypto<MultiSigner, MultiSignature> for TestAuthId {
+			type RuntimeAppPublic = Public;
+			type GenericSignature = sp_core::ed25519::Signature;
+			type GenericPublic = sp_core::ed25519::Public;
+		}
+	}
+
+	pub mod ecdsa {
+		use crate::KEY_TYPE;
+		use sp_runtime::app_crypto::{app_crypto, ecdsa};
+		use sp_runtime::{MultiSignature, MultiSigner};
+
+		app_crypto!(ecdsa, KEY_TYPE);
+
+		pub struct TestAuthId;
+		impl frame_system::offchain::AppCrypto<MultiSigner, MultiSignature> for TestAuthId {
+			type RuntimeAppPublic = Public;
+			type GenericSignature = sp_core::ecdsa::Signature;
+			type GenericPublic = sp_core::ecdsa::Public;
+		}
 	}
 }
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
-pub struct Payload<Public> {
+pub struct Payload<Public, BlockNumber> {
+	block_number: BlockNumber,
 	number: u64,
 	public: Public,
 }
 
-impl<T: SigningTypes> SignedPayload<T> for Payload<T::Public> {
+impl<T: SigningTypes, BlockNumber: Encode> SignedPayload<T> for Payload<T::Public, BlockNumber> {
+	fn public(&self) -> T::Public {
+		self.public.clone()
+	}
+}
+
+/// Several numbers an off-chain worker submits at once in `block_number`, oldest first
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct NumbersPayload<Public, BlockNumber> {
+	block_number: BlockNumber,
+	numbers: Vec<u64>,
+	public: Public,
+}
+
+impl<T: SigningTypes, BlockNumber: Encode> SignedPayload<T>
+	for NumbersPayload<T::Public, BlockNumber>
+{
+	fn public(&self) -> T::Public {
+		self.public.clone()
+	}
+}
+
+/// A report, signed by an off-chain worker, that it failed to fetch github info in `block_number`
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct FetchFailurePayload<Public, BlockNumber> {
+	block_number: BlockNumber,
+	public: Public,
+}
+
+impl<T: SigningTypes, BlockNumber: Encode> SignedPayload<T>
+	for FetchFailurePayload<T::Public, BlockNumber>
+{
 	fn public(&self) -> T::Public {
 		self.public.clone()
 	}
 }
 
 // ref: https://serde.rs/container-attrs.html#crate
-#[derive(Deserialize, Encode, Decode, Default)]
-struct GithubInfo {
+#[derive(Deserialize, Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct GithubInfo {
 	// Specify our own deserializing function to convert JSON string to vector of bytes
 	#[serde(deserialize_with = "de_string_to_bytes")]
 	login: Vec<u8>,
@@ -113,6 +180,20 @@ struct GithubInfo {
 #[derive(Debug, Deserialize, Encode, Decode, Default)]
 struct IndexingData(Vec<u8>, u64);
 
+/// The response of a price source
+#[derive(Deserialize)]
+struct PriceInfo {
+	price: u64,
+}
+
+/// The HTTP methods the off-chain worker sends requests with
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+enum HttpMethod {
+	Get,
+	// Sends a JSON body
+	Post,
+}
+
 pub fn de_string_to_bytes<'de, D>(de: D) -> Result<Vec<u8>, D::Error>
 where
 	D: Deserializer<'de>,
@@ -143,12 +224,91 @@ pub trait Config: frame_system::Config + CreateSignedTransaction<Call<Self>> {
 	type Call: From<Call<Self>>;
 	/// The overarching event type.
 	type Event: From<Event<Self>> + Into<<Self as frame_system::Config>::Event>;
+	/// Whether the average of `Numbers` is rounded half up rather than truncated.
+	///
+	/// Integer division truncates toward zero, which biases long-running aggregates downward.
+	/// Rounding removes that bias, at the cost of sometimes reporting a value above the true mean.
+	type RoundAverage: Get<bool>;
+	/// The number of times a failed HTTP request is retried before giving up.
+	type MaxFetchRetries: Get<u32>;
+	/// URLs the off-chain worker fetches a price from, each responding with JSON like
+	/// `{ "price": 100 }`. The median of the prices fetched is submitted instead of the block
+	/// number.
+	type PriceSources: Get<Vec<&'static [u8]>>;
+	/// The most recent numbers `Numbers` may hold. `NumberBufferLength` starts out at this, and
+	/// cannot be set above it.
+	type MaxNumbers: Get<u32>;
+	/// How often, in blocks, the off-chain worker fetches github info. It fetches in blocks whose
+	/// number is a multiple of this, and never if it is zero.
+	type FetchInterval: Get<Self::BlockNumber>;
+	/// How long, in milliseconds, fetched github info is cached before it is fetched again
+	type CacheTtl: Get<u64>;
+	/// How long, in milliseconds, an HTTP fetch may take, retries and fallback URLs included
+	type HttpTimeout: Get<u64>;
+	/// Whether a number equal to the most recent one in `Numbers` is ignored rather than stored
+	/// again
+	type DedupNumbers: Get<bool>;
+	/// How much a number has to differ from the last one an off-chain worker submitted for it to
+	/// be submitted too
+	type SubmitThreshold: Get<u64>;
+	/// How many fetches in a row may fail before off-chain workers stop fetching, until root
+	/// resets the circuit
+	type FailureThreshold: Get<u32>;
+	/// A URL the off-chain worker fetches several numbers from at once, every `FetchInterval`
+	/// blocks. It responds with a JSON array of numbers, or of objects like `{ "price": 100 }`.
+	type BatchSource: Get<Option<&'static [u8]>>;
+	/// The longest github login stored in `GithubLogin`
+	type MaxLoginLength: Get<u32>;
+	/// The longest github blog URL accepted along with the login
+	type MaxBlogLength: Get<u32>;
+	/// The largest response body, in bytes, the off-chain worker reads. A larger response fails
+	/// the request, so that a hostile endpoint cannot exhaust the worker's memory.
+	type MaxResponseBytes: Get<u32>;
+	/// How much weight each new number gets in `Ema`. The higher it is, the faster the average
+	/// follows new numbers.
+	type EmaAlpha: Get<Perbill>;
+	/// How many numbers an off-chain worker buffers before sending them together, in a single
+	/// signed transaction
+	type SignedBatchSize: Get<u32>;
 }
 
 decl_storage! {
 	trait Store for Module<T: Config> as OcwDemo {
-		/// A vector of recently submitted numbers. Bounded by NUM_VEC_LEN
+		/// A vector of recently submitted numbers. Bounded by NumberBufferLength
 		Numbers get(fn numbers): VecDeque<u64>;
+		/// The number of recent numbers kept in `Numbers`, if set by root. Otherwise, `MaxNumbers`
+		/// are kept.
+		NumberBufferLength get(fn number_buffer_length): Option<u32>;
+		/// The URL the off-chain worker fetches github info from. Empty means `HTTP_REMOTE_REQUEST`.
+		RemoteUrl get(fn remote_url): Vec<u8>;
+		/// URLs the off-chain worker fetches github info from, in order, when the remote URL fails
+		FallbackUrls get(fn fallback_urls): Vec<Vec<u8>>;
+		/// The User-Agent header the off-chain worker sends. Empty means `HTTP_HEADER_USER_AGENT`.
+		UserAgent get(fn user_agent): Vec<u8>;
+		/// The latest median price off-chain workers aggregated from `PriceSources`
+		AggregatedValue get(fn aggregated_value): Option<u64>;
+		/// The average of `Numbers`, updated whenever they change. Zero while there are none.
+		Average get(fn current_average): u64;
+		/// The median of `Numbers`, updated whenever they change
+		Median get(fn current_median): u64;
+		/// The exponential moving average of the numbers stored, weighting each new one by
+		/// `EmaAlpha`. `None` until the first number is stored.
+		Ema get(fn ema): Option<u64>;
+		/// The block in which an off-chain worker last fetched github info successfully
+		LastFetch get(fn last_fetch): Option<T::BlockNumber>;
+		/// How many times in a row off-chain workers have failed to fetch github info
+		FetchErrors get(fn fetch_errors): u32;
+		/// Whether more than `FailureThreshold` fetches in a row failed, so that off-chain workers
+		/// no longer fetch github info
+		CircuitOpen get(fn circuit_open): bool;
+		/// The accounts whose signed payloads are accepted from off-chain workers
+		Authorities get(fn authorities) config(): Vec<T::AccountId>;
+		/// The login of the github organization, as last submitted by an off-chain worker.
+		/// Bounded by `MaxLoginLength`.
+		GithubLogin get(fn github_login): Vec<u8>;
+		/// The number of public repos of the github organization, as last submitted by an
+		/// off-chain worker
+		GithubPublicRepos get(fn github_public_repos): u32;
 	}
 }
 
@@ -157,9 +317,44 @@ decl_event!(
 	pub enum Event<T>
 	where
 		AccountId = <T as frame_system::Config>::AccountId,
+		BlockNumber = <T as frame_system::Config>::BlockNumber,
 	{
-		/// Event generated when a new number is accepted to contribute to the average.
-		NewNumber(Option<AccountId>, u64),
+		/// Event generated when a new number is accepted to contribute to the average. The flag is
+		/// set when the number was a duplicate of the most recent one, and so was not stored.
+		NewNumber(Option<AccountId>, u64, bool),
+		/// Event generated when the number of recent numbers kept is changed.
+		NumberBufferLengthSet(u32),
+		/// Event generated when the URL fetched by the off-chain worker is changed.
+		RemoteUrlSet(Vec<u8>),
+		/// Event generated when the URLs fetched when the remote URL fails are changed.
+		FallbackUrlsSet(Vec<Vec<u8>>),
+		/// Event generated when the User-Agent sent by the off-chain worker is changed.
+		UserAgentSet(Vec<u8>),
+		/// Event generated when an off-chain worker submits the median price it aggregated from
+		/// `PriceSources`.
+		AggregatedValueUpdated(u64),
+		/// Event generated when the median of the recent numbers is recomputed.
+		NewMedian(u64),
+		/// Event generated when the average of the recent numbers is recomputed.
+		NewAverage(u64),
+		/// Event generated when the exponential moving average of the numbers is updated.
+		NewEma(u64),
+		/// Event generated when root clears the recent numbers.
+		NumbersCleared,
+		/// Event generated when an off-chain worker reports a successful fetch in the given block.
+		FetchRecorded(BlockNumber),
+		/// Event generated when an off-chain worker reports a failed fetch in the given block,
+		/// with how many fetches in a row have now failed.
+		FetchFailed(BlockNumber, u32),
+		/// Event generated when too many fetches in a row failed, and fetching stops.
+		CircuitOpened,
+		/// Event generated when root resets the circuit, and fetching resumes.
+		CircuitClosed,
+		/// Event generated when the accounts accepted from off-chain workers are changed.
+		AuthoritiesSet(Vec<AccountId>),
+		/// Event generated when an authority submits the github login and number of public repos
+		/// it fetched.
+		GithubInfoUpdated(AccountId, Vec<u8>, u32),
 	}
 );
 
@@ -180,6 +375,34 @@ decl_error! {
 
 		// Error returned when fetching github info
 		HttpFetchingError,
+
+		// Error returned when a remote response is not the JSON expected
+		JsonParsingError,
+
+		// Error returned when the number buffer would be unable to hold any numbers
+		ZeroBufferLength,
+
+		// Error returned when the number buffer would hold more than `MaxNumbers`
+		BufferLengthTooLarge,
+
+		// Error returned when a remote URL is not valid UTF-8
+		InvalidRemoteUrl,
+
+		// Error returned when a user agent is not valid UTF-8
+		InvalidUserAgent,
+
+		// Error returned when more numbers are submitted at once than `MaxNumbers`
+		TooManyNumbers,
+
+		// Error returned when github info or a failed fetch is reported by an account that is not an
+		// authority
+		NotAuthority,
+
+		// Error returned when a github login is longer than `MaxLoginLength`
+		LoginTooLong,
+
+		// Error returned when a github blog URL is longer than `MaxBlogLength`
+		BlogTooLong,
 	}
 }
 
@@ -191,7 +414,7 @@ decl_module! {
 		pub fn submit_number_signed(origin, number: u64) -> DispatchResult {
 			let who = ensure_signed(origin)?;
 			debug::info!("submit_number_signed: ({}, {:?})", number, who);
-			Self::append_or_replace_number(number);
+			let stored = Self::append_or_replace_number(number);
 
 			// Off-chain indexing allowing on-chain extrinsics to write to off-chain storage predictably
 			// so it can be read in off-chain worker context. As off-chain indexing is called in on-chain
@@ -208,42 +431,258 @@ decl_module! {
 			let data = IndexingData(b"submit_number_signed".to_vec(), number);
 			offchain_index::set(&key, &data.encode());
 
-			Self::deposit_event(RawEvent::NewNumber(Some(who), number));
+			Self::deposit_event(RawEvent::NewNumber(Some(who), number, !stored));
 			Ok(())
 		}
 
+		/// Submit a number an off-chain worker made. The transaction is unsigned, so no fee is
+		/// paid, but `validate_unsigned` only accepts payloads signed by one of `Authorities`.
 		#[weight = 10000]
-		pub fn submit_number_unsigned(origin, number: u64) -> DispatchResult {
+		pub fn submit_number_unsigned(origin, payload: Payload<T::Public, T::BlockNumber>,
+			_signature: T::Signature) -> DispatchResult
+		{
 			let _ = ensure_none(origin)?;
-			debug::info!("submit_number_unsigned: {}", number);
-			Self::append_or_replace_number(number);
+			let Payload { number, public, .. } = payload;
+			debug::info!("submit_number_unsigned: ({}, {:?})", number, public);
+			let stored = Self::append_or_replace_number(number);
 
 			// Off-chain indexing write
 			let key = Self::derived_key(frame_system::Module::<T>::block_number());
 			let data = IndexingData(b"submit_number_unsigned".to_vec(), number);
 			offchain_index::set(&key, &data.encode());
 
-			Self::deposit_event(RawEvent::NewNumber(None, number));
+			Self::deposit_event(RawEvent::NewNumber(None, number, !stored));
 			Ok(())
 		}
 
-		#[weight = 10000]
-		pub fn submit_number_unsigned_with_signed_payload(origin, payload: Payload<T::Public>,
+		/// Submit several numbers an off-chain worker made at once, oldest first. The transaction
+		/// is unsigned, but `validate_unsigned` only accepts payloads signed by one of
+		/// `Authorities`. Each number costs a write.
+		#[weight = 10000 + T::DbWeight::get().writes(payload.numbers.len() as Weight)]
+		pub fn submit_numbers_unsigned(origin, payload: NumbersPayload<T::Public, T::BlockNumber>,
 			_signature: T::Signature) -> DispatchResult
+		{
+			ensure!(
+				payload.numbers.len() <= T::MaxNumbers::get() as usize,
+				Error::<T>::TooManyNumbers
+			);
+			let _ = ensure_none(origin)?;
+			let NumbersPayload { numbers, public, .. } = payload;
+			debug::info!("submit_numbers_unsigned: ({:?}, {:?})", numbers, public);
+
+			for number in numbers {
+				let stored = Self::append_or_replace_number(number);
+				Self::deposit_event(RawEvent::NewNumber(None, number, !stored));
+			}
+			Ok(())
+		}
+
+		/// Append several numbers at once, oldest first. Off-chain workers send the numbers they
+		/// buffered with this. Each number costs a write.
+		#[weight = 10000 + T::DbWeight::get().writes(numbers.len() as Weight)]
+		pub fn submit_numbers_signed(origin, numbers: Vec<u64>) -> DispatchResult {
+			ensure!(numbers.len() <= T::MaxNumbers::get() as usize, Error::<T>::TooManyNumbers);
+			let who = ensure_signed(origin)?;
+			debug::info!("submit_numbers_signed: ({:?}, {:?})", numbers, who);
+
+			for number in numbers {
+				let stored = Self::append_or_replace_number(number);
+				Self::deposit_event(RawEvent::NewNumber(Some(who.clone()), number, !stored));
+			}
+			Ok(())
+		}
+
+		#[weight = 10000]
+		pub fn submit_number_unsigned_with_signed_payload(origin,
+			payload: Payload<T::Public, T::BlockNumber>, _signature: T::Signature) -> DispatchResult
 		{
 			let _ = ensure_none(origin)?;
 			// we don't need to verify the signature here because it has been verified in
 			//   `validate_unsigned` function when sending out the unsigned tx.
-			let Payload { number, public } = payload;
+			let Payload { number, public, .. } = payload;
 			debug::info!("submit_number_unsigned_with_signed_payload: ({}, {:?})", number, public);
-			Self::append_or_replace_number(number);
+			let stored = Self::append_or_replace_number(number);
 
 			// Off-chain indexing write
 			let key = Self::derived_key(frame_system::Module::<T>::block_number());
 			let data = IndexingData(b"submit_number_unsigned_with_signed_payload".to_vec(), number);
 			offchain_index::set(&key, &data.encode());
 
-			Self::deposit_event(RawEvent::NewNumber(None, number));
+			Self::deposit_event(RawEvent::NewNumber(None, number, !stored));
+			Ok(())
+		}
+
+		/// Submit the median price an off-chain worker aggregated from `PriceSources`. It is kept in
+		/// `AggregatedValue`, and joins the recent numbers like any other number. The transaction
+		/// is unsigned, but `validate_unsigned` only accepts payloads signed by one of
+		/// `Authorities`.
+		#[weight = 10000]
+		pub fn submit_aggregated_value(origin, payload: Payload<T::Public, T::BlockNumber>,
+			_signature: T::Signature) -> DispatchResult
+		{
+			let _ = ensure_none(origin)?;
+			let Payload { number, public, .. } = payload;
+			debug::info!("submit_aggregated_value: ({}, {:?})", number, public);
+			AggregatedValue::put(number);
+			Self::deposit_event(RawEvent::AggregatedValueUpdated(number));
+
+			let stored = Self::append_or_replace_number(number);
+			Self::deposit_event(RawEvent::NewNumber(None, number, !stored));
+			Ok(())
+		}
+
+		/// Store the github login and number of public repos an off-chain worker fetched, and
+		/// record the fetch in the current block. Off-chain workers call this in a signed
+		/// transaction, and only `Authorities` can submit them.
+		#[weight = 10000]
+		pub fn submit_gh_info(origin, info: GithubInfo) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			debug::info!("submit_gh_info: ({:?}, {:?})", info, who);
+			ensure!(Self::authorities().contains(&who), Error::<T>::NotAuthority);
+			Self::store_gh_info(who, &info)?;
+
+			let block_number = <frame_system::Module<T>>::block_number();
+			<LastFetch<T>>::put(block_number);
+			FetchErrors::kill();
+			// Indexing the info makes it available to the off-chain workers of every node that
+			//   imports this block, not just the one that fetched it.
+			offchain_index::set(GH_INFO_INDEX_KEY, &info.encode());
+
+			Self::deposit_event(RawEvent::FetchRecorded(block_number));
+			Ok(())
+		}
+
+		/// Record that an off-chain worker failed to fetch github info in the block of the
+		/// payload. The transaction is unsigned, but `validate_unsigned` only accepts payloads
+		/// signed by one of `Authorities`, for the current block.
+		#[weight = 10000]
+		pub fn record_fetch_failure(origin,
+			payload: FetchFailurePayload<T::Public, T::BlockNumber>, _signature: T::Signature)
+			-> DispatchResult
+		{
+			let _ = ensure_none(origin)?;
+			let FetchFailurePayload { block_number, public } = payload;
+			debug::info!("record_fetch_failure: ({:?}, {:?})", block_number, public);
+			// `validate_unsigned` checked the signature. Only reports from current authorities
+			//   count towards `FailureThreshold`, so nobody else can open the circuit.
+			ensure!(
+				Self::authorities().contains(&public.into_account()),
+				Error::<T>::NotAuthority
+			);
+
+			let errors = FetchErrors::mutate(|errors| {
+				*errors = errors.saturating_add(1);
+				*errors
+			});
+
+			Self::deposit_event(RawEvent::FetchFailed(block_number, errors));
+			if errors > T::FailureThreshold::get() && !Self::circuit_open() {
+				CircuitOpen::put(true);
+				Self::deposit_event(RawEvent::CircuitOpened);
+			}
+			Ok(())
+		}
+
+		/// Let off-chain workers fetch github info again after too many failures, starting the
+		/// count of failures over.
+		#[weight = 10000]
+		pub fn reset_circuit(origin) -> DispatchResult {
+			ensure_root(origin)?;
+
+			CircuitOpen::kill();
+			FetchErrors::kill();
+
+			Self::deposit_event(RawEvent::CircuitClosed);
+			Ok(())
+		}
+
+		/// Change the accounts whose signed payloads are accepted from off-chain workers.
+		#[weight = 10000]
+		pub fn set_authorities(origin, authorities: Vec<T::AccountId>) -> DispatchResult {
+			ensure_root(origin)?;
+
+			<Authorities<T>>::put(&authorities);
+
+			Self::deposit_event(RawEvent::AuthoritiesSet(authorities));
+			Ok(())
+		}
+
+		/// Change how many recent numbers are kept. If the buffer shrinks, only the newest numbers
+		/// are kept.
+		#[weight = 10000]
+		pub fn set_number_buffer_length(origin, length: u32) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(length > 0, Error::<T>::ZeroBufferLength);
+			ensure!(length <= T::MaxNumbers::get(), Error::<T>::BufferLengthTooLarge);
+
+			NumberBufferLength::put(length);
+			Numbers::mutate(|numbers| {
+				while numbers.len() > length as usize {
+					let _ = numbers.pop_front();
+				}
+				debug::info!("Number vector: {:?}", numbers);
+				Self::update_average(numbers);
+				Self::update_median(numbers);
+			});
+
+			Self::deposit_event(RawEvent::NumberBufferLengthSet(length));
+			Ok(())
+		}
+
+		/// Forget every number in `Numbers`, along with their average, median and moving average. The
+		/// next number stored starts them over.
+		#[weight = 10000]
+		pub fn clear_numbers(origin) -> DispatchResult {
+			ensure_root(origin)?;
+
+			Numbers::kill();
+			Average::kill();
+			Median::kill();
+			Ema::kill();
+
+			Self::deposit_event(RawEvent::NumbersCleared);
+			Ok(())
+		}
+
+		/// Change the URL the off-chain worker fetches github info from. An empty URL restores the
+		/// default.
+		#[weight = 10000]
+		pub fn set_remote_url(origin, url: Vec<u8>) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(str::from_utf8(&url).is_ok(), Error::<T>::InvalidRemoteUrl);
+
+			RemoteUrl::put(&url);
+
+			Self::deposit_event(RawEvent::RemoteUrlSet(url));
+			Ok(())
+		}
+
+		/// Change the URLs the off-chain worker falls back to, in order, when fetching github info
+		/// from the remote URL fails.
+		#[weight = 10000]
+		pub fn set_fallback_urls(origin, urls: Vec<Vec<u8>>) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(
+				urls.iter().all(|url| !url.is_empty() && str::from_utf8(url).is_ok()),
+				Error::<T>::InvalidRemoteUrl
+			);
+
+			FallbackUrls::put(&urls);
+
+			Self::deposit_event(RawEvent::FallbackUrlsSet(urls));
+			Ok(())
+		}
+
+		/// Change the User-Agent header the off-chain worker sends. An empty user agent restores
+		/// the default.
+		#[weight = 10000]
+		pub fn set_user_agent(origin, ua: Vec<u8>) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(str::from_utf8(&ua).is_ok(), Error::<T>::InvalidUserAgent);
+
+			UserAgent::put(&ua);
+
+			Self::deposit_event(RawEvent::UserAgentSet(ua));
 			Ok(())
 		}
 
@@ -255,19 +694,40 @@ decl_module! {
 			// 2. Sending unsigned transaction from ocw
 			// 3. Sending unsigned transactions with signed payloads from ocw
 			// 4. Fetching JSON via http requests in ocw
-			const TRANSACTION_TYPES: usize = 4;
-			let result = match block_number.try_into().unwrap_or(0) % TRANSACTION_TYPES	{
-				1 => Self::offchain_signed_tx(block_number),
-				2 => Self::offchain_unsigned_tx(block_number),
-				3 => Self::offchain_unsigned_tx_signed_payload(block_number),
-				0 => Self::fetch_github_info(),
-				_ => Err(Error::<T>::UnknownOffchainMux),
+			//
+			// The first three rotate every block, unless a price is aggregated from
+			//   `PriceSources`, which is then submitted on its own. Fetching, prices included,
+			//   runs on its own schedule, every `FetchInterval` blocks, alongside whichever
+			//   transaction is sent.
+			const TRANSACTION_TYPES: usize = 3;
+			let interval = T::FetchInterval::get();
+			let fetching = !interval.is_zero() && (block_number % interval).is_zero();
+			let price = if fetching { Self::aggregate_price() } else { None };
+			let result = match price {
+				Some(price) => Self::offchain_aggregated_value(block_number, price),
+				None => match block_number.try_into().unwrap_or(0) % TRANSACTION_TYPES	{
+					1 => Self::offchain_signed_tx(block_number),
+					2 => Self::offchain_unsigned_tx(block_number),
+					0 => Self::offchain_unsigned_tx_signed_payload(block_number),
+					_ => Err(Error::<T>::UnknownOffchainMux),
+				},
 			};
 
 			if let Err(e) = result {
 				debug::error!("offchain_worker error: {:?}", e);
 			}
 
+			if fetching {
+				if let Err(e) = Self::fetch_github_info() {
+					debug::error!("offchain_worker error: {:?}", e);
+				}
+				if let Some(source) = T::BatchSource::get() {
+					if let Err(e) = Self::offchain_unsigned_tx_batch(block_number, source) {
+						debug::error!("offchain_worker error: {:?}", e);
+					}
+				}
+			}
+
 			// Reading back the off-chain indexing value. It is exactly the same as reading from
 			// ocw local storage.
 			let key = Self::derived_key(block_number);
@@ -284,16 +744,109 @@ decl_module! {
 }
 
 impl<T: Config> Module<T> {
+	/// The number of recent numbers kept in `Numbers`: `NumberBufferLength` if it is set, and
+	///   `T::MaxNumbers` otherwise. `T::MaxNumbers` may have been lowered since the length was
+	///   set, so it bounds the result either way.
+	fn buffer_length() -> u32 {
+		let max = T::MaxNumbers::get();
+		Self::number_buffer_length().map_or(max, |length| length.min(max))
+	}
+
 	/// Append a new number to the tail of the list, removing an element from the head if reaching
-	///   the bounded length.
-	fn append_or_replace_number(number: u64) {
+	///   the bounded length. Returns whether the number was stored, which it is not if
+	///   `T::DedupNumbers` is set and it equals the tail of the list.
+	fn append_or_replace_number(number: u64) -> bool {
+		if T::DedupNumbers::get() && Numbers::get().back() == Some(&number) {
+			debug::info!("Ignoring duplicate number: {}", number);
+			return false;
+		}
+
+		let length = Self::buffer_length() as usize;
 		Numbers::mutate(|numbers| {
-			if numbers.len() == NUM_VEC_LEN {
+			while numbers.len() >= length {
 				let _ = numbers.pop_front();
 			}
 			numbers.push_back(number);
 			debug::info!("Number vector: {:?}", numbers);
+			Self::update_average(numbers);
+			Self::update_median(numbers);
+		});
+		Self::update_ema(number);
+		true
+	}
+
+	/// Fold `number` into `Ema` and emit the result. The first number stored becomes the average.
+	fn update_ema(number: u64) {
+		let alpha = T::EmaAlpha::get();
+		let ema = Self::ema().map_or(number, |ema| {
+			(alpha * number).saturating_add(Perbill::one().saturating_sub(alpha) * ema)
 		});
+		Ema::put(ema);
+		Self::deposit_event(RawEvent::NewEma(ema));
+	}
+
+	/// Store the average of the given numbers in `Average` and emit it, or reset it to zero if
+	///   there are no numbers.
+	fn update_average(numbers: &VecDeque<u64>) {
+		match Self::average(numbers) {
+			Some(average) => {
+				debug::info!("Average: {}", average);
+				Average::put(average);
+				Self::deposit_event(RawEvent::NewAverage(average));
+			}
+			None => Average::kill(),
+		}
+	}
+
+	/// Store the github login and number of public repos in `info`, reported by `who`, and emit
+	///   them. Nothing is stored if the login is longer than `T::MaxLoginLength`, or the blog URL
+	///   longer than `T::MaxBlogLength`, as the whole info is indexed.
+	fn store_gh_info(who: T::AccountId, info: &GithubInfo) -> DispatchResult {
+		ensure!(
+			info.login.len() <= T::MaxLoginLength::get() as usize,
+			Error::<T>::LoginTooLong
+		);
+		ensure!(
+			info.blog.len() <= T::MaxBlogLength::get() as usize,
+			Error::<T>::BlogTooLong
+		);
+
+		GithubLogin::put(&info.login);
+		GithubPublicRepos::put(info.public_repos);
+
+		Self::deposit_event(RawEvent::GithubInfoUpdated(
+			who,
+			info.login.clone(),
+			info.public_repos,
+		));
+		Ok(())
+	}
+
+	/// Store the median of the given numbers in `Median`, if there are any, and emit it.
+	fn update_median(numbers: &VecDeque<u64>) {
+		// Sorting a copy, as `Numbers` are kept in the order they were submitted
+		let mut sorted = numbers.iter().copied().collect::<Vec<_>>();
+		if let Some(median) = Self::median(&mut sorted) {
+			Median::put(median);
+			Self::deposit_event(RawEvent::NewMedian(median));
+		}
+	}
+
+	/// Calculate the average of the given numbers, or `None` if there are no numbers. Whether the
+	///   result is truncated or rounded half up is decided by `T::RoundAverage`.
+	fn average(numbers: &VecDeque<u64>) -> Option<u64> {
+		if numbers.is_empty() {
+			return None;
+		}
+		// Summing in `u128` so a full window of large numbers cannot overflow.
+		let len = numbers.len() as u128;
+		let sum = numbers.iter().map(|n| *n as u128).sum::<u128>();
+		let average = if T::RoundAverage::get() {
+			(sum + len / 2) / len
+		} else {
+			sum / len
+		};
+		Some(average as u64)
 	}
 
 	#[deny(clippy::clone_double_ref)]
@@ -308,14 +861,23 @@ impl<T: Config> Module<T> {
 		})
 	}
 
-	/// Check if we have fetched github info before. If yes, we can use the cached version
-	///   stored in off-chain worker storage `storage`. If not, we fetch the remote info and
-	///   write the info into the storage for future retrieval.
+	/// Check if we have fetched github info within the last `CacheTtl` milliseconds. If yes, we
+	///   can use the cached version stored in off-chain worker storage `storage`. If not, we
+	///   fetch the remote info and write the info and the time it was fetched into the storage
+	///   for future retrieval.
 	fn fetch_github_info() -> Result<(), Error<T>> {
+		// Off-chain workers can read on-chain storage, so they all stop fetching once the
+		//   circuit is open.
+		if Self::circuit_open() {
+			debug::warn!("Too many failed fetches, not fetching until the circuit is reset");
+			return Ok(());
+		}
+
 		// Create a reference to Local Storage value.
 		// Since the local storage is common for all offchain workers, it's a good practice
 		// to prepend our entry with the pallet name.
 		let s_info = StorageValueRef::persistent(b"ocw-demo::gh-info");
+		let s_fetched_at = StorageValueRef::persistent(b"ocw-demo::gh-info-fetched-at");
 
 		// Local storage is persisted and shared between runs of the offchain workers,
 		// offchain workers may run concurrently. We can use the `mutate` function to
@@ -327,26 +889,25 @@ impl<T: Config> Module<T> {
 		//
 		// Ref: https://substrate.dev/rustdocs/v3.0.0/sp_runtime/offchain/storage/struct.StorageValueRef.html
 		if let Some(Some(gh_info)) = s_info.get::<GithubInfo>() {
-			// gh-info has already been fetched. Return early.
-			debug::info!("cached gh-info: {:?}", gh_info);
-			return Ok(());
+			// gh-info has already been fetched. Return early, unless it has expired. Info cached
+			//   without the time it was fetched is treated as expired.
+			let now = sp_io::offchain::timestamp().unix_millis();
+			let fresh = s_fetched_at
+				.get::<u64>()
+				.flatten()
+				.map_or(false, |fetched_at| {
+					now.saturating_sub(fetched_at) <= T::CacheTtl::get()
+				});
+			if fresh {
+				debug::info!("cached gh-info: {:?}", gh_info);
+				return Ok(());
+			}
 		}
 
 		// Since off-chain storage can be accessed by off-chain workers from multiple runs, it is important to lock
 		//   it before doing heavy computations or write operations.
 		// ref: https://substrate.dev/rustdocs/v3.0.0-rc3/sp_runtime/offchain/storage_lock/index.html
-		//
-		// There are four ways of defining a lock:
-		//   1) `new` - lock with default time and block exipration
-		//   2) `with_deadline` - lock with default block but custom time expiration
-		//   3) `with_block_deadline` - lock with default time but custom block expiration
-		//   4) `with_block_and_time_deadline` - lock with custom time and block expiration
-		// Here we choose the most custom one for demonstration purpose.
-		let mut lock = StorageLock::<BlockAndTime<Self>>::with_block_and_time_deadline(
-			b"ocw-demo::lock",
-			LOCK_BLOCK_EXPIRATION,
-			rt_offchain::Duration::from_millis(LOCK_TIMEOUT_EXPIRATION),
-		);
+		let mut lock = Self::fetch_lock();
 
 		// We try to acquire the lock here. If failed, we know the `fetch_n_parse` part inside is being
 		//   executed by previous run of ocw, so the function just returns.
@@ -355,8 +916,31 @@ impl<T: Config> Module<T> {
 			match Self::fetch_n_parse() {
 				Ok(gh_info) => {
 					s_info.set(&gh_info);
+					s_fetched_at.set(&sp_io::offchain::timestamp().unix_millis());
+					// Submit the info on-chain, where `LastFetch` and the info itself can be read.
+					//   The transaction is signed, so that nobody but an authority can write the
+					//   info. Failing to send it does not undo the fetch, so it is only logged.
+					let result = Signer::<T, T::AuthorityId>::any_account()
+						.send_signed_transaction(|_| Call::submit_gh_info(gh_info.clone()));
+					if !matches!(result, Some((_, Ok(())))) {
+						debug::error!("Failed to submit submit_gh_info");
+					}
 				}
 				Err(err) => {
+					// Report the failure on-chain too, where `FetchErrors` counts them. The
+					//   report is signed, so that nobody but an authority can open the circuit.
+					let block_number = <frame_system::Module<T>>::block_number();
+					let result = Signer::<T, T::AuthorityId>::any_account()
+						.send_unsigned_transaction(
+							|acct| FetchFailurePayload {
+								block_number,
+								public: acct.public.clone(),
+							},
+							Call::record_fetch_failure,
+						);
+					if !matches!(result, Some((_, Ok(())))) {
+						debug::error!("Failed to submit record_fetch_failure");
+					}
 					return Err(err);
 				}
 			}
@@ -364,40 +948,164 @@ impl<T: Config> Module<T> {
 		Ok(())
 	}
 
+	/// The lock HTTP fetches are made under, so that off-chain workers running at the same time
+	///   do not fetch at once. It expires a margin after `HttpTimeout`, which the fetches made
+	///   under it keep within.
+	fn fetch_lock() -> StorageLock<'static, BlockAndTime<Self>> {
+		// There are four ways of defining a lock:
+		//   1) `new` - lock with default time and block exipration
+		//   2) `with_deadline` - lock with default block but custom time expiration
+		//   3) `with_block_deadline` - lock with default time but custom block expiration
+		//   4) `with_block_and_time_deadline` - lock with custom time and block expiration
+		// Here we choose the most custom one for demonstration purpose.
+		StorageLock::<BlockAndTime<Self>>::with_block_and_time_deadline(
+			b"ocw-demo::lock",
+			LOCK_BLOCK_EXPIRATION,
+			rt_offchain::Duration::from_millis(
+				T::HttpTimeout::get().saturating_add(LOCK_TIMEOUT_MARGIN),
+			),
+		)
+	}
+
+	/// The github info most recently recorded on-chain, read back from the off-chain index.
+	///   Unlike `ocw-demo::gh-info`, this is filled in on every node, not only the one whose
+	///   off-chain worker fetched it.
+	pub fn indexed_github_info() -> Option<GithubInfo> {
+		StorageValueRef::persistent(GH_INFO_INDEX_KEY)
+			.get::<GithubInfo>()
+			.flatten()
+	}
+
 	/// Fetch from remote and deserialize the JSON to a struct
 	fn fetch_n_parse() -> Result<GithubInfo, Error<T>> {
 		let resp_bytes = Self::fetch_from_remote().map_err(|e| {
 			debug::error!("fetch_from_remote error: {:?}", e);
 			<Error<T>>::HttpFetchingError
 		})?;
+		Self::parse_gh_info(&resp_bytes)
+	}
 
-		let resp_str = str::from_utf8(&resp_bytes).map_err(|_| <Error<T>>::HttpFetchingError)?;
+	/// Deserialize a JSON response from github to a struct. A response that is not UTF-8 is
+	///   treated as a failed fetch, and one that is not the JSON expected as a parsing error.
+	fn parse_gh_info(bytes: &[u8]) -> Result<GithubInfo, Error<T>> {
+		let resp_str = str::from_utf8(bytes).map_err(|_| <Error<T>>::HttpFetchingError)?;
 		// Print out our fetched JSON string
 		debug::info!("{}", resp_str);
 
 		// Deserializing JSON to struct, thanks to `serde` and `serde_derive`
-		let gh_info: GithubInfo =
-			serde_json::from_str(resp_str).map_err(|_| <Error<T>>::HttpFetchingError)?;
+		let gh_info: GithubInfo = serde_json::from_str(resp_str).map_err(|e| {
+			debug::error!("unexpected github info {:?}: {}", resp_str, e);
+			<Error<T>>::JsonParsingError
+		})?;
 		Ok(gh_info)
 	}
 
 	/// This function uses the `offchain::http` API to query the remote github information,
 	///   and returns the JSON response as vector of bytes.
+	///   The remote URL is tried first, then each of `FallbackUrls` in turn, until one of them
+	///   answers. The URL that answered is kept in local storage. All of them share one
+	///   `HttpTimeout`, so that the fetch ends before the lock it is made under expires.
 	fn fetch_from_remote() -> Result<Vec<u8>, Error<T>> {
-		debug::info!("sending request to: {}", HTTP_REMOTE_REQUEST);
+		// Off-chain workers can read on-chain storage, so the URLs can be changed by extrinsics.
+		//   They were checked to be UTF-8 when they were set.
+		let remote_url = Self::remote_url();
+		let remote_url = match str::from_utf8(&remote_url) {
+			Ok(url) if !url.is_empty() => url,
+			_ => HTTP_REMOTE_REQUEST,
+		};
+		let fallback_urls = Self::fallback_urls();
+		let urls = sp_std::iter::once(remote_url).chain(
+			fallback_urls
+				.iter()
+				.filter_map(|url| str::from_utf8(url).ok()),
+		);
+
+		let deadline = sp_io::offchain::timestamp()
+			.add(rt_offchain::Duration::from_millis(T::HttpTimeout::get()));
+		let mut result = Err(<Error<T>>::HttpFetchingError);
+		for url in urls {
+			if sp_io::offchain::timestamp() >= deadline {
+				break;
+			}
+			result = Self::fetch_remote_until(HttpMethod::Get, url, &[], deadline);
+			if result.is_ok() {
+				StorageValueRef::persistent(LAST_HOST_KEY).set(&url.as_bytes());
+				break;
+			}
+			debug::warn!("fetching from {} failed", url);
+		}
+		result
+	}
+
+	/// Send an HTTP request to `url` and return the response as vector of bytes. `body` is sent
+	///   as JSON with a POST, and ignored with a GET.
+	///   Failed requests are retried up to `T::MaxFetchRetries` times, waiting twice as long
+	///   before each retry, as long as the retry can start before the deadline.
+	fn fetch_remote(method: HttpMethod, url: &str, body: &[u8]) -> Result<Vec<u8>, Error<T>> {
+		// Keeping the offchain worker execution time reasonable, so limiting the call, retries
+		//   included, to be within `HttpTimeout`.
+		let deadline = sp_io::offchain::timestamp()
+			.add(rt_offchain::Duration::from_millis(T::HttpTimeout::get()));
+		Self::fetch_remote_until(method, url, body, deadline)
+	}
+
+	/// Like `fetch_remote`, but with the request and its retries ending by `deadline`.
+	fn fetch_remote_until(
+		method: HttpMethod,
+		url: &str,
+		body: &[u8],
+		deadline: rt_offchain::Timestamp,
+	) -> Result<Vec<u8>, Error<T>> {
+		let mut backoff = FETCH_BACKOFF_PERIOD;
+		let mut retries = 0;
+		loop {
+			let err = match Self::send_request(method, url, body, deadline) {
+				Ok(response) => return Ok(response),
+				Err(err) => err,
+			};
 
-		// Initiate an external HTTP GET request. This is using high-level wrappers from `sp_runtime`.
-		let request = rt_offchain::http::Request::get(HTTP_REMOTE_REQUEST);
+			let retry_at =
+				sp_io::offchain::timestamp().add(rt_offchain::Duration::from_millis(backoff));
+			if retries >= T::MaxFetchRetries::get() || retry_at >= deadline {
+				return Err(err);
+			}
+			retries += 1;
+			debug::warn!("retrying request to {} in {} ms", url, backoff);
+			sp_io::offchain::sleep_until(retry_at);
+			backoff = backoff.saturating_mul(2);
+		}
+	}
 
-		// Keeping the offchain worker execution time reasonable, so limiting the call to be within 3s.
-		let timeout = sp_io::offchain::timestamp()
-			.add(rt_offchain::Duration::from_millis(FETCH_TIMEOUT_PERIOD));
+	/// Send a single HTTP request to `url`, which has until `deadline` to respond.
+	fn send_request(
+		method: HttpMethod,
+		url: &str,
+		body: &[u8],
+		deadline: rt_offchain::Timestamp,
+	) -> Result<Vec<u8>, Error<T>> {
+		debug::info!("sending {:?} request to: {}", method, url);
+
+		// Initiate an external HTTP request. This is using high-level wrappers from `sp_runtime`.
+		let mut request = rt_offchain::http::Request::new(url);
+		if method == HttpMethod::Post {
+			// The body is written out in chunks, and here there is just the one
+			request = request
+				.method(rt_offchain::http::Method::Post)
+				.body(vec![body])
+				.add_header("Content-Type", "application/json");
+		}
 
 		// For github API request, we also need to specify `user-agent` in http request header.
 		//   See: https://developer.github.com/v3/#user-agent-required
+		// Like the URL, it can be changed on-chain, and was checked to be UTF-8 when it was set.
+		let user_agent = Self::user_agent();
+		let user_agent = match str::from_utf8(&user_agent) {
+			Ok(user_agent) if !user_agent.is_empty() => user_agent,
+			_ => HTTP_HEADER_USER_AGENT,
+		};
 		let pending = request
-			.add_header("User-Agent", HTTP_HEADER_USER_AGENT)
-			.deadline(timeout) // Setting the timeout time
+			.add_header("User-Agent", user_agent)
+			.deadline(deadline) // Setting the timeout time
 			.send() // Sending the request out by the host
 			.map_err(|_| <Error<T>>::HttpFetchingError)?;
 
@@ -406,7 +1114,7 @@ impl<T: Config> Module<T> {
 		// The returning value here is a `Result` of `Result`, so we are unwrapping it twice by two `?`
 		//   ref: https://substrate.dev/rustdocs/v3.0.0/sp_runtime/offchain/http/struct.PendingRequest.html#method.try_wait
 		let response = pending
-			.try_wait(timeout)
+			.try_wait(deadline)
 			.map_err(|_| <Error<T>>::HttpFetchingError)?
 			.map_err(|_| <Error<T>>::HttpFetchingError)?;
 
@@ -415,27 +1123,130 @@ impl<T: Config> Module<T> {
 			return Err(<Error<T>>::HttpFetchingError);
 		}
 
-		// Next we fully read the response body and collect it to a vector of bytes.
-		Ok(response.body().collect::<Vec<u8>>())
+		// Next we read the response body and collect it to a vector of bytes, reading one byte
+		//   past `MaxResponseBytes` to tell whether the body is larger.
+		let max = T::MaxResponseBytes::get() as usize;
+		let bytes = response
+			.body()
+			.take(max.saturating_add(1))
+			.collect::<Vec<u8>>();
+		if bytes.len() > max {
+			debug::error!("Response from {} is larger than {} bytes", url, max);
+			return Err(<Error<T>>::HttpFetchingError);
+		}
+		Ok(bytes)
+	}
+
+	/// The number an off-chain worker submits when none of `T::PriceSources` answers: the
+	///   block number.
+	fn number_to_submit(block_number: T::BlockNumber) -> u64 {
+		block_number.try_into().unwrap_or(0)
+	}
+
+	/// Fetch the price from every source in `T::PriceSources`, and return the median of the
+	///   prices fetched. Sources that fail are left out, so one bad source cannot skew the result.
+	///   Like github info, prices are fetched under `fetch_lock`, within one `HttpTimeout`, and
+	///   not at all while another off-chain worker holds the lock.
+	fn aggregate_price() -> Option<u64> {
+		let sources = T::PriceSources::get();
+		if sources.is_empty() {
+			return None;
+		}
+		let mut lock = Self::fetch_lock();
+		let _guard = lock.try_lock().ok()?;
+
+		let deadline = sp_io::offchain::timestamp()
+			.add(rt_offchain::Duration::from_millis(T::HttpTimeout::get()));
+		let mut prices = sources
+			.into_iter()
+			.filter_map(|source| {
+				Self::fetch_price(source, deadline)
+					.map_err(|e| debug::warn!("price source failed: {:?}", e))
+					.ok()
+			})
+			.collect::<Vec<_>>();
+		Self::median(&mut prices)
+	}
+
+	/// Fetch a price from `source`, a URL that responds with JSON like `{ "price": 100 }`, by
+	///   `deadline`.
+	fn fetch_price(source: &[u8], deadline: rt_offchain::Timestamp) -> Result<u64, Error<T>> {
+		let url = str::from_utf8(source).map_err(|_| <Error<T>>::HttpFetchingError)?;
+		let resp_bytes = Self::fetch_remote_until(HttpMethod::Get, url, &[], deadline)?;
+		let price_info: PriceInfo = serde_json::from_slice(&resp_bytes).map_err(|e| {
+			debug::error!(
+				"unexpected price from {}: {:?}: {}",
+				url,
+				str::from_utf8(&resp_bytes).unwrap_or("error"),
+				e
+			);
+			<Error<T>>::JsonParsingError
+		})?;
+		Ok(price_info.price)
+	}
+
+	/// The median of `values`, or `None` if there are none. With an even number of values, the
+	///   two in the middle are averaged, rounding down.
+	fn median(values: &mut [u64]) -> Option<u64> {
+		if values.is_empty() {
+			return None;
+		}
+		values.sort_unstable();
+		let mid = values.len() / 2;
+		if values.len() % 2 == 1 {
+			Some(values[mid])
+		} else {
+			// Summing in `u128` so two large values cannot overflow.
+			Some(((values[mid - 1] as u128 + values[mid] as u128) / 2) as u64)
+		}
 	}
 
 	fn offchain_signed_tx(block_number: T::BlockNumber) -> Result<(), Error<T>> {
+		// Submitting the current block number on-chain
+		let number = Self::number_to_submit(block_number);
+		if !Self::worth_submitting(number) {
+			return Ok(());
+		}
+
+		// Numbers are buffered in local storage until there are `SignedBatchSize` of them, so that
+		//   a single signature and dispatch covers them all. They stay there until they are sent.
+		let mut pending = StorageValueRef::persistent(PENDING_NUMBERS_KEY);
+		let mut numbers = pending.get::<Vec<u64>>().flatten().unwrap_or_default();
+		numbers.push(number);
+		Self::record_submitted(number);
+		// Only the newest `MaxNumbers` would be accepted anyway
+		let max = T::MaxNumbers::get() as usize;
+		if numbers.len() > max {
+			numbers.drain(..numbers.len() - max);
+		}
+		pending.set(&numbers);
+		if (numbers.len() as u32) < T::SignedBatchSize::get() {
+			return Ok(());
+		}
+
 		// We retrieve a signer and check if it is valid.
-		//   Since this pallet only has one key in the keystore. We use `any_account()1 to
-		//   retrieve it. If there are multiple keys and we want to pinpoint it, `with_filter()` can be chained,
+		//   `any_account()` would always sign with the same key. To spread transactions, and their
+		//   nonces, across all the keys in the keystore, we pinpoint the next key in turn by
+		//   chaining `with_filter()`,
 		//   ref: https://substrate.dev/rustdocs/v3.0.0/frame_system/offchain/struct.Signer.html
-		let signer = Signer::<T, T::AuthorityId>::any_account();
-
-		// Translating the current block number to number and submit it on-chain
-		let number: u64 = block_number.try_into().unwrap_or(0);
+		let public = Self::next_signing_key().ok_or_else(|| {
+			debug::error!("No local account available");
+			<Error<T>>::NoLocalAcctForSigning
+		})?;
+		let signer = Signer::<T, T::AuthorityId>::any_account().with_filter(vec![public]);
 
 		// `result` is in the type of `Option<(Account<T>, Result<(), ()>)>`. It is:
 		//   - `None`: no account is available for sending transaction
 		//   - `Some((account, Ok(())))`: transaction is successfully sent
 		//   - `Some((account, Err(())))`: error occured when sending the transaction
-		let result = signer.send_signed_transaction(|_acct|
+		let result = signer.send_signed_transaction(|acct| {
+			debug::info!("offchain_signed_tx: signing with {:?}", acct.id);
 			// This is the on-chain function
-			Call::submit_number_signed(number));
+			match numbers.as_slice() {
+				[number] => Call::submit_number_signed(*number),
+				_ => Call::submit_numbers_signed(numbers.clone()),
+			}
+		});
 
 		// Display error if the signed tx fails.
 		if let Some((acc, res)) = result {
@@ -444,6 +1255,7 @@ impl<T: Config> Module<T> {
 				return Err(<Error<T>>::OffchainSignedTxError);
 			}
 			// Transaction is sent successfully
+			pending.clear();
 			Ok(())
 		} else {
 			// The case result == `None`: no account is available for sending
@@ -452,23 +1264,154 @@ impl<T: Config> Module<T> {
 		}
 	}
 
+	/// The key the next signed transaction is sent with. Successive calls rotate through the
+	///   local keys of `KEY_TYPE`, keeping track of whose turn it is in local storage.
+	fn next_signing_key() -> Option<T::Public> {
+		let mut keys =
+			<T::AuthorityId as AppCrypto<T::Public, T::Signature>>::RuntimeAppPublic::all()
+				.into_iter()
+				.map(|key| {
+					<T::AuthorityId as AppCrypto<T::Public, T::Signature>>::GenericPublic::from(key)
+						.into()
+				})
+				.collect::<Vec<T::Public>>();
+		if keys.is_empty() {
+			return None;
+		}
+		// The keystore lists keys in no particular order, so they are sorted to take turns fairly
+		keys.sort();
+
+		let s_index = StorageValueRef::persistent(b"ocw-demo::signer-index");
+		let index = s_index.get::<u32>().flatten().unwrap_or(0);
+		s_index.set(&index.wrapping_add(1));
+		Some(keys.swap_remove(index as usize % keys.len()))
+	}
+
+	/// Whether `number` differs from the last number submitted from this node by at least
+	///   `T::SubmitThreshold`. Anything is worth submitting if nothing has been yet.
+	fn worth_submitting(number: u64) -> bool {
+		let last = StorageValueRef::persistent(LAST_SUBMITTED_KEY)
+			.get::<u64>()
+			.flatten();
+		match last {
+			Some(last) if number.max(last) - number.min(last) < T::SubmitThreshold::get() => {
+				debug::info!("Skipping submission of {}, too close to {}", number, last);
+				false
+			}
+			_ => true,
+		}
+	}
+
+	/// Remember `number` as the last number submitted from this node.
+	fn record_submitted(number: u64) {
+		StorageValueRef::persistent(LAST_SUBMITTED_KEY).set(&number);
+	}
+
 	fn offchain_unsigned_tx(block_number: T::BlockNumber) -> Result<(), Error<T>> {
-		let number: u64 = block_number.try_into().unwrap_or(0);
-		let call = Call::submit_number_unsigned(number);
-
-		// `submit_unsigned_transaction` returns a type of `Result<(), ()>`
-		//   ref: https://substrate.dev/rustdocs/v3.0.0/frame_system/offchain/struct.SubmitTransaction.html#method.submit_unsigned_transaction
-		SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into()).map_err(|_| {
-			debug::error!("Failed in offchain_unsigned_tx");
-			<Error<T>>::OffchainUnsignedTxError
-		})
+		let number = Self::number_to_submit(block_number);
+		if !Self::worth_submitting(number) {
+			return Ok(());
+		}
+
+		// The number is sent in a payload signed by a local key, so that other nodes can tell it
+		//   comes from an authority rather than anybody able to send an unsigned transaction.
+		let signer = Signer::<T, T::AuthorityId>::any_account();
+		if let Some((_, res)) = signer.send_unsigned_transaction(
+			|acct| Payload {
+				block_number,
+				number,
+				public: acct.public.clone(),
+			},
+			Call::submit_number_unsigned,
+		) {
+			res.map_err(|_| {
+				debug::error!("Failed in offchain_unsigned_tx");
+				<Error<T>>::OffchainUnsignedTxError
+			})?;
+			Self::record_submitted(number);
+			Ok(())
+		} else {
+			// The case of `None`: no account is available for sending
+			debug::error!("No local account available");
+			Err(<Error<T>>::NoLocalAcctForSigning)
+		}
+	}
+
+	/// Fetch the numbers `source` responds with, and submit them all in one unsigned
+	///   transaction, with a signed payload. Nothing is submitted if there are none.
+	fn offchain_unsigned_tx_batch(
+		block_number: T::BlockNumber,
+		source: &[u8],
+	) -> Result<(), Error<T>> {
+		let url = str::from_utf8(source).map_err(|_| <Error<T>>::HttpFetchingError)?;
+		let resp_bytes = Self::fetch_remote(HttpMethod::Get, url, &[])?;
+		let mut numbers = Self::parse_numbers(&resp_bytes)?;
+		if numbers.is_empty() {
+			debug::info!("No numbers to submit from {}", url);
+			return Ok(());
+		}
+		// Only the newest `MaxNumbers` would be kept anyway
+		let max = T::MaxNumbers::get() as usize;
+		if numbers.len() > max {
+			numbers.drain(..numbers.len() - max);
+		}
+
+		let signer = Signer::<T, T::AuthorityId>::any_account();
+		match signer.send_unsigned_transaction(
+			|acct| NumbersPayload {
+				block_number,
+				numbers: numbers.clone(),
+				public: acct.public.clone(),
+			},
+			Call::submit_numbers_unsigned,
+		) {
+			Some((_, Ok(()))) => Ok(()),
+			Some((_, Err(()))) => {
+				debug::error!("Failed in offchain_unsigned_tx_batch");
+				Err(<Error<T>>::OffchainUnsignedTxSignedPayloadError)
+			}
+			None => {
+				debug::error!("No local account available");
+				Err(<Error<T>>::NoLocalAcctForSigning)
+			}
+		}
+	}
+
+	/// Parse a JSON array of numbers, or of objects with a numeric `price`, like `[1, 2]` or
+	///   `[{ "price": 1 }, { "price": 2 }]`. The two kinds may be mixed.
+	fn parse_numbers(resp_bytes: &[u8]) -> Result<Vec<u64>, Error<T>> {
+		let entries: Vec<serde_json::Value> = serde_json::from_slice(resp_bytes).map_err(|e| {
+			debug::error!(
+				"unexpected numbers: {:?}: {}",
+				str::from_utf8(resp_bytes).unwrap_or("error"),
+				e
+			);
+			<Error<T>>::JsonParsingError
+		})?;
+
+		entries
+			.iter()
+			.map(|entry| {
+				let number = match entry {
+					serde_json::Value::Object(fields) => fields.get("price"),
+					_ => Some(entry),
+				};
+				number.and_then(|number| number.as_u64()).ok_or_else(|| {
+					debug::error!("unexpected number: {:?}", entry);
+					<Error<T>>::JsonParsingError
+				})
+			})
+			.collect()
 	}
 
 	fn offchain_unsigned_tx_signed_payload(block_number: T::BlockNumber) -> Result<(), Error<T>> {
 		// Retrieve the signer to sign the payload
 		let signer = Signer::<T, T::AuthorityId>::any_account();
 
-		let number: u64 = block_number.try_into().unwrap_or(0);
+		let number = Self::number_to_submit(block_number);
+		if !Self::worth_submitting(number) {
+			return Ok(());
+		}
 
 		// `send_unsigned_transaction` is returning a type of `Option<(Account<T>, Result<(), ()>)>`.
 		//   Similar to `send_signed_transaction`, they account for:
@@ -477,6 +1420,7 @@ impl<T: Config> Module<T> {
 		//   - `Some((account, Err(())))`: error occured when sending the transaction
 		if let Some((_, res)) = signer.send_unsigned_transaction(
 			|acct| Payload {
+				block_number,
 				number,
 				public: acct.public.clone(),
 			},
@@ -485,7 +1429,38 @@ impl<T: Config> Module<T> {
 			res.map_err(|_| {
 				debug::error!("Failed in offchain_unsigned_tx_signed_payload");
 				<Error<T>>::OffchainUnsignedTxSignedPayloadError
-			})
+			})?;
+			Self::record_submitted(number);
+			Ok(())
+		} else {
+			// The case of `None`: no account is available for sending
+			debug::error!("No local account available");
+			Err(<Error<T>>::NoLocalAcctForSigning)
+		}
+	}
+
+	/// Submit `price`, the median aggregated from `T::PriceSources`, in an unsigned transaction
+	///   with a signed payload, so that the chain can tell it apart from other numbers.
+	fn offchain_aggregated_value(block_number: T::BlockNumber, price: u64) -> Result<(), Error<T>> {
+		if !Self::worth_submitting(price) {
+			return Ok(());
+		}
+
+		let signer = Signer::<T, T::AuthorityId>::any_account();
+		if let Some((_, res)) = signer.send_unsigned_transaction(
+			|acct| Payload {
+				block_number,
+				number: price,
+				public: acct.public.clone(),
+			},
+			Call::submit_aggregated_value,
+		) {
+			res.map_err(|_| {
+				debug::error!("Failed in offchain_aggregated_value");
+				<Error<T>>::OffchainUnsignedTxSignedPayloadError
+			})?;
+			Self::record_submitted(price);
+			Ok(())
 		} else {
 			// The case of `None`: no account is available for sending
 			debug::error!("No local account available");
@@ -508,13 +1483,48 @@ impl<T: Config> frame_support::unsigned::ValidateUnsigned for Module<T> {
 		};
 
 		match call {
-			Call::submit_number_unsigned(_number) => valid_tx(b"submit_number_unsigned".to_vec()),
+			Call::submit_number_unsigned(ref payload, ref signature) => {
+				Self::validate_signed_number(b"submit_number_unsigned", payload, signature)
+			}
+
+			Call::submit_numbers_unsigned(ref payload, ref signature) => {
+				// Batches the call would reject are turned away before the signature is checked
+				if payload.numbers.len() > T::MaxNumbers::get() as usize {
+					return InvalidTransaction::ExhaustsResources.into();
+				}
+				Self::check_authority_signature(payload, signature)?;
+				Self::validate_number(
+					b"submit_numbers_unsigned",
+					payload.block_number,
+					&payload.numbers,
+				)
+			}
 
 			Call::submit_number_unsigned_with_signed_payload(ref payload, ref signature) => {
-				if !SignedPayload::<T>::verify::<T::AuthorityId>(payload, signature.clone()) {
-					return InvalidTransaction::BadProof.into();
+				Self::validate_signed_number(
+					b"submit_number_unsigned_with_signed_payload",
+					payload,
+					signature,
+				)
+			}
+
+			Call::submit_aggregated_value(ref payload, ref signature) => {
+				Self::validate_signed_number(b"submit_aggregated_value", payload, signature)
+			}
+
+			Call::record_fetch_failure(ref payload, ref signature) => {
+				Self::check_authority_signature(payload, signature)?;
+				// A failure is only reported in the block it happened in, and is included in
+				//   the next one at the latest
+				let now = <frame_system::Module<T>>::block_number();
+				if payload.block_number > now {
+					return InvalidTransaction::Future.into();
 				}
-				valid_tx(b"submit_number_unsigned_with_signed_payload".to_vec())
+				if now - payload.block_number > One::one() {
+					return InvalidTransaction::Stale.into();
+				}
+				// A failure is recorded at most once per block
+				valid_tx((b"record_fetch_failure", payload.block_number).encode())
 			}
 
 			_ => InvalidTransaction::Call.into(),
@@ -522,6 +1532,61 @@ impl<T: Config> frame_support::unsigned::ValidateUnsigned for Module<T> {
 	}
 }
 
+impl<T: Config> Module<T> {
+	/// Validate a number submitted in a signed payload. The payload has to be signed by one of
+	///   `Authorities`, and is then validated like any other number.
+	fn validate_signed_number(
+		tag: &[u8],
+		payload: &Payload<T::Public, T::BlockNumber>,
+		signature: &T::Signature,
+	) -> TransactionValidity {
+		Self::check_authority_signature(payload, signature)?;
+		Self::validate_number(tag, payload.block_number, payload.number)
+	}
+
+	/// Check that `payload` is signed, with `signature`, by one of `Authorities`.
+	fn check_authority_signature<P: SignedPayload<T>>(
+		payload: &P,
+		signature: &T::Signature,
+	) -> Result<(), TransactionValidityError> {
+		if !payload.verify::<T::AuthorityId>(signature.clone()) {
+			return Err(InvalidTransaction::BadProof.into());
+		}
+		let signer = payload.public().into_account();
+		if !Self::authorities().contains(&signer) {
+			debug::warn!("Payload signed by unknown authority: {:?}", signer);
+			return Err(InvalidTransaction::BadProof.into());
+		}
+		Ok(())
+	}
+
+	/// Validate a number, or several, submitted by an off-chain worker in `block_number`.
+	///   Numbers are only accepted for `UNSIGNED_TXS_MAX_AGE` blocks, and the older they are, the
+	///   lower their priority. The same numbers can only be submitted once per block.
+	fn validate_number(
+		tag: &[u8],
+		block_number: T::BlockNumber,
+		number: impl Encode,
+	) -> TransactionValidity {
+		let now = <frame_system::Module<T>>::block_number();
+		if block_number > now {
+			return InvalidTransaction::Future.into();
+		}
+		let age: u64 = (now - block_number).unique_saturated_into();
+		let max_age = u64::from(UNSIGNED_TXS_MAX_AGE);
+		if age > max_age {
+			return InvalidTransaction::Stale.into();
+		}
+
+		ValidTransaction::with_tag_prefix("ocw-demo")
+			.priority(UNSIGNED_TXS_PRIORITY.saturating_sub(age))
+			.and_provides((tag, block_number, number))
+			.longevity(max_age + 1 - age)
+			.propagate(true)
+			.build()
+	}
+}
+
 impl<T: Config> rt_offchain::storage_lock::BlockNumberProvider for Module<T> {
 	type BlockNumber = T::BlockNumber;
 	fn current_block_number() -> Self::BlockNumber {
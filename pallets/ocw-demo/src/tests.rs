@@ -1,26 +1,34 @@
 use crate::{self as ocw_demo, *};
-use frame_support::{assert_ok, construct_runtime, parameter_types};
+use frame_support::{
+	assert_noop, assert_ok, construct_runtime, parameter_types,
+	traits::OffchainWorker,
+	unsigned::ValidateUnsigned,
+	weights::{constants::RocksDbWeight, GetDispatchInfo},
+};
 use frame_system::{limits, mocking};
 use parity_scale_codec::alloc::sync::Arc;
 use parking_lot::RwLock;
 use sp_core::{
 	offchain::{
+		self,
 		testing::{self, OffchainState, PoolState},
-		OffchainExt, TransactionPoolExt,
+		HttpError, HttpRequestId, HttpRequestStatus, OffchainExt, OpaqueNetworkState, StorageKind,
+		Timestamp, TransactionPoolExt,
 	},
-	sr25519::{self, Signature},
-	H256,
+	sr25519, OpaquePeerId, H256,
 };
 use sp_io::TestExternalities;
 use sp_keystore::{testing::KeyStore, KeystoreExt, SyncCryptoStore};
 use sp_runtime::{
 	testing::{Header, TestXt},
 	traits::{BlakeTwo256, Extrinsic as ExtrinsicT, IdentifyAccount, IdentityLookup, Verify},
+	DispatchError, MultiSignature, MultiSigner,
 };
 
 type Extrinsic = TestXt<Call, ()>;
 type UncheckedExtrinsic = mocking::MockUncheckedExtrinsic<TestRuntime>;
 type Block = mocking::MockBlock<TestRuntime>;
+type Signature = MultiSignature;
 type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
 
 // For testing the module, we construct a mock runtime.
@@ -31,7 +39,7 @@ construct_runtime!(
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system::{Module, Call, Config, Storage, Event<T>},
-		OcwDemo: ocw_demo::{Module, Call, Storage, Event<T>, ValidateUnsigned},
+		OcwDemo: ocw_demo::{Module, Call, Storage, Config<T>, Event<T>, ValidateUnsigned},
 	}
 );
 
@@ -43,14 +51,14 @@ impl frame_system::Config for TestRuntime {
 	type BaseCallFilter = ();
 	type BlockWeights = ();
 	type BlockLength = ();
-	type DbWeight = ();
+	type DbWeight = RocksDbWeight;
 	type Origin = Origin;
 	type Call = Call;
 	type Index = u64;
 	type BlockNumber = u64;
 	type Hash = H256;
 	type Hashing = BlakeTwo256;
-	type AccountId = sr25519::Public;
+	type AccountId = AccountId;
 	type Lookup = IdentityLookup<Self::AccountId>;
 	type Header = Header;
 	type Event = Event;
@@ -66,12 +74,46 @@ impl frame_system::Config for TestRuntime {
 
 parameter_types! {
 	pub const UnsignedPriority: u64 = 100;
+	pub static RoundAverage: bool = false;
+	pub static MaxFetchRetries: u32 = 2;
+	pub static PriceSources: Vec<&'static [u8]> = Vec::new();
+	pub static MaxNumbers: u32 = 10;
+	pub static FetchInterval: u64 = 4;
+	pub static CacheTtl: u64 = 60_000;
+	pub static HttpTimeout: u64 = 3_000;
+	pub static DedupNumbers: bool = false;
+	pub static SubmitThreshold: u64 = 0;
+	pub static FailureThreshold: u32 = 3;
+	pub static BatchSource: Option<&'static [u8]> = None;
+	pub static MaxLoginLength: u32 = 39;
+	pub static MaxBlogLength: u32 = 256;
+	pub static MaxResponseBytes: u32 = 1024;
+	pub static EmaAlpha: Perbill = Perbill::from_percent(50);
+	pub static SignedBatchSize: u32 = 1;
+	// The accounts transactions were signed for, in the order they were created
+	pub static SignedFor: Vec<AccountId> = Vec::new();
 }
 
 impl Config for TestRuntime {
 	type AuthorityId = crypto::TestAuthId;
 	type Call = Call;
 	type Event = Event;
+	type RoundAverage = RoundAverage;
+	type MaxFetchRetries = MaxFetchRetries;
+	type PriceSources = PriceSources;
+	type MaxNumbers = MaxNumbers;
+	type FetchInterval = FetchInterval;
+	type CacheTtl = CacheTtl;
+	type HttpTimeout = HttpTimeout;
+	type DedupNumbers = DedupNumbers;
+	type SubmitThreshold = SubmitThreshold;
+	type FailureThreshold = FailureThreshold;
+	type BatchSource = BatchSource;
+	type MaxLoginLength = MaxLoginLength;
+	type MaxBlogLength = MaxBlogLength;
+	type MaxResponseBytes = MaxResponseBytes;
+	type EmaAlpha = EmaAlpha;
+	type SignedBatchSize = SignedBatchSize;
 }
 
 impl frame_system::offchain::SigningTypes for TestRuntime {
@@ -94,13 +136,109 @@ where
 	fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
 		call: Call,
 		_public: <Signature as Verify>::Signer,
-		_account: AccountId,
+		account: AccountId,
 		nonce: u64,
 	) -> Option<(Call, <Extrinsic as ExtrinsicT>::SignaturePayload)> {
+		let mut signed_for = SignedFor::get();
+		signed_for.push(account);
+		SignedFor::set(signed_for);
 		Some((call, (nonce, ())))
 	}
 }
 
+/// The public key of an sr25519 key in the keystore
+fn local_public() -> MultiSigner {
+	let key = <crypto::Public as RuntimeAppPublic>::all().pop().unwrap();
+	<crypto::TestAuthId as AppCrypto<MultiSigner, MultiSignature>>::GenericPublic::from(key).into()
+}
+
+/// A `submit_number_unsigned` call with its payload signed by an sr25519 key in the keystore
+fn signed_number_call(block_number: u64, number: u64) -> ocw_demo::Call<TestRuntime> {
+	let payload = Payload {
+		block_number,
+		number,
+		public: local_public(),
+	};
+	let signature = SignedPayload::<TestRuntime>::sign::<crypto::TestAuthId>(&payload).unwrap();
+	ocw_demo::Call::submit_number_unsigned(payload, signature)
+}
+
+/// A `submit_numbers_unsigned` call with its payload signed by an sr25519 key in the keystore
+fn numbers_call(block_number: u64, numbers: Vec<u64>) -> ocw_demo::Call<TestRuntime> {
+	let payload = NumbersPayload {
+		block_number,
+		numbers,
+		public: local_public(),
+	};
+	let signature = SignedPayload::<TestRuntime>::sign::<crypto::TestAuthId>(&payload).unwrap();
+	ocw_demo::Call::submit_numbers_unsigned(payload, signature)
+}
+
+/// Dispatch `submit_numbers_unsigned` with `numbers`, signed by an sr25519 key in the keystore
+fn submit_numbers_unsigned(numbers: Vec<u64>) -> DispatchResult {
+	match numbers_call(1, numbers) {
+		ocw_demo::Call::submit_numbers_unsigned(payload, signature) => {
+			OcwDemo::submit_numbers_unsigned(Origin::none(), payload, signature)
+		}
+		_ => unreachable!(),
+	}
+}
+
+/// Dispatch `submit_gh_info` with `gh_info`, signed by the account of the key in the keystore
+fn submit_gh_info(gh_info: GithubInfo) -> DispatchResult {
+	OcwDemo::submit_gh_info(Origin::signed(local_public().into_account()), gh_info)
+}
+
+/// A `record_fetch_failure` call with its payload signed by an sr25519 key in the keystore
+fn fetch_failure_call(block_number: u64) -> ocw_demo::Call<TestRuntime> {
+	let payload = FetchFailurePayload {
+		block_number,
+		public: local_public(),
+	};
+	let signature = SignedPayload::<TestRuntime>::sign::<crypto::TestAuthId>(&payload).unwrap();
+	ocw_demo::Call::record_fetch_failure(payload, signature)
+}
+
+/// Dispatch `record_fetch_failure` for `block_number`, signed by an sr25519 key in the keystore
+fn record_fetch_failure(block_number: u64) -> DispatchResult {
+	match fetch_failure_call(block_number) {
+		ocw_demo::Call::record_fetch_failure(payload, signature) => {
+			OcwDemo::record_fetch_failure(Origin::none(), payload, signature)
+		}
+		_ => unreachable!(),
+	}
+}
+
+/// Dispatch `submit_number_unsigned` with `number`. Only `validate_unsigned` checks the payload's
+/// signature, so a blank one will do.
+fn submit_number_unsigned(number: u64) -> DispatchResult {
+	let payload = Payload {
+		block_number: 1,
+		number,
+		public: sr25519::Public::default().into(),
+	};
+	OcwDemo::submit_number_unsigned(
+		Origin::none(),
+		payload,
+		sr25519::Signature::default().into(),
+	)
+}
+
+/// Expect the off-chain worker to fetch github info from the default URL, and answer with
+/// `response`
+fn expect_gh_info_request(offchain_state: &Arc<RwLock<OffchainState>>, response: &[u8]) {
+	offchain_state
+		.write()
+		.expect_request(testing::PendingRequest {
+			method: "GET".into(),
+			uri: HTTP_REMOTE_REQUEST.into(),
+			headers: vec![("User-Agent".into(), HTTP_HEADER_USER_AGENT.into())],
+			response: Some(response.to_vec()),
+			sent: true,
+			..Default::default()
+		});
+}
+
 struct ExternalityBuilder;
 
 impl ExternalityBuilder {
@@ -112,16 +250,36 @@ impl ExternalityBuilder {
 		const PHRASE: &str =
 			"expire stage crawl shell boss any story swamp skull yellow bamboo copy";
 
-		let (offchain, offchain_state) = testing::TestOffchainExt::new();
-		let (pool, pool_state) = testing::TestTransactionPoolExt::new();
 		let keystore = KeyStore::new();
 		keystore
 			.sr25519_generate_new(KEY_TYPE, Some(&format!("{}/hunter1", PHRASE)))
 			.unwrap();
+		Self::build_with_keystore(keystore)
+	}
 
-		let storage = frame_system::GenesisConfig::default()
+	/// Like `build`, but with the keys in `keystore` instead of the default sr25519 key. Every
+	/// sr25519 key in the keystore is an authority.
+	pub fn build_with_keystore(
+		keystore: KeyStore,
+	) -> (
+		TestExternalities,
+		Arc<RwLock<PoolState>>,
+		Arc<RwLock<OffchainState>>,
+	) {
+		let (offchain, offchain_state) = testing::TestOffchainExt::new();
+		let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+
+		let mut storage = frame_system::GenesisConfig::default()
 			.build_storage::<TestRuntime>()
 			.unwrap();
+		let authorities = keystore
+			.sr25519_public_keys(KEY_TYPE)
+			.into_iter()
+			.map(|key| MultiSigner::from(key).into_account())
+			.collect();
+		ocw_demo::GenesisConfig::<TestRuntime> { authorities }
+			.assimilate_storage(&mut storage)
+			.unwrap();
 
 		let mut t = TestExternalities::from(storage);
 		t.register_extension(OffchainExt::new(offchain));
@@ -132,6 +290,157 @@ impl ExternalityBuilder {
 	}
 }
 
+/// Off-chain externalities that answer the first `failures` HTTP requests with a server error,
+/// and take `latency` milliseconds to answer each, since `TestOffchainExt` always answers with 200
+/// right away
+struct FlakyOffchainExt {
+	inner: testing::TestOffchainExt,
+	failures: u32,
+	latency: u64,
+}
+
+impl FlakyOffchainExt {
+	fn new(failures: u32) -> (Self, Arc<RwLock<OffchainState>>) {
+		let (inner, state) = testing::TestOffchainExt::new();
+		(
+			Self {
+				inner,
+				failures,
+				latency: 0,
+			},
+			state,
+		)
+	}
+
+	fn slow(latency: u64) -> (Self, Arc<RwLock<OffchainState>>) {
+		let (inner, state) = testing::TestOffchainExt::new();
+		(
+			Self {
+				inner,
+				failures: 0,
+				latency,
+			},
+			state,
+		)
+	}
+}
+
+impl offchain::Externalities for FlakyOffchainExt {
+	fn is_validator(&self) -> bool {
+		self.inner.is_validator()
+	}
+
+	fn network_state(&self) -> Result<OpaqueNetworkState, ()> {
+		self.inner.network_state()
+	}
+
+	fn timestamp(&mut self) -> Timestamp {
+		self.inner.timestamp()
+	}
+
+	fn sleep_until(&mut self, deadline: Timestamp) {
+		self.inner.sleep_until(deadline)
+	}
+
+	fn random_seed(&mut self) -> [u8; 32] {
+		self.inner.random_seed()
+	}
+
+	fn local_storage_set(&mut self, kind: StorageKind, key: &[u8], value: &[u8]) {
+		self.inner.local_storage_set(kind, key, value)
+	}
+
+	fn local_storage_clear(&mut self, kind: StorageKind, key: &[u8]) {
+		self.inner.local_storage_clear(kind, key)
+	}
+
+	fn local_storage_compare_and_set(
+		&mut self,
+		kind: StorageKind,
+		key: &[u8],
+		old_value: Option<&[u8]>,
+		new_value: &[u8],
+	) -> bool {
+		self.inner
+			.local_storage_compare_and_set(kind, key, old_value, new_value)
+	}
+
+	fn local_storage_get(&mut self, kind: StorageKind, key: &[u8]) -> Option<Vec<u8>> {
+		self.inner.local_storage_get(kind, key)
+	}
+
+	fn http_request_start(
+		&mut self,
+		method: &str,
+		uri: &str,
+		meta: &[u8],
+	) -> Result<HttpRequestId, ()> {
+		self.inner.http_request_start(method, uri, meta)
+	}
+
+	fn http_request_add_header(
+		&mut self,
+		request_id: HttpRequestId,
+		name: &str,
+		value: &str,
+	) -> Result<(), ()> {
+		self.inner.http_request_add_header(request_id, name, value)
+	}
+
+	fn http_request_write_body(
+		&mut self,
+		request_id: HttpRequestId,
+		chunk: &[u8],
+		deadline: Option<Timestamp>,
+	) -> Result<(), HttpError> {
+		self.inner
+			.http_request_write_body(request_id, chunk, deadline)
+	}
+
+	fn http_response_wait(
+		&mut self,
+		ids: &[HttpRequestId],
+		deadline: Option<Timestamp>,
+	) -> Vec<HttpRequestStatus> {
+		let answered_at = self
+			.inner
+			.timestamp()
+			.add(rt_offchain::Duration::from_millis(self.latency));
+		if let Some(deadline) = deadline.filter(|deadline| *deadline < answered_at) {
+			self.inner.sleep_until(deadline);
+			return vec![HttpRequestStatus::DeadlineReached; ids.len()];
+		}
+		self.inner.sleep_until(answered_at);
+
+		let mut statuses = self.inner.http_response_wait(ids, deadline);
+		for status in statuses.iter_mut() {
+			if self.failures > 0 {
+				self.failures -= 1;
+				*status = HttpRequestStatus::Finished(500);
+			}
+		}
+		statuses
+	}
+
+	fn http_response_headers(&mut self, request_id: HttpRequestId) -> Vec<(Vec<u8>, Vec<u8>)> {
+		self.inner.http_response_headers(request_id)
+	}
+
+	fn http_response_read_body(
+		&mut self,
+		request_id: HttpRequestId,
+		buffer: &mut [u8],
+		deadline: Option<Timestamp>,
+	) -> Result<usize, HttpError> {
+		self.inner
+			.http_response_read_body(request_id, buffer, deadline)
+	}
+
+	fn set_authorized_nodes(&mut self, nodes: Vec<OpaquePeerId>, authorized_only: bool) {
+		self.inner.set_authorized_nodes(nodes, authorized_only)
+	}
+}
+
 #[test]
 fn submit_number_signed_works() {
 	let (mut t, _, _) = ExternalityBuilder::build();
@@ -140,17 +449,24 @@ fn submit_number_signed_works() {
 		// call submit_number_signed
 		let num = 32;
 		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
-		assert_ok!(OcwDemo::submit_number_signed(Origin::signed(acct), num));
+		assert_ok!(OcwDemo::submit_number_signed(
+			Origin::signed(acct.clone()),
+			num
+		));
 		// A number is inserted to <Numbers> vec
 		assert_eq!(<Numbers>::get(), vec![num]);
 		// An event is emitted
 		assert!(System::events()
 			.iter()
-			.any(|er| er.event == Event::ocw_demo(RawEvent::NewNumber(Some(acct), num))));
+			.any(|er| er.event
+				== Event::ocw_demo(RawEvent::NewNumber(Some(acct.clone()), num, false))));
 
 		// Insert another number
 		let num2 = num * 2;
-		assert_ok!(OcwDemo::submit_number_signed(Origin::signed(acct), num2));
+		assert_ok!(OcwDemo::submit_number_signed(
+			Origin::signed(acct.clone()),
+			num2
+		));
 		// A number is inserted to <Numbers> vec
 		assert_eq!(<Numbers>::get(), vec![num, num2]);
 	});
@@ -190,9 +506,1791 @@ fn test_offchain_unsigned_tx() {
 		assert!(pool_state.read().transactions.is_empty());
 		let tx = Extrinsic::decode(&mut &*tx).unwrap();
 		assert_eq!(tx.signature, None);
+		assert!(matches!(
+			tx.call,
+			Call::OcwDemo(ocw_demo::Call::submit_number_unsigned(
+				Payload {
+					block_number: 32,
+					number: 32,
+					..
+				},
+				_
+			))
+		));
+	});
+}
+
+#[test]
+fn average_truncates_by_default() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		RoundAverage::set(false);
+		assert_eq!(OcwDemo::average(&VecDeque::new()), None);
+		assert_eq!(OcwDemo::average(&vec![1, 2].into()), Some(1));
+		assert_eq!(OcwDemo::average(&vec![1, 2, 2].into()), Some(1));
+	});
+}
+
+#[test]
+fn average_rounds_half_up_when_configured() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		RoundAverage::set(true);
+		assert_eq!(OcwDemo::average(&VecDeque::new()), None);
+		// 1.5 rounds up to 2
+		assert_eq!(OcwDemo::average(&vec![1, 2].into()), Some(2));
+		// 1.67 rounds up to 2
+		assert_eq!(OcwDemo::average(&vec![1, 2, 2].into()), Some(2));
+		// 1.33 rounds down to 1
+		assert_eq!(OcwDemo::average(&vec![1, 1, 2].into()), Some(1));
+	});
+}
+
+#[test]
+fn shrinking_number_buffer_keeps_newest_numbers() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		assert_ok!(OcwDemo::set_number_buffer_length(Origin::root(), 5));
+		for num in 1..=6 {
+			assert_ok!(OcwDemo::submit_number_signed(
+				Origin::signed(acct.clone()),
+				num
+			));
+		}
+		// The buffer is full, so the oldest number was replaced
+		assert_eq!(<Numbers>::get(), vec![2, 3, 4, 5, 6]);
+
+		assert_ok!(OcwDemo::set_number_buffer_length(Origin::root(), 3));
+		assert_eq!(<Numbers>::get(), vec![4, 5, 6]);
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::NumberBufferLengthSet(3))));
+
+		// New numbers keep to the new bound
+		assert_ok!(OcwDemo::submit_number_signed(
+			Origin::signed(acct.clone()),
+			7
+		));
+		assert_eq!(<Numbers>::get(), vec![5, 6, 7]);
+	});
+}
+
+#[test]
+fn numbers_never_exceed_max_numbers() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		MaxNumbers::set(3);
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		assert_eq!(OcwDemo::buffer_length(), 3);
+		for num in 1..=5 {
+			assert_ok!(OcwDemo::submit_number_signed(
+				Origin::signed(acct.clone()),
+				num
+			));
+			assert!(<Numbers>::get().len() <= 3);
+		}
+		assert_eq!(<Numbers>::get(), vec![3, 4, 5]);
+
+		// Lowering the bound applies even to a buffer length set before
+		MaxNumbers::set(10);
+		assert_ok!(OcwDemo::set_number_buffer_length(Origin::root(), 5));
+		MaxNumbers::set(2);
+		assert_ok!(OcwDemo::submit_number_signed(
+			Origin::signed(acct.clone()),
+			6
+		));
+		assert_eq!(<Numbers>::get(), vec![5, 6]);
+		MaxNumbers::set(10);
+	});
+}
+
+#[test]
+fn set_number_buffer_length_handles_basic_errors() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		assert_noop!(
+			OcwDemo::set_number_buffer_length(Origin::signed(acct.clone()), 3),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			OcwDemo::set_number_buffer_length(Origin::root(), 0),
+			Error::<TestRuntime>::ZeroBufferLength
+		);
+		assert_noop!(
+			OcwDemo::set_number_buffer_length(Origin::root(), MaxNumbers::get() + 1),
+			Error::<TestRuntime>::BufferLengthTooLarge
+		);
+	});
+}
+
+#[test]
+fn fetch_from_remote_uses_configured_url() {
+	let (mut t, _, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let url = "https://example.com/orgs/substrate-developer-hub";
+		assert_ok!(OcwDemo::set_remote_url(
+			Origin::root(),
+			url.as_bytes().to_vec()
+		));
+		assert_eq!(OcwDemo::remote_url(), url.as_bytes().to_vec());
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::RemoteUrlSet(url.as_bytes().to_vec()))));
+
+		offchain_state
+			.write()
+			.expect_request(testing::PendingRequest {
+				method: "GET".into(),
+				uri: url.into(),
+				headers: vec![("User-Agent".into(), HTTP_HEADER_USER_AGENT.into())],
+				response: Some(b"{}".to_vec()),
+				sent: true,
+				..Default::default()
+			});
+		assert_eq!(OcwDemo::fetch_from_remote().unwrap(), b"{}".to_vec());
+
+		// Clearing the URL falls back to the default
+		assert_ok!(OcwDemo::set_remote_url(Origin::root(), Vec::new()));
+		expect_gh_info_request(&offchain_state, b"{}");
+		assert_eq!(OcwDemo::fetch_from_remote().unwrap(), b"{}".to_vec());
+	});
+}
+
+#[test]
+fn set_remote_url_handles_basic_errors() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		assert_noop!(
+			OcwDemo::set_remote_url(
+				Origin::signed(acct.clone()),
+				b"https://example.com".to_vec()
+			),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			OcwDemo::set_remote_url(Origin::root(), vec![0xff, 0xfe]),
+			Error::<TestRuntime>::InvalidRemoteUrl
+		);
+	});
+}
+
+#[test]
+fn fetch_remote_posts_json_body() {
+	let (mut t, _, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let url = "https://example.com/numbers";
+		let body = br#"{"number":32}"#;
+		offchain_state
+			.write()
+			.expect_request(testing::PendingRequest {
+				method: "POST".into(),
+				uri: url.into(),
+				headers: vec![
+					("Content-Type".into(), "application/json".into()),
+					("User-Agent".into(), HTTP_HEADER_USER_AGENT.into()),
+				],
+				body: body.to_vec(),
+				response: Some(br#"{"ok":true}"#.to_vec()),
+				sent: true,
+				..Default::default()
+			});
+		assert_eq!(
+			OcwDemo::fetch_remote(HttpMethod::Post, url, body).unwrap(),
+			br#"{"ok":true}"#.to_vec()
+		);
+	});
+}
+
+#[test]
+fn fetch_github_info_skips_while_another_run_holds_the_lock() {
+	let (mut t, _, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		// Another off-chain worker run is in the middle of fetching
+		let mut lock = StorageLock::<BlockAndTime<OcwDemo>>::with_block_and_time_deadline(
+			b"ocw-demo::lock",
+			LOCK_BLOCK_EXPIRATION,
+			rt_offchain::Duration::from_millis(HttpTimeout::get() + LOCK_TIMEOUT_MARGIN),
+		);
+		let guard = lock
+			.try_lock()
+			.unwrap_or_else(|_| panic!("nobody else holds the lock"));
+
+		// No response is set up, so a request here would fail the fetch
+		assert!(OcwDemo::fetch_github_info().is_ok());
+		assert!(StorageValueRef::persistent(b"ocw-demo::gh-info")
+			.get::<GithubInfo>()
+			.is_none());
+		drop(guard);
+
+		// Once the lock is released, exactly one run fetches and caches the info
+		expect_gh_info_request(
+			&offchain_state,
+			br#"{"login":"substrate-developer-hub","blog":"","public_repos":1}"#,
+		);
+		assert!(OcwDemo::fetch_github_info().is_ok());
+		let gh_info = StorageValueRef::persistent(b"ocw-demo::gh-info")
+			.get::<GithubInfo>()
+			.unwrap()
+			.unwrap();
+		assert_eq!(gh_info.login, b"substrate-developer-hub".to_vec());
+
+		// Later runs use the cached info without another request
+		assert!(OcwDemo::fetch_github_info().is_ok());
+	});
+}
+
+#[test]
+fn fetch_remote_retries_failed_requests() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+	// The server fails once, then answers
+	let (offchain, offchain_state) = FlakyOffchainExt::new(1);
+	t.register_extension(OffchainExt::new(offchain));
+
+	t.execute_with(|| {
+		let request = |response: &[u8]| testing::PendingRequest {
+			method: "GET".into(),
+			uri: HTTP_REMOTE_REQUEST.into(),
+			headers: vec![("User-Agent".into(), HTTP_HEADER_USER_AGENT.into())],
+			response: Some(response.to_vec()),
+			sent: true,
+			..Default::default()
+		};
+
+		offchain_state.write().expect_request(request(b""));
+		offchain_state.write().expect_request(request(b"{}"));
+		let start = sp_io::offchain::timestamp();
+		assert_eq!(OcwDemo::fetch_from_remote().unwrap(), b"{}".to_vec());
+		// The worker backed off before retrying
+		assert_eq!(
+			sp_io::offchain::timestamp(),
+			start.add(rt_offchain::Duration::from_millis(FETCH_BACKOFF_PERIOD))
+		);
+	});
+}
+
+#[test]
+fn fetch_remote_gives_up_after_max_retries() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+	let (offchain, offchain_state) = FlakyOffchainExt::new(MaxFetchRetries::get() + 1);
+	t.register_extension(OffchainExt::new(offchain));
+
+	t.execute_with(|| {
+		for _ in 0..=MaxFetchRetries::get() {
+			expect_gh_info_request(&offchain_state, b"");
+		}
+		assert!(matches!(
+			OcwDemo::fetch_from_remote(),
+			Err(Error::<TestRuntime>::HttpFetchingError)
+		));
+	});
+}
+
+#[test]
+fn offchain_worker_submits_median_price() {
+	let (mut t, pool_state, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		PriceSources::set(vec![
+			b"https://a.example.com/price",
+			b"https://b.example.com/price",
+			b"https://c.example.com/price",
+		]);
+		for (uri, response) in &[
+			("https://a.example.com/price", &br#"{"price":100}"#[..]),
+			// This source answers with something that is not a price
+			("https://b.example.com/price", &b"<html></html>"[..]),
+			("https://c.example.com/price", &br#"{"price":300}"#[..]),
+		] {
+			offchain_state
+				.write()
+				.expect_request(testing::PendingRequest {
+					method: "GET".into(),
+					uri: (*uri).into(),
+					headers: vec![("User-Agent".into(), HTTP_HEADER_USER_AGENT.into())],
+					response: Some(response.to_vec()),
+					sent: true,
+					..Default::default()
+				});
+		}
+
+		// Prices are only fetched every `FetchInterval` blocks
+		System::set_block_number(2);
+		OcwDemo::offchain_worker(2);
+		assert!(offchain_state.read().requests.is_empty());
+		let _ = pool_state.write().transactions.pop().unwrap();
+
+		// Github info fetched in the same block is still cached, so only prices are fetched
+		StorageValueRef::persistent(b"ocw-demo::gh-info").set(&GithubInfo::default());
+		StorageValueRef::persistent(b"ocw-demo::gh-info-fetched-at")
+			.set(&sp_io::offchain::timestamp().unix_millis());
+
+		// The median of the two prices fetched is submitted instead of the block number
+		System::set_block_number(4);
+		OcwDemo::offchain_worker(4);
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		let call = match tx.call {
+			Call::OcwDemo(call) => call,
+			_ => panic!("unexpected call"),
+		};
+		assert!(matches!(
+			call,
+			ocw_demo::Call::submit_aggregated_value(
+				Payload {
+					block_number: 4,
+					number: 200,
+					..
+				},
+				_
+			)
+		));
+		assert!(OcwDemo::validate_unsigned(TransactionSource::Local, &call).is_ok());
+
+		// Plain numbers leave the aggregated value alone
+		assert_ok!(submit_number_unsigned(5));
+		assert_eq!(OcwDemo::aggregated_value(), None);
+
+		match call {
+			ocw_demo::Call::submit_aggregated_value(payload, signature) => {
+				assert_ok!(OcwDemo::submit_aggregated_value(
+					Origin::none(),
+					payload,
+					signature
+				));
+			}
+			_ => unreachable!(),
+		}
+		assert_eq!(OcwDemo::aggregated_value(), Some(200));
+		assert_eq!(<Numbers>::get(), vec![5, 200]);
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::AggregatedValueUpdated(200))));
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::NewNumber(None, 200, false))));
+		PriceSources::set(Vec::new());
+	});
+}
+
+#[test]
+fn median_works() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		assert_eq!(OcwDemo::median(&mut []), None);
+		assert_eq!(OcwDemo::median(&mut [3, 1, 2]), Some(2));
+		assert_eq!(OcwDemo::median(&mut [4, 1, 2, 3]), Some(2));
+		assert_eq!(OcwDemo::median(&mut [u64::MAX, u64::MAX]), Some(u64::MAX));
+	});
+}
+
+#[test]
+fn median_of_numbers_is_stored() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		assert_ok!(OcwDemo::set_number_buffer_length(Origin::root(), 4));
+		for (num, median) in &[(5, 5), (1, 3), (9, 5), (3, 4), (7, 5)] {
+			assert_ok!(OcwDemo::submit_number_signed(
+				Origin::signed(acct.clone()),
+				*num
+			));
+			assert_eq!(OcwDemo::current_median(), *median);
+		}
+		// The window is now 1, 9, 3, 7, whose middle elements 3 and 7 are averaged
+		assert_eq!(<Numbers>::get(), vec![1, 9, 3, 7]);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::ocw_demo(RawEvent::NewNumber(Some(acct.clone()), 7, false))
+		);
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::NewMedian(5))));
+
+		// Shrinking the window recomputes the median
+		assert_ok!(OcwDemo::set_number_buffer_length(Origin::root(), 3));
+		assert_eq!(OcwDemo::current_median(), 7);
+	});
+}
+
+#[test]
+fn successful_fetch_is_recorded_on_chain() {
+	let (mut t, pool_state, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		assert_eq!(OcwDemo::last_fetch(), None);
+		expect_gh_info_request(
+			&offchain_state,
+			br#"{"login":"substrate-developer-hub","blog":"","public_repos":1}"#,
+		);
+		assert!(OcwDemo::fetch_github_info().is_ok());
+		let expected = GithubInfo {
+			login: b"substrate-developer-hub".to_vec(),
+			blog: Vec::new(),
+			public_repos: 1,
+		};
+
+		// The off-chain worker reports the fetch with a signed transaction, once
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert!(tx.signature.is_some());
+		assert_eq!(
+			tx.call,
+			Call::OcwDemo(ocw_demo::Call::submit_gh_info(expected.clone()))
+		);
+
+		assert_ok!(submit_gh_info(expected));
+		assert_eq!(OcwDemo::last_fetch(), Some(1));
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::FetchRecorded(1))));
+	});
+}
+
+#[test]
+fn recorded_github_info_is_indexed() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+	// Off-chain workers read the same database the off-chain index writes to
+	let (offchain, _) = testing::TestOffchainExt::with_offchain_db(t.offchain_db());
+	t.register_extension(OffchainExt::new(offchain));
+
+	let gh_info = GithubInfo {
+		login: b"substrate-developer-hub".to_vec(),
+		blog: b"https://substrate.dev".to_vec(),
+		public_repos: 42,
+	};
+	t.execute_with(|| {
+		assert_eq!(OcwDemo::indexed_github_info(), None);
+		assert_ok!(submit_gh_info(gh_info.clone()));
+	});
+	// Indexed values reach the off-chain database once the block is imported
+	t.persist_offchain_overlay();
+	t.execute_with(|| {
+		assert_eq!(OcwDemo::indexed_github_info(), Some(gh_info));
+	});
+}
+
+/// Sign and submit `submit_number_signed` with whichever key of type `C` is in the keystore,
+/// then check that a signed transaction carrying the call reached the pool
+fn assert_signed_submit_works<C>(keystore: KeyStore)
+where
+	C: AppCrypto<<TestRuntime as SigningTypes>::Public, <TestRuntime as SigningTypes>::Signature>,
+{
+	let (mut t, pool_state, _offchain_state) = ExternalityBuilder::build_with_keystore(keystore);
+
+	t.execute_with(|| {
+		let signer = Signer::<TestRuntime, C>::any_account();
+		let result = signer.send_signed_transaction(|_| ocw_demo::Call::submit_number_signed(7));
+		assert!(matches!(result, Some((_, Ok(())))));
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert!(tx.signature.is_some());
 		assert_eq!(
 			tx.call,
-			Call::OcwDemo(ocw_demo::Call::submit_number_unsigned(num))
+			Call::OcwDemo(ocw_demo::Call::submit_number_signed(7))
+		);
+	});
+}
+
+#[test]
+fn signed_submit_works_with_sr25519_keys() {
+	let keystore = KeyStore::new();
+	keystore.sr25519_generate_new(KEY_TYPE, None).unwrap();
+	assert_signed_submit_works::<crypto::TestAuthId>(keystore);
+}
+
+#[test]
+fn signed_submit_works_with_ed25519_keys() {
+	let keystore = KeyStore::new();
+	keystore.ed25519_generate_new(KEY_TYPE, None).unwrap();
+	assert_signed_submit_works::<crypto::ed25519::TestAuthId>(keystore);
+}
+
+#[test]
+fn signed_submit_works_with_ecdsa_keys() {
+	let keystore = KeyStore::new();
+	keystore.ecdsa_generate_new(KEY_TYPE, None).unwrap();
+	assert_signed_submit_works::<crypto::ecdsa::TestAuthId>(keystore);
+}
+
+#[test]
+fn offchain_worker_fetches_every_fetch_interval() {
+	let (mut t, _, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		FetchInterval::set(5);
+
+		// No HTTP request is made before the fifth block
+		for block_number in 1..5 {
+			System::set_block_number(block_number);
+			OcwDemo::offchain_worker(block_number);
+		}
+		assert!(offchain_state.read().requests.is_empty());
+		assert!(StorageValueRef::persistent(b"ocw-demo::gh-info")
+			.get::<GithubInfo>()
+			.is_none());
+
+		expect_gh_info_request(
+			&offchain_state,
+			br#"{"login":"substrate-developer-hub","blog":"","public_repos":1}"#,
+		);
+		System::set_block_number(5);
+		OcwDemo::offchain_worker(5);
+		assert!(StorageValueRef::persistent(b"ocw-demo::gh-info")
+			.get::<GithubInfo>()
+			.unwrap()
+			.is_some());
+
+		FetchInterval::set(4);
+	});
+}
+
+#[test]
+fn fetch_n_parse_rejects_malformed_json() {
+	let (mut t, _, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		for response in &[
+			&br#"{"login":"substrate-developer-hub","#[..],
+			&br#"{"login":1}"#[..],
+		] {
+			expect_gh_info_request(&offchain_state, response);
+			assert!(matches!(
+				OcwDemo::fetch_n_parse(),
+				Err(Error::<TestRuntime>::JsonParsingError)
+			));
+		}
+	});
+}
+
+#[test]
+fn fetch_github_info_refetches_once_the_cache_expires() {
+	let (mut t, _, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		CacheTtl::set(1_000);
+		let expect_login = |login: &str| {
+			expect_gh_info_request(
+				&offchain_state,
+				format!(r#"{{"login":"{}","blog":"","public_repos":1}}"#, login).as_bytes(),
+			);
+		};
+		let cached_login = || {
+			StorageValueRef::persistent(b"ocw-demo::gh-info")
+				.get::<GithubInfo>()
+				.unwrap()
+				.unwrap()
+				.login
+		};
+
+		expect_login("first");
+		assert!(OcwDemo::fetch_github_info().is_ok());
+		assert_eq!(cached_login(), b"first".to_vec());
+
+		// Within the TTL, the cached info is used without a request
+		let fetched_at = offchain_state.read().timestamp;
+		offchain_state.write().timestamp =
+			fetched_at.add(rt_offchain::Duration::from_millis(1_000));
+		assert!(OcwDemo::fetch_github_info().is_ok());
+		assert_eq!(cached_login(), b"first".to_vec());
+
+		// Once it is older than the TTL, the info is fetched again
+		offchain_state.write().timestamp =
+			fetched_at.add(rt_offchain::Duration::from_millis(1_001));
+		expect_login("second");
+		assert!(OcwDemo::fetch_github_info().is_ok());
+		assert_eq!(cached_login(), b"second".to_vec());
+
+		CacheTtl::set(60_000);
+	});
+}
+
+#[test]
+fn fetch_remote_times_out_after_http_timeout() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+	// The server takes a second to answer
+	let (offchain, offchain_state) = FlakyOffchainExt::slow(1_000);
+	t.register_extension(OffchainExt::new(offchain));
+
+	t.execute_with(|| {
+		expect_gh_info_request(&offchain_state, b"{}");
+		assert_eq!(OcwDemo::fetch_from_remote().unwrap(), b"{}".to_vec());
+
+		HttpTimeout::set(500);
+		let start = sp_io::offchain::timestamp();
+		assert!(matches!(
+			OcwDemo::fetch_from_remote(),
+			Err(Error::<TestRuntime>::HttpFetchingError)
+		));
+		// The worker stopped waiting at the deadline, without retrying
+		assert_eq!(
+			sp_io::offchain::timestamp(),
+			start.add(rt_offchain::Duration::from_millis(500))
+		);
+
+		// Fallback URLs share the deadline, rather than each getting a timeout of its own
+		assert_ok!(OcwDemo::set_fallback_urls(
+			Origin::root(),
+			vec![
+				b"https://example.com/a".to_vec(),
+				b"https://example.com/b".to_vec()
+			]
+		));
+		let start = sp_io::offchain::timestamp();
+		assert!(matches!(
+			OcwDemo::fetch_from_remote(),
+			Err(Error::<TestRuntime>::HttpFetchingError)
+		));
+		assert_eq!(
+			sp_io::offchain::timestamp(),
+			start.add(rt_offchain::Duration::from_millis(500))
+		);
+		HttpTimeout::set(3_000);
+	});
+}
+
+#[test]
+fn fetch_from_remote_sends_configured_user_agent() {
+	let (mut t, _, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let ua = "ocw-demo/1.0";
+		assert_ok!(OcwDemo::set_user_agent(
+			Origin::root(),
+			ua.as_bytes().to_vec()
+		));
+		assert_eq!(OcwDemo::user_agent(), ua.as_bytes().to_vec());
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::UserAgentSet(ua.as_bytes().to_vec()))));
+
+		let request = |ua: &str| testing::PendingRequest {
+			method: "GET".into(),
+			uri: HTTP_REMOTE_REQUEST.into(),
+			headers: vec![("User-Agent".into(), ua.into())],
+			response: Some(b"{}".to_vec()),
+			sent: true,
+			..Default::default()
+		};
+		offchain_state.write().expect_request(request(ua));
+		assert_eq!(OcwDemo::fetch_from_remote().unwrap(), b"{}".to_vec());
+
+		// Clearing the user agent falls back to the default
+		assert_ok!(OcwDemo::set_user_agent(Origin::root(), Vec::new()));
+		offchain_state
+			.write()
+			.expect_request(request(HTTP_HEADER_USER_AGENT));
+		assert_eq!(OcwDemo::fetch_from_remote().unwrap(), b"{}".to_vec());
+	});
+}
+
+#[test]
+fn set_user_agent_handles_basic_errors() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		assert_noop!(
+			OcwDemo::set_user_agent(Origin::signed(acct), b"ocw-demo/1.0".to_vec()),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			OcwDemo::set_user_agent(Origin::root(), vec![0xff, 0xfe]),
+			Error::<TestRuntime>::InvalidUserAgent
+		);
+	});
+}
+
+#[test]
+fn full_number_buffer_evicts_oldest_first() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		let max = MaxNumbers::get() as u64;
+		for num in 1..=max + 3 {
+			assert_ok!(OcwDemo::submit_number_signed(
+				Origin::signed(acct.clone()),
+				num
+			));
+		}
+		// The three oldest numbers were evicted, one per submission once the buffer was full
+		assert_eq!(<Numbers>::get(), (4..=max + 3).collect::<Vec<_>>());
+	});
+}
+
+#[test]
+fn duplicate_numbers_are_stored_without_dedup() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		for num in &[5, 5, 6, 5] {
+			assert_ok!(OcwDemo::submit_number_signed(
+				Origin::signed(acct.clone()),
+				*num
+			));
+		}
+		assert_eq!(<Numbers>::get(), vec![5, 5, 6, 5]);
+		assert!(!System::events()
+			.iter()
+			.any(|er| matches!(er.event, Event::ocw_demo(RawEvent::NewNumber(_, _, true)))));
+	});
+}
+
+#[test]
+fn duplicate_numbers_are_skipped_with_dedup() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		DedupNumbers::set(true);
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		assert_ok!(OcwDemo::submit_number_signed(
+			Origin::signed(acct.clone()),
+			5
+		));
+		System::reset_events();
+
+		// Repeating the latest number is reported, but not stored
+		assert_ok!(submit_number_unsigned(5));
+		assert_eq!(<Numbers>::get(), vec![5]);
+		assert_eq!(
+			System::events()
+				.into_iter()
+				.map(|er| er.event)
+				.collect::<Vec<_>>(),
+			vec![Event::ocw_demo(RawEvent::NewNumber(None, 5, true))]
+		);
+
+		// Only consecutive duplicates are skipped
+		assert_ok!(submit_number_unsigned(6));
+		assert_ok!(submit_number_unsigned(5));
+		assert_eq!(<Numbers>::get(), vec![5, 6, 5]);
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::NewNumber(None, 5, false))));
+		DedupNumbers::set(false);
+	});
+}
+
+#[test]
+fn offchain_signed_tx_rotates_through_local_accounts() {
+	let keystore = KeyStore::new();
+	for _ in 0..3 {
+		keystore.sr25519_generate_new(KEY_TYPE, None).unwrap();
+	}
+	let (mut t, pool_state, _offchain_state) = ExternalityBuilder::build_with_keystore(keystore);
+
+	t.execute_with(|| {
+		SignedFor::set(Vec::new());
+		for num in 0..4 {
+			OcwDemo::offchain_signed_tx(num).unwrap();
+		}
+		assert_eq!(pool_state.read().transactions.len(), 4);
+
+		// Each of the three accounts signs in turn, then the first one again
+		let signed_for = SignedFor::get();
+		assert_eq!(signed_for.len(), 4);
+		assert_ne!(signed_for[0], signed_for[1]);
+		assert_ne!(signed_for[1], signed_for[2]);
+		assert_ne!(signed_for[0], signed_for[2]);
+		assert_eq!(signed_for[3], signed_for[0]);
+	});
+}
+
+#[test]
+fn offchain_worker_skips_submissions_below_threshold() {
+	let (mut t, pool_state, _offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		SubmitThreshold::set(10);
+		let submitted = || pool_state.read().transactions.len();
+
+		// The first number is always submitted
+		OcwDemo::offchain_unsigned_tx(100).unwrap();
+		assert_eq!(submitted(), 1);
+
+		// Changes smaller than the threshold are skipped, by every kind of submission
+		OcwDemo::offchain_unsigned_tx(105).unwrap();
+		OcwDemo::offchain_signed_tx(91).unwrap();
+		OcwDemo::offchain_unsigned_tx_signed_payload(109).unwrap();
+		assert_eq!(submitted(), 1);
+
+		// Changes of at least the threshold are submitted, and compared against from then on
+		OcwDemo::offchain_signed_tx(110).unwrap();
+		assert_eq!(submitted(), 2);
+		OcwDemo::offchain_unsigned_tx_signed_payload(101).unwrap();
+		assert_eq!(submitted(), 2);
+		OcwDemo::offchain_unsigned_tx_signed_payload(100).unwrap();
+		assert_eq!(submitted(), 3);
+
+		SubmitThreshold::set(0);
+	});
+}
+
+#[test]
+fn failed_fetches_are_counted_on_chain() {
+	let (mut t, pool_state, _) = ExternalityBuilder::build();
+	// The server fails every attempt
+	let (offchain, offchain_state) = FlakyOffchainExt::new(MaxFetchRetries::get() + 1);
+	t.register_extension(OffchainExt::new(offchain));
+
+	t.execute_with(|| {
+		for _ in 0..=MaxFetchRetries::get() {
+			expect_gh_info_request(&offchain_state, b"");
+		}
+		assert!(OcwDemo::fetch_github_info().is_err());
+
+		// The off-chain worker reports the failure with an unsigned transaction, carrying a
+		//   signed payload
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert_eq!(tx.signature, None);
+		let call = match tx.call {
+			Call::OcwDemo(call) => call,
+			_ => unreachable!(),
+		};
+		assert!(matches!(
+			call,
+			ocw_demo::Call::record_fetch_failure(
+				FetchFailurePayload {
+					block_number: 1,
+					..
+				},
+				_
+			)
+		));
+		assert!(OcwDemo::validate_unsigned(TransactionSource::Local, &call).is_ok());
+
+		// Failures count up until a fetch succeeds
+		assert_ok!(record_fetch_failure(1));
+		System::set_block_number(2);
+		assert_ok!(record_fetch_failure(2));
+		assert_eq!(OcwDemo::fetch_errors(), 2);
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::FetchFailed(2, 2))));
+
+		assert_ok!(submit_gh_info(Default::default()));
+		assert_eq!(OcwDemo::fetch_errors(), 0);
+	});
+}
+
+#[test]
+fn repeated_fetch_failures_open_the_circuit() {
+	let (mut t, pool_state, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		for block_number in 1..=FailureThreshold::get() as u64 {
+			assert_ok!(record_fetch_failure(block_number));
+		}
+		assert!(!OcwDemo::circuit_open());
+
+		// One failure more than the threshold opens the circuit
+		System::reset_events();
+		assert_ok!(record_fetch_failure(4));
+		assert!(OcwDemo::circuit_open());
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::CircuitOpened)));
+
+		// Further failures do not open it again
+		System::reset_events();
+		assert_ok!(record_fetch_failure(5));
+		assert!(!System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::CircuitOpened)));
+
+		// No response is set up, so a request here would fail the fetch
+		assert!(OcwDemo::fetch_github_info().is_ok());
+		assert!(pool_state.read().transactions.is_empty());
+		assert!(offchain_state.read().requests.is_empty());
+
+		// Once root resets the circuit, fetching resumes
+		assert_ok!(OcwDemo::reset_circuit(Origin::root()));
+		assert!(!OcwDemo::circuit_open());
+		assert_eq!(OcwDemo::fetch_errors(), 0);
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::CircuitClosed)));
+
+		expect_gh_info_request(
+			&offchain_state,
+			br#"{"login":"substrate-developer-hub","blog":"","public_repos":1}"#,
+		);
+		assert!(OcwDemo::fetch_github_info().is_ok());
+		assert_eq!(pool_state.read().transactions.len(), 1);
+	});
+}
+
+#[test]
+fn reset_circuit_requires_root() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		assert_noop!(
+			OcwDemo::reset_circuit(Origin::signed(acct)),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_submits_numbers_from_an_array() {
+	let (mut t, pool_state, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let url = "https://example.com/numbers";
+		let expect_response = |response: &[u8]| {
+			offchain_state
+				.write()
+				.expect_request(testing::PendingRequest {
+					method: "GET".into(),
+					uri: url.into(),
+					headers: vec![("User-Agent".into(), HTTP_HEADER_USER_AGENT.into())],
+					response: Some(response.to_vec()),
+					sent: true,
+					..Default::default()
+				});
+		};
+
+		// Numbers and objects with a price can be mixed
+		expect_response(br#"[4, {"price": 2}, 7]"#);
+		OcwDemo::offchain_unsigned_tx_batch(1, url.as_bytes()).unwrap();
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert_eq!(tx.signature, None);
+		let call = match tx.call {
+			Call::OcwDemo(call) => call,
+			_ => panic!("unexpected call"),
+		};
+		assert!(matches!(
+			call,
+			ocw_demo::Call::submit_numbers_unsigned(ref payload, _)
+				if payload.block_number == 1 && payload.numbers == vec![4, 2, 7]
+					&& payload.public == local_public()
+		));
+		assert!(OcwDemo::validate_unsigned(TransactionSource::Local, &call).is_ok());
+
+		match call {
+			ocw_demo::Call::submit_numbers_unsigned(payload, signature) => {
+				assert_ok!(OcwDemo::submit_numbers_unsigned(
+					Origin::none(),
+					payload,
+					signature
+				));
+			}
+			_ => unreachable!(),
+		}
+		assert_eq!(<Numbers>::get(), vec![4, 2, 7]);
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::NewNumber(None, 2, false))));
+
+		// Nothing is submitted for an empty array
+		expect_response(b"[]");
+		OcwDemo::offchain_unsigned_tx_batch(1, url.as_bytes()).unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+
+		// Anything else in the array is rejected
+		expect_response(br#"[1, "two"]"#);
+		assert!(matches!(
+			OcwDemo::offchain_unsigned_tx_batch(1, url.as_bytes()),
+			Err(Error::<TestRuntime>::JsonParsingError)
+		));
+	});
+}
+
+#[test]
+fn submit_numbers_unsigned_handles_basic_errors() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		let (payload, signature) = match numbers_call(1, vec![1]) {
+			ocw_demo::Call::submit_numbers_unsigned(payload, signature) => (payload, signature),
+			_ => unreachable!(),
+		};
+		assert_noop!(
+			OcwDemo::submit_numbers_unsigned(Origin::signed(acct), payload, signature),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			submit_numbers_unsigned(vec![1; MaxNumbers::get() as usize + 1]),
+			Error::<TestRuntime>::TooManyNumbers
+		);
+		assert_ok!(submit_numbers_unsigned(vec![1, 2]));
+		assert_eq!(<Numbers>::get(), vec![1, 2]);
+	});
+}
+
+#[test]
+fn unsigned_number_batches_are_authenticated() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		System::set_block_number(10);
+		let validate = |call: &ocw_demo::Call<TestRuntime>| {
+			OcwDemo::validate_unsigned(TransactionSource::Local, call)
+		};
+		assert!(validate(&numbers_call(10, vec![4, 2])).is_ok());
+
+		// Changing the numbers invalidates the signature
+		let tampered = match numbers_call(10, vec![4, 2]) {
+			ocw_demo::Call::submit_numbers_unsigned(mut payload, signature) => {
+				payload.numbers = vec![0; MaxNumbers::get() as usize];
+				ocw_demo::Call::submit_numbers_unsigned(payload, signature)
+			}
+			_ => unreachable!(),
+		};
+		assert_eq!(validate(&tampered), InvalidTransaction::BadProof.into());
+
+		// Batches longer than `MaxNumbers` are turned away before the signature is checked
+		let too_long = match numbers_call(10, vec![4, 2]) {
+			ocw_demo::Call::submit_numbers_unsigned(mut payload, signature) => {
+				payload.numbers = vec![0; MaxNumbers::get() as usize + 1];
+				ocw_demo::Call::submit_numbers_unsigned(payload, signature)
+			}
+			_ => unreachable!(),
+		};
+		assert_eq!(
+			validate(&too_long),
+			InvalidTransaction::ExhaustsResources.into()
+		);
+
+		// Batches from the future or older than `UNSIGNED_TXS_MAX_AGE` blocks are rejected
+		assert_eq!(
+			validate(&numbers_call(11, vec![4, 2])),
+			InvalidTransaction::Future.into()
+		);
+		assert_eq!(
+			validate(&numbers_call(9 - UNSIGNED_TXS_MAX_AGE as u64, vec![4, 2])),
+			InvalidTransaction::Stale.into()
+		);
+
+		// A key that is not an authority can not have its batch validated
+		assert_ok!(OcwDemo::set_authorities(Origin::root(), Vec::new()));
+		assert_eq!(
+			validate(&numbers_call(10, vec![4, 2])),
+			InvalidTransaction::BadProof.into()
+		);
+	});
+}
+
+#[test]
+fn number_batch_weight_scales_with_length() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let write = <TestRuntime as frame_system::Config>::DbWeight::get().writes(1);
+		let signed = |numbers: Vec<u64>| {
+			ocw_demo::Call::<TestRuntime>::submit_numbers_signed(numbers)
+				.get_dispatch_info()
+				.weight
+		};
+		assert_eq!(signed(vec![1, 2, 3]) - signed(vec![1]), 2 * write);
+
+		let unsigned = |numbers: Vec<u64>| numbers_call(1, numbers).get_dispatch_info().weight;
+		assert_eq!(unsigned(vec![1, 2, 3]) - unsigned(Vec::new()), 3 * write);
+	});
+}
+
+#[test]
+fn fetch_from_remote_falls_back_to_the_next_url() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+	// The remote URL fails every attempt, then the fallback answers
+	let (offchain, offchain_state) = FlakyOffchainExt::new(MaxFetchRetries::get() + 1);
+	t.register_extension(OffchainExt::new(offchain));
+
+	t.execute_with(|| {
+		let fallback = "https://mirror.example.com/orgs/substrate-developer-hub";
+		assert_ok!(OcwDemo::set_fallback_urls(
+			Origin::root(),
+			vec![fallback.as_bytes().to_vec()]
+		));
+		assert!(System::events().iter().any(|er| er.event
+			== Event::ocw_demo(RawEvent::FallbackUrlsSet(vec![fallback
+				.as_bytes()
+				.to_vec()]))));
+
+		let request = |uri: &str, response: &[u8]| testing::PendingRequest {
+			method: "GET".into(),
+			uri: uri.into(),
+			headers: vec![("User-Agent".into(), HTTP_HEADER_USER_AGENT.into())],
+			response: Some(response.to_vec()),
+			sent: true,
+			..Default::default()
+		};
+		for _ in 0..=MaxFetchRetries::get() {
+			offchain_state
+				.write()
+				.expect_request(request(HTTP_REMOTE_REQUEST, b""));
+		}
+		offchain_state
+			.write()
+			.expect_request(request(fallback, b"{}"));
+
+		assert_eq!(OcwDemo::fetch_from_remote().unwrap(), b"{}".to_vec());
+		assert_eq!(
+			StorageValueRef::persistent(LAST_HOST_KEY).get::<Vec<u8>>(),
+			Some(Some(fallback.as_bytes().to_vec()))
+		);
+	});
+}
+
+#[test]
+fn set_fallback_urls_handles_basic_errors() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		assert_noop!(
+			OcwDemo::set_fallback_urls(Origin::signed(acct), vec![b"https://example.com".to_vec()]),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			OcwDemo::set_fallback_urls(Origin::root(), vec![vec![0xff, 0xfe]]),
+			Error::<TestRuntime>::InvalidRemoteUrl
+		);
+		assert_noop!(
+			OcwDemo::set_fallback_urls(Origin::root(), vec![Vec::new()]),
+			Error::<TestRuntime>::InvalidRemoteUrl
+		);
+	});
+}
+
+#[test]
+fn validate_unsigned_rejects_stale_and_future_numbers() {
+	let (mut t, pool_state, _offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let validate = |block_number, number| {
+			OcwDemo::validate_unsigned(
+				TransactionSource::Local,
+				&signed_number_call(block_number, number),
+			)
+		};
+		System::set_block_number(10);
+
+		// Older numbers are still accepted for a while, at a lower priority
+		let recent = validate(10, 5).unwrap();
+		let older = validate(10 - UNSIGNED_TXS_MAX_AGE as u64, 5).unwrap();
+		assert!(older.priority < recent.priority);
+		assert!(older.longevity < recent.longevity);
+		assert_eq!(
+			validate(9 - UNSIGNED_TXS_MAX_AGE as u64, 5),
+			InvalidTransaction::Stale.into()
+		);
+		assert_eq!(validate(11, 5), InvalidTransaction::Future.into());
+
+		// Signed payloads age the same way
+		OcwDemo::offchain_unsigned_tx_signed_payload(10).unwrap();
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		let call = match tx.call {
+			Call::OcwDemo(call) => call,
+			_ => panic!("the off-chain worker submits an ocw-demo call"),
+		};
+		assert!(OcwDemo::validate_unsigned(TransactionSource::Local, &call).is_ok());
+		System::set_block_number(11 + UNSIGNED_TXS_MAX_AGE as u64);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::Local, &call),
+			InvalidTransaction::Stale.into()
+		);
+	});
+}
+
+#[test]
+fn validate_unsigned_tags_duplicate_numbers_alike() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let provides = |block_number, number| {
+			OcwDemo::validate_unsigned(
+				TransactionSource::Local,
+				&signed_number_call(block_number, number),
+			)
+			.unwrap()
+			.provides
+		};
+		System::set_block_number(2);
+
+		// The pool only keeps one of two transactions providing the same tag
+		assert_eq!(provides(2, 5), provides(2, 5));
+		assert_ne!(provides(2, 5), provides(2, 6));
+		assert_ne!(provides(2, 5), provides(1, 5));
+	});
+}
+
+#[test]
+fn signed_number_payloads_are_authenticated() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let validate = |call: &ocw_demo::Call<TestRuntime>| {
+			OcwDemo::validate_unsigned(TransactionSource::Local, call)
+		};
+
+		// A payload signed by an authority is accepted
+		let call = signed_number_call(1, 42);
+		assert!(validate(&call).is_ok());
+
+		// Changing the payload invalidates the signature
+		let tampered = match call.clone() {
+			ocw_demo::Call::submit_number_unsigned(mut payload, signature) => {
+				payload.number = 43;
+				ocw_demo::Call::submit_number_unsigned(payload, signature)
+			}
+			_ => unreachable!(),
+		};
+		assert_eq!(validate(&tampered), InvalidTransaction::BadProof.into());
+
+		// A valid signature from a key that is not an authority is rejected
+		assert_ok!(OcwDemo::set_authorities(Origin::root(), Vec::new()));
+		assert_eq!(validate(&call), InvalidTransaction::BadProof.into());
+	});
+}
+
+#[test]
+fn set_authorities_works() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		let authorities = vec![MultiSigner::from(sr25519::Public::default()).into_account()];
+		assert_noop!(
+			OcwDemo::set_authorities(Origin::signed(acct), authorities.clone()),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(OcwDemo::set_authorities(
+			Origin::root(),
+			authorities.clone()
+		));
+		assert_eq!(OcwDemo::authorities(), authorities);
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::AuthoritiesSet(authorities.clone()))));
+	});
+}
+
+#[test]
+fn fetched_github_info_is_stored_on_chain() {
+	let (mut t, pool_state, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		expect_gh_info_request(
+			&offchain_state,
+			br#"{"login":"substrate-developer-hub","blog":"","public_repos":42}"#,
+		);
+		assert!(OcwDemo::fetch_github_info().is_ok());
+		let gh_info = GithubInfo {
+			login: b"substrate-developer-hub".to_vec(),
+			blog: Vec::new(),
+			public_repos: 42,
+		};
+
+		// The off-chain worker submits the info only once, signed by its authority key
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		let info = match tx.call {
+			Call::OcwDemo(ocw_demo::Call::submit_gh_info(info)) => info,
+			_ => unreachable!(),
+		};
+		assert_eq!(info, gh_info);
+
+		let authority = OcwDemo::authorities().pop().unwrap();
+		assert_eq!(SignedFor::get(), vec![authority.clone()]);
+		assert_ok!(OcwDemo::submit_gh_info(
+			Origin::signed(authority.clone()),
+			info
+		));
+		assert_eq!(OcwDemo::github_login(), b"substrate-developer-hub".to_vec());
+		assert_eq!(OcwDemo::github_public_repos(), 42);
+		assert!(System::events().iter().any(|er| er.event
+			== Event::ocw_demo(RawEvent::GithubInfoUpdated(
+				authority.clone(),
+				b"substrate-developer-hub".to_vec(),
+				42
+			))));
+	});
+}
+
+#[test]
+fn submit_gh_info_handles_basic_errors() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let gh_info = GithubInfo {
+			login: b"substrate-developer-hub".to_vec(),
+			blog: Vec::new(),
+			public_repos: 42,
+		};
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		assert_noop!(
+			OcwDemo::submit_gh_info(Origin::signed(acct), gh_info.clone()),
+			Error::<TestRuntime>::NotAuthority
+		);
+
+		let too_long = GithubInfo {
+			login: vec![b'a'; MaxLoginLength::get() as usize + 1],
+			..gh_info.clone()
+		};
+		assert_noop!(submit_gh_info(too_long), Error::<TestRuntime>::LoginTooLong);
+
+		// The blog URL is indexed along with the login, so it is bounded too
+		let too_long = GithubInfo {
+			blog: vec![b'a'; MaxBlogLength::get() as usize + 1],
+			..gh_info
+		};
+		assert_noop!(submit_gh_info(too_long), Error::<TestRuntime>::BlogTooLong);
+		assert_eq!(OcwDemo::last_fetch(), None);
+	});
+}
+
+#[test]
+fn fetch_remote_rejects_oversized_responses() {
+	let (mut t, _, offchain_state) = ExternalityBuilder::build();
+	MaxFetchRetries::set(0);
+
+	t.execute_with(|| {
+		let max = MaxResponseBytes::get() as usize;
+		for (size, ok) in &[(max, true), (max + 1, false)] {
+			offchain_state
+				.write()
+				.expect_request(testing::PendingRequest {
+					method: "GET".into(),
+					uri: "https://example.com/huge".into(),
+					headers: vec![("User-Agent".into(), HTTP_HEADER_USER_AGENT.into())],
+					response: Some(vec![b'1'; *size]),
+					sent: true,
+					..Default::default()
+				});
+			let result = OcwDemo::fetch_remote(HttpMethod::Get, "https://example.com/huge", &[]);
+			if *ok {
+				assert_eq!(result.ok().map(|body| body.len()), Some(max));
+			} else {
+				assert!(matches!(
+					result,
+					Err(Error::<TestRuntime>::HttpFetchingError)
+				));
+			}
+		}
+	});
+}
+
+#[test]
+fn ema_tracks_stored_numbers() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		EmaAlpha::set(Perbill::from_percent(25));
+		assert_eq!(OcwDemo::ema(), None);
+
+		// The first number starts the average off
+		assert_ok!(submit_number_unsigned(100));
+		assert_eq!(OcwDemo::ema(), Some(100));
+
+		// 25% of 200 and 75% of 100
+		assert_ok!(submit_number_unsigned(200));
+		assert_eq!(OcwDemo::ema(), Some(125));
+		// 25% of 25 and 75% of 125
+		assert_ok!(submit_number_unsigned(25));
+		assert_eq!(OcwDemo::ema(), Some(100));
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::NewEma(100))));
+
+		// A steady stream of numbers pulls the average towards them, though rounding may keep it
+		//   just short
+		for _ in 0..20 {
+			assert_ok!(submit_number_unsigned(1000));
+		}
+		assert!((995..=1000).contains(&OcwDemo::ema().unwrap()));
+	});
+}
+
+#[test]
+fn ema_follows_new_numbers_faster_with_a_higher_alpha() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		EmaAlpha::set(Perbill::one());
+		assert_ok!(submit_number_unsigned(10));
+		assert_ok!(submit_number_unsigned(30));
+		assert_eq!(OcwDemo::ema(), Some(30));
+
+		EmaAlpha::set(Perbill::zero());
+		assert_ok!(submit_number_unsigned(50));
+		assert_eq!(OcwDemo::ema(), Some(30));
+
+		EmaAlpha::set(Perbill::from_percent(50));
+		assert_ok!(submit_number_unsigned(50));
+		assert_eq!(OcwDemo::ema(), Some(40));
+	});
+}
+
+#[test]
+fn parse_gh_info_works() {
+	let gh_info = OcwDemo::parse_gh_info(
+		br#"{"login":"substrate-developer-hub","blog":"https://substrate.dev","public_repos":42}"#,
+	);
+	assert_eq!(
+		gh_info.ok(),
+		Some(GithubInfo {
+			login: b"substrate-developer-hub".to_vec(),
+			blog: b"https://substrate.dev".to_vec(),
+			public_repos: 42,
+		})
+	);
+
+	// Fields other than these are ignored
+	let gh_info =
+		OcwDemo::parse_gh_info(br#"{"id":1,"login":"","blog":"","public_repos":0,"type":"Org"}"#);
+	assert_eq!(gh_info.ok(), Some(GithubInfo::default()));
+}
+
+#[test]
+fn parse_gh_info_rejects_empty_and_malformed_input() {
+	for bytes in &[
+		&b""[..],
+		&b"{}"[..],
+		&br#"{"login":"substrate-developer-hub","#[..],
+		&br#"{"login":1,"blog":"","public_repos":0}"#[..],
+		&br#"{"login":"","blog":"","public_repos":-1}"#[..],
+	] {
+		assert!(matches!(
+			OcwDemo::parse_gh_info(bytes),
+			Err(Error::<TestRuntime>::JsonParsingError)
+		));
+	}
+
+	// A body that is not even text is treated like a failed fetch
+	assert!(matches!(
+		OcwDemo::parse_gh_info(&[0xff, 0xfe]),
+		Err(Error::<TestRuntime>::HttpFetchingError)
+	));
+}
+
+#[test]
+fn offchain_worker_fetches_parses_and_caches_github_info() {
+	let (mut t, pool_state, offchain_state) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let gh_info = GithubInfo {
+			login: b"substrate-developer-hub".to_vec(),
+			blog: b"https://substrate.dev".to_vec(),
+			public_repos: 42,
+		};
+
+		// The first fetch block sends the request, which would panic if it were not expected,
+		//   and caches what it parses
+		expect_gh_info_request(
+			&offchain_state,
+			br#"{"login":"substrate-developer-hub","blog":"https://substrate.dev","public_repos":42}"#,
+		);
+		System::set_block_number(FetchInterval::get());
+		OcwDemo::offchain_worker(FetchInterval::get());
+		assert_eq!(
+			StorageValueRef::persistent(b"ocw-demo::gh-info")
+				.get::<GithubInfo>()
+				.flatten(),
+			Some(gh_info.clone())
+		);
+		assert_eq!(
+			StorageValueRef::persistent(LAST_HOST_KEY)
+				.get::<Vec<u8>>()
+				.flatten(),
+			Some(HTTP_REMOTE_REQUEST.as_bytes().to_vec())
+		);
+
+		// The fetch is reported on-chain
+		let calls = pool_state
+			.read()
+			.transactions
+			.iter()
+			.map(|tx| Extrinsic::decode(&mut &tx[..]).unwrap().call)
+			.collect::<Vec<_>>();
+		assert!(calls
+			.iter()
+			.any(|call| *call == Call::OcwDemo(ocw_demo::Call::submit_gh_info(gh_info.clone()))));
+
+		// The next fetch block is served from the cache, without a request
+		System::set_block_number(FetchInterval::get() * 2);
+		OcwDemo::offchain_worker(FetchInterval::get() * 2);
+		assert!(offchain_state.read().requests.is_empty());
+		assert_eq!(
+			StorageValueRef::persistent(b"ocw-demo::gh-info")
+				.get::<GithubInfo>()
+				.flatten(),
+			Some(gh_info)
+		);
+	});
+}
+
+#[test]
+fn submit_numbers_signed_works() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		MaxNumbers::set(3);
+		assert_ok!(OcwDemo::submit_number_signed(
+			Origin::signed(acct.clone()),
+			1
+		));
+		assert_ok!(OcwDemo::submit_numbers_signed(
+			Origin::signed(acct.clone()),
+			vec![2, 3]
+		));
+		assert_eq!(OcwDemo::numbers(), vec![1, 2, 3]);
+		assert!(System::events().iter().any(
+			|er| er.event == Event::ocw_demo(RawEvent::NewNumber(Some(acct.clone()), 3, false))
+		));
+
+		// The window keeps the newest numbers of a batch
+		assert_ok!(OcwDemo::submit_numbers_signed(
+			Origin::signed(acct.clone()),
+			vec![4, 5]
+		));
+		assert_eq!(OcwDemo::numbers(), vec![3, 4, 5]);
+
+		assert_noop!(
+			OcwDemo::submit_numbers_signed(Origin::signed(acct), vec![6, 7, 8, 9]),
+			Error::<TestRuntime>::TooManyNumbers
+		);
+		assert_noop!(
+			OcwDemo::submit_numbers_signed(Origin::none(), vec![6]),
+			DispatchError::BadOrigin
+		);
+		assert_eq!(OcwDemo::numbers(), vec![3, 4, 5]);
+	});
+}
+
+#[test]
+fn offchain_signed_tx_flushes_buffered_numbers() {
+	let (mut t, pool_state, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		SignedBatchSize::set(3);
+
+		// Numbers are held back until the batch is full
+		OcwDemo::offchain_signed_tx(1).unwrap();
+		OcwDemo::offchain_signed_tx(4).unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+
+		OcwDemo::offchain_signed_tx(7).unwrap();
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert!(tx.signature.is_some());
+		assert_eq!(
+			tx.call,
+			Call::OcwDemo(ocw_demo::Call::submit_numbers_signed(vec![1, 4, 7]))
+		);
+
+		// The buffer starts over once it is sent
+		OcwDemo::offchain_signed_tx(10).unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+	});
+}
+
+#[test]
+fn clear_numbers_resets_the_window_and_aggregates() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let acct: <TestRuntime as frame_system::Config>::AccountId = Default::default();
+		for number in &[10, 20, 30] {
+			assert_ok!(submit_number_unsigned(*number));
+		}
+		assert_eq!(OcwDemo::numbers().len(), 3);
+		assert_eq!(OcwDemo::current_median(), 20);
+		assert!(OcwDemo::ema().is_some());
+
+		assert_noop!(
+			OcwDemo::clear_numbers(Origin::signed(acct)),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(OcwDemo::clear_numbers(Origin::root()));
+		assert!(OcwDemo::numbers().is_empty());
+		assert_eq!(OcwDemo::average(&OcwDemo::numbers()), None);
+		assert_eq!(OcwDemo::current_median(), 0);
+		assert_eq!(OcwDemo::ema(), None);
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::NumbersCleared)));
+
+		// The next number starts the aggregates over
+		assert_ok!(submit_number_unsigned(7));
+		assert_eq!(OcwDemo::numbers(), vec![7]);
+		assert_eq!(OcwDemo::current_median(), 7);
+		assert_eq!(OcwDemo::ema(), Some(7));
+	});
+}
+
+#[test]
+fn average_of_numbers_is_stored() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		RoundAverage::set(false);
+		MaxNumbers::set(3);
+		assert_eq!(OcwDemo::current_average(), 0);
+
+		assert_ok!(submit_number_unsigned(10));
+		assert_eq!(OcwDemo::current_average(), 10);
+		assert_ok!(submit_number_unsigned(20));
+		assert_eq!(OcwDemo::current_average(), 15);
+		assert_ok!(submit_number_unsigned(40));
+		// 70 / 3, truncated
+		assert_eq!(OcwDemo::current_average(), 23);
+		assert!(System::events()
+			.iter()
+			.any(|er| er.event == Event::ocw_demo(RawEvent::NewAverage(23))));
+
+		// The oldest number leaves the window: (20 + 40 + 60) / 3
+		assert_ok!(submit_number_unsigned(60));
+		assert_eq!(OcwDemo::current_average(), 40);
+
+		// Shrinking the window updates the average too: (40 + 60) / 2
+		assert_ok!(OcwDemo::set_number_buffer_length(Origin::root(), 2));
+		assert_eq!(OcwDemo::current_average(), 50);
+
+		// An empty window averages to zero
+		assert_ok!(OcwDemo::clear_numbers(Origin::root()));
+		assert_eq!(OcwDemo::current_average(), 0);
+	});
+}
+
+#[test]
+fn fetch_failure_reports_are_authenticated_and_current() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let validate = |call: &ocw_demo::Call<TestRuntime>| {
+			OcwDemo::validate_unsigned(TransactionSource::Local, call)
+		};
+		System::set_block_number(5);
+
+		// A report for the current block is accepted, and so is one made in the block before,
+		//   as it is included in the block after it was made
+		assert!(validate(&fetch_failure_call(5)).is_ok());
+		assert!(validate(&fetch_failure_call(4)).is_ok());
+		assert_eq!(
+			validate(&fetch_failure_call(3)),
+			InvalidTransaction::Stale.into()
+		);
+		assert_eq!(
+			validate(&fetch_failure_call(6)),
+			InvalidTransaction::Future.into()
+		);
+
+		// Changing the payload invalidates the signature
+		let tampered = match fetch_failure_call(5) {
+			ocw_demo::Call::record_fetch_failure(mut payload, signature) => {
+				payload.block_number = 4;
+				ocw_demo::Call::record_fetch_failure(payload, signature)
+			}
+			_ => unreachable!(),
+		};
+		assert_eq!(validate(&tampered), InvalidTransaction::BadProof.into());
+
+		// A valid signature from a key that is not an authority is rejected
+		assert_ok!(OcwDemo::set_authorities(Origin::root(), Vec::new()));
+		assert_eq!(
+			validate(&fetch_failure_call(5)),
+			InvalidTransaction::BadProof.into()
+		);
+	});
+}
+
+#[test]
+fn only_authority_reports_count_towards_the_circuit() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		// Reports signed by a key that is not an authority are neither valid nor counted
+		assert_ok!(OcwDemo::set_authorities(Origin::root(), Vec::new()));
+		for block_number in 1..=FailureThreshold::get() as u64 + 1 {
+			System::set_block_number(block_number);
+			assert_eq!(
+				OcwDemo::validate_unsigned(
+					TransactionSource::External,
+					&fetch_failure_call(block_number)
+				),
+				InvalidTransaction::BadProof.into()
+			);
+			assert_noop!(
+				record_fetch_failure(block_number),
+				Error::<TestRuntime>::NotAuthority
+			);
+		}
+		assert_eq!(OcwDemo::fetch_errors(), 0);
+		assert!(!OcwDemo::circuit_open());
+
+		// Once the key is an authority, its reports count
+		let authority = local_public().into_account();
+		assert_ok!(OcwDemo::set_authorities(Origin::root(), vec![authority]));
+		assert_ok!(record_fetch_failure(FailureThreshold::get() as u64 + 1));
+		assert_eq!(OcwDemo::fetch_errors(), 1);
+	});
+}
+
+#[test]
+fn fetch_reports_are_authenticated() {
+	let (mut t, _, _) = ExternalityBuilder::build();
+
+	t.execute_with(|| {
+		let validate = |call: &ocw_demo::Call<TestRuntime>| {
+			OcwDemo::validate_unsigned(TransactionSource::Local, call)
+		};
+		let gh_info = GithubInfo {
+			login: b"substrate-developer-hub".to_vec(),
+			blog: Vec::new(),
+			public_repos: 1,
+		};
+		assert!(validate(&fetch_failure_call(1)).is_ok());
+
+		// Changing the payload invalidates the signature
+		let tampered = match fetch_failure_call(1) {
+			ocw_demo::Call::record_fetch_failure(mut payload, signature) => {
+				payload.block_number = 0;
+				ocw_demo::Call::record_fetch_failure(payload, signature)
+			}
+			_ => unreachable!(),
+		};
+		assert_eq!(validate(&tampered), InvalidTransaction::BadProof.into());
+
+		// A key that is not an authority can neither have its report validated nor dispatched
+		assert_ok!(OcwDemo::set_authorities(Origin::root(), Vec::new()));
+		assert_eq!(
+			validate(&fetch_failure_call(1)),
+			InvalidTransaction::BadProof.into()
 		);
+		assert_noop!(submit_gh_info(gh_info), Error::<TestRuntime>::NotAuthority);
+		assert_noop!(record_fetch_failure(1), Error::<TestRuntime>::NotAuthority);
+		assert_eq!(OcwDemo::last_fetch(), None);
 	});
 }
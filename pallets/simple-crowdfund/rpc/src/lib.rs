@@ -0,0 +1,168 @@
+//! RPC interface for the simple crowdfund pallet.
+//!
+//! Contributions live in child tries, so they can't be read with the usual storage RPCs. This
+//! crate surfaces them through the `CrowdfundApi` runtime API instead. A node registers the
+//! handler alongside its other RPC extensions:
+//!
+//! ```ignore
+//! let mut io = jsonrpc_core::IoHandler::default();
+//! io.extend_with(simple_crowdfund_rpc::CrowdfundApi::to_delegate(
+//!     simple_crowdfund_rpc::Crowdfund::new(client.clone()),
+//! ));
+//! ```
+
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use parity_scale_codec::Codec;
+use simple_crowdfund::FundIndex;
+use simple_crowdfund_runtime_api::CrowdfundApi as CrowdfundRuntimeApi;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_rpc::number::NumberOrHex;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::{convert::TryInto, sync::Arc};
+
+#[rpc]
+pub trait CrowdfundApi<BlockHash, AccountId> {
+	/// The amount `who` has contributed to the fund at `index`
+	#[rpc(name = "crowdfund_contribution")]
+	fn contribution(
+		&self,
+		index: FundIndex,
+		who: AccountId,
+		at: Option<BlockHash>,
+	) -> Result<NumberOrHex>;
+}
+
+/// A struct that implements the `CrowdfundApi`.
+pub struct Crowdfund<C, M> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<M>,
+}
+
+impl<C, M> Crowdfund<C, M> {
+	/// Create new `Crowdfund` instance with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self {
+			client,
+			_marker: Default::default(),
+		}
+	}
+}
+
+impl<C, Block, AccountId, Balance> CrowdfundApi<<Block as BlockT>::Hash, AccountId>
+	for Crowdfund<C, (Block, Balance)>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static,
+	C: ProvideRuntimeApi<Block>,
+	C: HeaderBackend<Block>,
+	C::Api: CrowdfundRuntimeApi<Block, AccountId, Balance>,
+	AccountId: Codec,
+	Balance: Codec + TryInto<NumberOrHex> + Send + Sync + 'static,
+{
+	fn contribution(
+		&self,
+		index: FundIndex,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<NumberOrHex> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let contribution = api.contribution(&at, index, who).map_err(|e| RpcError {
+			code: ErrorCode::ServerError(1),
+			message: "Unable to query contribution.".into(),
+			data: Some(format!("{:?}", e).into()),
+		})?;
+		contribution.try_into().map_err(|_| RpcError {
+			code: ErrorCode::InvalidParams,
+			message: "Contribution does not fit into a number or hex.".into(),
+			data: None,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use simple_crowdfund::FundState;
+	use sp_runtime::testing::{Block as TestBlock, ExtrinsicWrapper, Header, H256};
+
+	type Block = TestBlock<ExtrinsicWrapper<()>>;
+
+	struct MockApi;
+
+	sp_api::mock_impl_runtime_apis! {
+		impl simple_crowdfund_runtime_api::CrowdfundApi<Block, u64, u64> for MockApi {
+			type Error = sp_api::ApiError;
+
+			fn contribution(index: FundIndex, who: u64) -> u64 {
+				if index == 0 && who == 1 {
+					100
+				} else {
+					0
+				}
+			}
+
+			fn backer_portfolio(_who: u64) -> Vec<(FundIndex, u64, FundState)> {
+				Vec::new()
+			}
+		}
+	}
+
+	struct MockClient;
+
+	impl ProvideRuntimeApi<Block> for MockClient {
+		type Api = MockApi;
+
+		fn runtime_api(&self) -> sp_api::ApiRef<'_, Self::Api> {
+			MockApi.into()
+		}
+	}
+
+	impl HeaderBackend<Block> for MockClient {
+		fn header(&self, _id: BlockId<Block>) -> sp_blockchain::Result<Option<Header>> {
+			Ok(None)
+		}
+
+		fn info(&self) -> sp_blockchain::Info<Block> {
+			sp_blockchain::Info {
+				best_hash: Default::default(),
+				best_number: 0,
+				genesis_hash: Default::default(),
+				finalized_hash: Default::default(),
+				finalized_number: 0,
+				number_leaves: 0,
+			}
+		}
+
+		fn status(&self, _id: BlockId<Block>) -> sp_blockchain::Result<sp_blockchain::BlockStatus> {
+			Ok(sp_blockchain::BlockStatus::Unknown)
+		}
+
+		fn number(&self, _hash: H256) -> sp_blockchain::Result<Option<u64>> {
+			Ok(None)
+		}
+
+		fn hash(&self, _number: u64) -> sp_blockchain::Result<Option<H256>> {
+			Ok(None)
+		}
+	}
+
+	#[test]
+	fn contribution_works() {
+		let rpc = Crowdfund::<_, (Block, u64)>::new(Arc::new(MockClient));
+
+		assert_eq!(
+			rpc.contribution(0, 1, None).unwrap(),
+			NumberOrHex::Number(100)
+		);
+		assert_eq!(
+			rpc.contribution(0, 2, Some(Default::default())).unwrap(),
+			NumberOrHex::Number(0)
+		);
+	}
+}
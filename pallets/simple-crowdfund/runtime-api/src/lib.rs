@@ -0,0 +1,19 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::unnecessary_mut_passed)]
+
+use parity_scale_codec::Codec;
+use simple_crowdfund::{FundIndex, FundState};
+use sp_std::prelude::*;
+
+// Here we declare the runtime API. It is implemented it the `impl` block in
+// runtime amalgamator file (the `runtime/src/lib.rs`)
+sp_api::decl_runtime_apis! {
+	pub trait CrowdfundApi<AccountId: Codec, Balance: Codec> {
+		/// The amount `who` has contributed to the fund at `index`
+		fn contribution(index: FundIndex, who: AccountId) -> Balance;
+
+		/// Every fund `who` holds a contribution in, with that contribution and the fund's state
+		fn backer_portfolio(who: AccountId) -> Vec<(FundIndex, Balance, FundState)>;
+	}
+}
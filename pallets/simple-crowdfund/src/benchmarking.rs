@@ -0,0 +1,107 @@
+//! Benchmarks for the simple crowdfund pallet
+
+use super::*;
+
+use frame_benchmarking::{
+	account, benchmarks_instance_pallet, impl_benchmark_test_suite, whitelisted_caller,
+};
+use frame_support::traits::EnsureOrigin;
+use frame_system::RawOrigin;
+use sp_runtime::traits::Bounded;
+
+const SEED: u32 = 0;
+
+fn funded_account<T: Config<I>, I: 'static>(name: &'static str, index: u32) -> T::AccountId {
+	let who = account(name, index, SEED);
+	T::Currency::make_free_balance_be(&who, BalanceOf::<T, I>::max_value() / 2u32.into());
+	who
+}
+
+/// Create a fund that has started, ends in ten blocks, and cannot reach its goal.
+fn open_fund<T: Config<I>, I: 'static>() -> FundIndex {
+	let owner = funded_account::<T, I>("owner", 0);
+	let now = frame_system::Module::<T>::block_number();
+	Pallet::<T, I>::do_create(
+		owner.clone(),
+		owner,
+		BalanceOf::<T, I>::max_value(),
+		BalanceOf::<T, I>::max_value(),
+		T::MinContribution::get(),
+		now,
+		now + 10u32.into(),
+		T::RetirementPeriod::get(),
+		FundCategory::Other,
+		Vec::new(),
+	)
+	.expect("owner can pay the deposit")
+}
+
+benchmarks_instance_pallet! {
+	create {
+		let origin = T::CreateOrigin::successful_origin();
+		let caller = T::CreateOrigin::ensure_origin(origin.clone()).expect("origin is accepted");
+		T::Currency::make_free_balance_be(&caller, BalanceOf::<T, I>::max_value() / 2u32.into());
+		let now = frame_system::Module::<T>::block_number();
+		let goal = T::MinContribution::get();
+		let metadata = vec![0u8; T::MaxMetadataLen::get() as usize];
+	}: {
+		Pallet::<T, I>::create(origin, caller, goal, CapMode::Absolute(goal), goal, now, now + 10u32.into(), T::RetirementPeriod::get(), FundCategory::Other, metadata)?;
+	}
+	verify {
+		assert_eq!(Pallet::<T, I>::fund_count(), 1);
+	}
+
+	contribute {
+		let c in 0 .. 1_000;
+		let index = open_fund::<T, I>();
+		let value = T::MinContribution::get();
+		for i in 0 .. c {
+			Pallet::<T, I>::contribution_put(index, &account("contributor", i, SEED), &value);
+		}
+		let caller: T::AccountId = whitelisted_caller();
+		T::Currency::make_free_balance_be(&caller, BalanceOf::<T, I>::max_value() / 2u32.into());
+	}: _(RawOrigin::Signed(caller.clone()), index, value)
+	verify {
+		assert_eq!(Pallet::<T, I>::contribution_get(index, &caller), value);
+	}
+
+	withdraw {
+		let b in 1 .. T::MaxFundsPerContributor::get();
+		let index = open_fund::<T, I>();
+		let value = T::MinContribution::get();
+		let caller = funded_account::<T, I>("caller", 0);
+		Pallet::<T, I>::contribute(RawOrigin::Signed(caller.clone()).into(), index, value)?;
+		// The fund withdrawn from is the last of those the caller backs
+		BackedFunds::<T, I>::mutate(&caller, |funds| {
+			funds.splice(0 .. 0, (1 .. b).map(|i| index + i));
+		});
+		let end = Pallet::<T, I>::funds(index).expect("fund was just created").end;
+		frame_system::Module::<T>::set_block_number(end + 1u32.into());
+	}: _(RawOrigin::Signed(caller.clone()), index)
+	verify {
+		assert!(Pallet::<T, I>::contribution_get(index, &caller).is_zero());
+	}
+
+	dissolve {
+		let index = open_fund::<T, I>();
+		let value = T::MinContribution::get();
+		let contributor = funded_account::<T, I>("contributor", 0);
+		Pallet::<T, I>::contribute(RawOrigin::Signed(contributor).into(), index, value)?;
+		let end = Pallet::<T, I>::funds(index).expect("fund was just created").end;
+		frame_system::Module::<T>::set_block_number(end + T::RetirementPeriod::get());
+		// Satisfies timed retirement, if configured, without waiting for the clock
+		RetireAfter::<T, I>::insert(index, MomentOf::<T, I>::zero());
+		let origin = T::DissolveOrigin::successful_origin();
+	}: {
+		Pallet::<T, I>::dissolve(origin, index)?;
+	}
+	verify {
+		assert!(Pallet::<T, I>::funds(index).is_none());
+	}
+}
+
+impl_benchmark_test_suite!(
+	Pallet,
+	crate::tests::new_test_ext(),
+	crate::tests::TestRuntime,
+);
@@ -7,103 +7,638 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 #![allow(clippy::unused_unit)]
 
-use frame_support::{storage::child, traits::Currency};
-
-use parity_scale_codec::Encode;
+use frame_support::{
+	dispatch::{DispatchError, DispatchResult},
+	ensure,
+	storage::child,
+	traits::{
+		BalanceStatus, Currency, ExistenceRequirement, Get, Imbalance, OnUnbalanced, PalletInfo,
+		ReservableCurrency, Time, WithdrawReasons,
+	},
+	weights::Weight,
+};
+
+use parity_scale_codec::{Decode, Encode};
 use sp_core::Hasher;
 
-use sp_runtime::{traits::AccountIdConversion, ModuleId};
+use sp_runtime::{
+	traits::{AccountIdConversion, CheckedAdd, One, SaturatedConversion, Saturating, Zero},
+	FixedPointNumber, Perbill,
+};
 use sp_std::prelude::*;
 
 pub type FundIndex = u32;
 type AccountIdOf<T> = <T as frame_system::Config>::AccountId;
-type BalanceOf<T> = <<T as Config>::Currency as Currency<AccountIdOf<T>>>::Balance;
-type FundInfoOf<T> =
-	FundInfo<AccountIdOf<T>, BalanceOf<T>, <T as frame_system::Config>::BlockNumber>;
+type BalanceOf<T, I = ()> = <<T as Config<I>>::Currency as Currency<AccountIdOf<T>>>::Balance;
+type RewardBalanceOf<T, I = ()> =
+	<<T as Config<I>>::RewardCurrency as Currency<AccountIdOf<T>>>::Balance;
+type NegativeImbalanceOf<T, I = ()> =
+	<<T as Config<I>>::Currency as Currency<AccountIdOf<T>>>::NegativeImbalance;
+type MomentOf<T, I = ()> = <<T as Config<I>>::Time as Time>::Moment;
+type FundInfoOf<T, I = ()> =
+	FundInfo<AccountIdOf<T>, BalanceOf<T, I>, <T as frame_system::Config>::BlockNumber>;
+type GenesisFundOf<T, I = ()> = (
+	AccountIdOf<T>,
+	AccountIdOf<T>,
+	BalanceOf<T, I>,
+	BalanceOf<T, I>,
+	<T as frame_system::Config>::BlockNumber,
+	<T as frame_system::Config>::BlockNumber,
+);
 
 pub use pallet::*;
+pub use weights::WeightInfo;
 
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+mod migrations;
 #[cfg(test)]
 mod tests;
+pub mod weights;
 
-const PALLET_ID: ModuleId = ModuleId(*b"ex/cfund");
+/// The number of existing contributors `contribute` is weighed against. A child trie write only
+/// grows logarithmically with the number of entries, so larger funds are not much more costly.
+const CONTRIBUTE_WEIGHT_CONTRIBUTORS: u32 = 1_000;
 
 /// Simple index for identifying a fund.
 
 #[frame_support::pallet]
 pub mod pallet {
-	use crate::{AccountIdOf, BalanceOf, FundIndex, FundInfoOf};
+	use crate::{
+		AccountIdOf, BalanceOf, FundIndex, FundInfoOf, GenesisFundOf, MomentOf,
+		NegativeImbalanceOf, RewardBalanceOf, WeightInfo, CONTRIBUTE_WEIGHT_CONTRIBUTORS,
+	};
 	use frame_support::sp_runtime::traits::Zero;
 	use frame_support::traits::{
-		Currency, ExistenceRequirement, ReservableCurrency, WithdrawReasons,
+		BalanceStatus, Currency, ExistenceRequirement, Imbalance, OnUnbalanced, ReservableCurrency,
+		Time, WithdrawReasons,
+	};
+	use frame_support::{
+		dispatch::DispatchResultWithPostInfo, pallet_prelude::*, storage::child, transactional,
 	};
-	use frame_support::{dispatch::DispatchResultWithPostInfo, pallet_prelude::*};
 	use frame_system::pallet_prelude::*;
-	use sp_runtime::traits::Saturating;
+	use sp_runtime::{
+		traits::{CheckedAdd, Saturating},
+		FixedU128, ModuleId, Perbill,
+	};
+	use sp_std::prelude::*;
 
 	/// The pallet's configuration trait
 	#[pallet::config]
-	pub trait Config: frame_system::Config {
+	pub trait Config<I: 'static = ()>: frame_system::Config {
 		/// The ubiquious Event type
-		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		type Event: From<Event<Self, I>> + IsType<<Self as frame_system::Config>::Event>;
 
 		/// The currency in which the crowdfunds will be denominated
 		type Currency: ReservableCurrency<Self::AccountId>;
 
+		/// If true, contributions are reserved in the backers' own accounts rather than moved
+		/// into the fund's account. They are moved into the fund's account with `collect_reserves`
+		/// once the fund ends, before it can pay out or be dissolved. Only change this while there
+		/// are no funds.
+		type ReserveContributions: Get<bool>;
+
+		/// The id from which fund accounts and child tries are derived. Each instance of the
+		/// pallet in a runtime must have its own.
+		type ModuleId: Get<ModuleId>;
+
 		/// The amount to be held on deposit by the owner of a crowdfund
-		type SubmissionDeposit: Get<BalanceOf<Self>>;
+		type SubmissionDeposit: Get<BalanceOf<Self, I>>;
+
+		/// The extra amount held on deposit for each unit of a crowdfund's cap, so that funds that
+		/// may raise more pay a larger deposit
+		type DepositPerCapUnit: Get<BalanceOf<Self, I>>;
 
 		/// The minimum amount that may be contributed into a crowdfund. Should almost certainly be at
 		/// least ExistentialDeposit.
-		type MinContribution: Get<BalanceOf<Self>>;
+		type MinContribution: Get<BalanceOf<Self, I>>;
+
+		/// Contributions must be a multiple of this amount, so that no contribution is dust.
+		/// Zero allows any amount.
+		type ContributionIncrement: Get<BalanceOf<Self, I>>;
 
-		/// The period of time (in blocks) after an unsuccessful crowdfund ending during which
-		/// contributors are able to withdraw their funds. After this period, their funds are lost.
+		/// The shortest period of time (in blocks) after an unsuccessful crowdfund ending during
+		/// which contributors are able to withdraw their funds. Each fund sets its own period, no
+		/// shorter than this. After the period, their funds are lost.
 		type RetirementPeriod: Get<Self::BlockNumber>;
+
+		/// The source of wall-clock time, usually the timestamp pallet
+		type Time: Time;
+
+		/// If set, retirement also waits for this much time (e.g. milliseconds for the timestamp
+		/// pallet) to pass from the fund's end, on top of the fund's own retirement period, so
+		/// that it does not depend on block times alone.
+		type RetirementDuration: Get<Option<MomentOf<Self, I>>>;
+
+		/// The origin allowed to create funds, which resolves to the account that owns the fund and
+		/// pays the deposit. Use `EnsureSigned` to let anyone do it.
+		type CreateOrigin: EnsureOrigin<Self::Origin, Success = Self::AccountId>;
+
+		/// The origin allowed to dissolve retired funds, which resolves to the account that is paid
+		/// the keeper reward. Use `EnsureSigned` to let anyone do it.
+		type DissolveOrigin: EnsureOrigin<Self::Origin, Success = Self::AccountId>;
+
+		/// Where the contributions left in a fund go when it is dissolved
+		type DissolveDestination: OnUnbalanced<NegativeImbalanceOf<Self, I>>;
+
+		/// The least the account dissolving a fund is paid for doing so. It comes out of what is
+		/// left in the fund first and out of the owner's deposit after that, so for a small fund
+		/// it may take the whole leftover and deposit.
+		type MinKeeperReward: Get<BalanceOf<Self, I>>;
+
+		/// The origin allowed to cancel a fund and slash its owner's deposit, such as in response
+		/// to fraud
+		type SlashOrigin: EnsureOrigin<Self::Origin>;
+
+		/// Where slashed deposits go
+		type SlashDestination: OnUnbalanced<NegativeImbalanceOf<Self, I>>;
+
+		/// The portion of each contribution taken as a platform fee. The fee is rounded down.
+		type FeePercent: Get<Perbill>;
+
+		/// Where contribution fees go
+		type FeeCollector: OnUnbalanced<NegativeImbalanceOf<Self, I>>;
+
+		/// The token minted to contributors as a reward for backing a fund
+		type RewardCurrency: Currency<Self::AccountId>;
+
+		/// Units of `RewardCurrency` minted per unit contributed, after fees. The reward is
+		/// rounded down, so contributions too small to earn a whole unit earn nothing.
+		type RewardRate: Get<FixedU128>;
+
+		/// The portion of each contribution, after fees, that is matched from the fund's match
+		/// pool while the pool lasts
+		type MatchRatio: Get<Perbill>;
+
+		/// The maximum number of blocks by which `extend_end` may push back the end of a
+		/// crowdfund, in total, past the end it was created with.
+		type MaxExtension: Get<Self::BlockNumber>;
+
+		/// The maximum number of funds a single account may be backing at once
+		type MaxFundsPerContributor: Get<u32>;
+
+		/// The maximum number of funds a single account may own at once
+		type MaxFundsPerOwner: Get<u32>;
+
+		/// Whether the owner of a fund may contribute to it
+		type AllowOwnerContribution: Get<bool>;
+
+		/// The most a single account may contribute to one fund. Zero means there is no limit.
+		type MaxContributionPerAccount: Get<BalanceOf<Self, I>>;
+
+		/// The most that may be withdrawn from a single fund in one block, if limited
+		type MaxWithdrawPerBlock: Get<Option<BalanceOf<Self, I>>>;
+
+		/// The most contributions a single account may make in one block, across all funds.
+		/// Zero means there is no limit.
+		type MaxContributionsPerBlock: Get<u32>;
+
+		/// The maximum length, in bytes, of a fund's metadata
+		type MaxMetadataLen: Get<u32>;
+
+		/// The maximum number of milestones a fund may have
+		type MaxMilestones: Get<u32>;
+
+		/// The maximum number of bonus tiers a fund may have
+		type MaxBonusTiers: Get<u32>;
+
+		/// The maximum number of accounts on a fund's whitelist
+		type MaxWhitelistLen: Get<u32>;
+
+		/// The maximum number of installment pledges a single fund may have at once
+		type MaxInstallmentsPerFund: Get<u32>;
+
+		/// The maximum number of funds whose installment pledges are collected in one block. With
+		/// more funds than this, each is collected from in turn.
+		type MaxInstallmentFundsPerBlock: Get<u32>;
+
+		/// Weight information for the extrinsics in this pallet
+		type WeightInfo: WeightInfo;
 	}
 
 	#[derive(Encode, Decode, Default, PartialEq, Eq)]
 	#[cfg_attr(feature = "std", derive(Debug))]
 	pub struct FundInfo<AccountId, Balance, BlockNumber> {
+		/// The account that created the fund and is allowed to manage it
+		pub owner: AccountId,
 		/// The account that will receive the funds if the campaign is successful
 		pub beneficiary: AccountId,
 		/// The amount of deposit placed
 		pub deposit: Balance,
 		/// The total amount raised
 		pub raised: Balance,
-		/// Block number after which funding must have succeeded
+		/// The number of distinct accounts currently holding a contribution
+		pub contributors: u32,
+		/// Block number from which contributions are accepted
+		pub start: BlockNumber,
+		/// Block number after which funding must have succeeded. Brought forward to the block in
+		/// which the fund reaches its cap.
 		pub end: BlockNumber,
-		/// Upper bound on `raised`
+		/// The number of blocks after `end` before the fund may be dissolved. Never below
+		/// `Config::RetirementPeriod`.
+		pub retirement_period: BlockNumber,
+		/// The amount that must be raised for the fund to be successful
 		pub goal: Balance,
+		/// Upper bound on `raised`
+		pub cap: Balance,
+		/// The smallest contribution the fund accepts. Never below `Config::MinContribution`.
+		pub min_contribution: Balance,
+		/// The account matching contributions to this fund, if any
+		pub sponsor: Option<AccountId>,
+		/// The portion of `raised` that was provided by the sponsor
+		pub matched: Balance,
+		/// How the beneficiary's payout is released once the fund succeeds, if gradually
+		pub vesting: Option<VestingSchedule<BlockNumber, Balance>>,
+		/// The portion of `raised` already paid out through `claim_vested`
+		pub claimed: Balance,
+		/// What the fund is raising money for, so backers can find funds they are interested in
+		pub category: FundCategory,
+		/// Free-form data describing the fund, such as a name and description. Set once, when
+		/// the fund is created.
+		pub metadata: Vec<u8>,
+		/// Whether the owner has halted contributions. Withdrawals are unaffected.
+		pub paused: bool,
+		/// Whether the fund was cancelled by `SlashOrigin`. A cancelled fund has ended and
+		/// failed, whatever it raised.
+		pub cancelled: bool,
+	}
+
+	/// A linear release of a successful fund's proceeds
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub struct VestingSchedule<BlockNumber, Balance> {
+		/// Block number from which proceeds start to vest
+		pub cliff: BlockNumber,
+		/// The amount that vests in each block after the cliff
+		pub per_block: Balance,
+	}
+
+	/// A pledge paid into a fund a little at a time
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub struct InstallmentPledge<Balance> {
+		/// The amount still to be paid
+		pub remaining: Balance,
+		/// The amount paid at the start of each block
+		pub per_block: Balance,
+		/// Set when an installment could not be paid. Delinquent pledges are not collected.
+		pub delinquent: bool,
+	}
+
+	/// The state of a fund as seen by its backers
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub enum FundState {
+		/// The fund is still accepting contributions
+		Active,
+		/// The fund has ended and reached its goal
+		Succeeded,
+		/// The fund has ended without reaching its goal
+		Failed,
+	}
+
+	/// What a fund is raising money for
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub enum FundCategory {
+		/// Software, hardware and research
+		Tech,
+		/// Creative work
+		Art,
+		/// Local and public-good projects
+		Community,
+		/// Anything else
+		Other,
+	}
+
+	impl Default for FundCategory {
+		fn default() -> Self {
+			FundCategory::Other
+		}
 	}
 
+	/// How the cap of a new fund is given
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub enum CapMode<Balance> {
+		/// A fixed amount
+		Absolute(Balance),
+		/// A share of the currency's total issuance at the time the fund is created
+		IssuanceFraction(Perbill),
+	}
+
+	/// Where a fund is in its life, from creation until it is removed
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub enum FundPhase {
+		/// The fund does not accept contributions yet
+		NotStarted,
+		/// The fund is accepting contributions
+		Active,
+		/// The fund has ended and reached its goal, and its proceeds can be paid out
+		SucceededPendingClaim,
+		/// The fund has ended without reaching its goal, and backers can withdraw
+		Failed,
+		/// The fund may be dissolved soon, or already may be, whether it succeeded or not. This
+		/// starts once the fund's retirement period has passed since the end and, with timed
+		/// retirement, once `RetirementDuration` has as well.
+		Retiring,
+	}
+
+	/// The layout of the pallet's storage
+	///
+	/// Chains that ran the pallet before versioning was introduced have no version stored, so the
+	/// default is the first layout.
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub enum StorageVersion {
+		/// `FundInfo` only records the beneficiary, deposit, raised amount, end and goal
+		V1,
+		/// `FundInfo` records the owner, contributors, start, cap and payout terms
+		V2,
+		/// Child trie ids also depend on the name the pallet instance has in the runtime
+		V3,
+	}
+
+	impl Default for StorageVersion {
+		fn default() -> Self {
+			StorageVersion::V1
+		}
+	}
+
+	/// The layout of the pallet's storage, used to decide which migrations to run
+	#[pallet::storage]
+	#[pallet::getter(fn pallet_version)]
+	pub(super) type PalletVersion<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, StorageVersion, ValueQuery>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn funds)]
-	pub(super) type Funds<T: Config> =
-		StorageMap<_, Blake2_128Concat, FundIndex, FundInfoOf<T>, OptionQuery>;
+	pub(super) type Funds<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, FundInfoOf<T, I>, OptionQuery>;
 
 	#[pallet::storage]
 	#[pallet::getter(fn fund_count)]
-	pub(super) type FundCount<T: Config> = StorageValue<_, FundIndex, ValueQuery>;
+	pub(super) type FundCount<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, FundIndex, ValueQuery>;
+
+	/// The sum of `raised` across all funds that have not been dispensed or dissolved
+	#[pallet::storage]
+	#[pallet::getter(fn total_raised)]
+	pub(super) type TotalRaised<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, BalanceOf<T, I>, ValueQuery>;
+
+	/// The sum of contributions that backers never withdrew from funds that were dissolved
+	#[pallet::storage]
+	#[pallet::getter(fn lost_funds)]
+	pub(super) type LostFunds<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, BalanceOf<T, I>, ValueQuery>;
+
+	/// Funds whose reserved contributions have been moved into the fund's account, when
+	/// `ReserveContributions` is set
+	#[pallet::storage]
+	pub(super) type ReservesCollected<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, (), OptionQuery>;
+
+	/// The key of the last contribution whose reserve has been moved into the fund's account,
+	/// for funds whose reserves are partway through being collected
+	#[pallet::storage]
+	pub(super) type ReserveCursor<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, Vec<u8>, OptionQuery>;
+
+	/// The end each fund had before its first extension, which bounds any later extensions
+	#[pallet::storage]
+	#[pallet::getter(fn original_end)]
+	pub(super) type OriginalEnd<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, T::BlockNumber, OptionQuery>;
+
+	/// The moment after which a fund may be dissolved, when retirement is timed. It is
+	/// `RetirementDuration` after the moment the fund was seen to end.
+	#[pallet::storage]
+	#[pallet::getter(fn retire_after)]
+	pub(super) type RetireAfter<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, MomentOf<T, I>, OptionQuery>;
+
+	/// The amount withdrawn from each fund in the current block. Cleared at the start of every
+	/// block, and only tracked when `MaxWithdrawPerBlock` is set.
+	#[pallet::storage]
+	#[pallet::getter(fn withdrawn_this_block)]
+	pub(super) type WithdrawnThisBlock<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, BalanceOf<T, I>, ValueQuery>;
+
+	/// The number of contributions each account has made in the current block. Cleared at the
+	/// start of every block, and only tracked when `MaxContributionsPerBlock` is set.
+	#[pallet::storage]
+	#[pallet::getter(fn contributions_this_block)]
+	pub(super) type ContributionsThisBlock<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	/// The blocks after which portions of a successful fund's proceeds may be released, and the
+	/// portion of `raised` each one releases
+	#[pallet::storage]
+	#[pallet::getter(fn milestones)]
+	pub(super) type Milestones<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, Vec<(T::BlockNumber, Perbill)>, ValueQuery>;
+
+	/// The indices of the milestones that have been released for each fund
+	#[pallet::storage]
+	#[pallet::getter(fn released_milestones)]
+	pub(super) type ReleasedMilestones<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, Vec<u32>, ValueQuery>;
+
+	/// The bonus credited to early contributions to each fund, as the last block of each tier and
+	/// the portion of the contribution credited on top of it during that tier
+	#[pallet::storage]
+	#[pallet::getter(fn bonus_tiers)]
+	pub(super) type BonusTiers<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, Vec<(T::BlockNumber, Perbill)>, ValueQuery>;
+
+	/// The part of each recorded contribution that was credited as a bonus rather than paid in.
+	/// It counts toward the contribution, but not toward `raised`, and is never refunded.
+	#[pallet::storage]
+	#[pallet::getter(fn bonus)]
+	pub(super) type Bonuses<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		FundIndex,
+		Blake2_128Concat,
+		T::AccountId,
+		BalanceOf<T, I>,
+		ValueQuery,
+	>;
+
+	/// The only accounts allowed to contribute to each fund. A fund with no entries is open to
+	/// everyone.
+	#[pallet::storage]
+	pub(super) type Whitelist<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		FundIndex,
+		Blake2_128Concat,
+		T::AccountId,
+		(),
+		OptionQuery,
+	>;
+
+	/// Pledges to each fund that are paid in installments, by pledger
+	#[pallet::storage]
+	#[pallet::getter(fn installments)]
+	pub(super) type Installments<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		FundIndex,
+		Blake2_128Concat,
+		T::AccountId,
+		InstallmentPledge<BalanceOf<T, I>>,
+		OptionQuery,
+	>;
+
+	/// The number of installment pledges to each fund, bounded by `MaxInstallmentsPerFund`
+	#[pallet::storage]
+	#[pallet::getter(fn installment_count)]
+	pub(super) type InstallmentCount<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, u32, ValueQuery>;
+
+	/// The fund whose installment pledges are collected first in the next block
+	#[pallet::storage]
+	pub(super) type InstallmentCursor<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, FundIndex, ValueQuery>;
+
+	/// The amount each fund's sponsor has set aside to match future contributions. It is held in
+	/// the fund's account, but only counts toward `raised` as it is drawn.
+	#[pallet::storage]
+	#[pallet::getter(fn match_pool)]
+	pub(super) type MatchPool<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, BalanceOf<T, I>, ValueQuery>;
+
+	/// The total value, after fees, of contributions to each fund that credited each referrer
+	#[pallet::storage]
+	#[pallet::getter(fn referrals)]
+	pub(super) type Referrals<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		FundIndex,
+		Blake2_128Concat,
+		T::AccountId,
+		BalanceOf<T, I>,
+		ValueQuery,
+	>;
+
+	/// The phase each fund was last seen in, used to report changes in phase
+	#[pallet::storage]
+	pub(super) type ReportedStatus<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, FundIndex, FundPhase, OptionQuery>;
+
+	/// The funds whose phase may change in each block, so that only they are checked then
+	#[pallet::storage]
+	#[pallet::getter(fn status_schedule)]
+	pub(super) type StatusSchedule<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, T::BlockNumber, Vec<FundIndex>, ValueQuery>;
+
+	/// The funds each account owns that have not been dispensed or dissolved, so they can be
+	/// listed without scanning every fund
+	#[pallet::storage]
+	pub(super) type OwnerFunds<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		FundIndex,
+		(),
+		OptionQuery,
+	>;
+
+	/// The funds in each category that have not been dispensed or dissolved
+	#[pallet::storage]
+	pub(super) type CategoryIndex<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		FundCategory,
+		Blake2_128Concat,
+		FundIndex,
+		(),
+		OptionQuery,
+	>;
+
+	/// The number of funds each account owns that have not been dispensed or dissolved
+	#[pallet::storage]
+	#[pallet::getter(fn owner_fund_count)]
+	pub(super) type OwnerFundCount<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	/// The funds each account holds a contribution in. Entries for funds that have since been
+	/// dispensed or dissolved are pruned the next time the account backs a new fund.
+	#[pallet::storage]
+	#[pallet::getter(fn backed_funds)]
+	pub(super) type BackedFunds<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, Vec<FundIndex>, ValueQuery>;
 
 	#[pallet::event]
 	#[pallet::metadata(T::AccountId = "AccountId")]
 	#[pallet::generate_deposit(pub (super) fn deposit_event)]
-	pub enum Event<T: Config> {
+	pub enum Event<T: Config<I>, I: 'static = ()> {
 		Created(FundIndex, T::BlockNumber),
-		Contributed(T::AccountId, FundIndex, BalanceOf<T>, T::BlockNumber),
-		Withdrew(T::AccountId, FundIndex, BalanceOf<T>, T::BlockNumber),
+		/// A contribution was made, as the contributor, the fund, the amount that counts toward
+		/// the fund, the amount matched from the match pool, the reward minted to the
+		/// contributor, the fund's total after both, the portion of the cap that total makes up,
+		/// and the block
+		Contributed(
+			T::AccountId,
+			FundIndex,
+			BalanceOf<T, I>,
+			BalanceOf<T, I>,
+			RewardBalanceOf<T, I>,
+			BalanceOf<T, I>,
+			Perbill,
+			T::BlockNumber,
+		),
+		/// A contribution was withdrawn, as the contributor, the fund, the amount, the block, and
+		/// the account it was paid to
+		Withdrew(
+			T::AccountId,
+			FundIndex,
+			BalanceOf<T, I>,
+			T::BlockNumber,
+			T::AccountId,
+		),
 		Retiring(FundIndex, T::BlockNumber),
-		Dissolved(FundIndex, T::BlockNumber, T::AccountId),
+		/// A fund was dissolved, as the fund, the block, the account that dissolved it, and the
+		/// deposit plus the contributions still in the fund, leaving out any dust
+		Dissolved(FundIndex, T::BlockNumber, T::AccountId, BalanceOf<T, I>),
 		Dispensed(FundIndex, T::BlockNumber, T::AccountId),
+		EndExtended(FundIndex, T::BlockNumber),
+		Matched(T::AccountId, FundIndex, BalanceOf<T, I>, T::BlockNumber),
+		MatchRefunded(T::AccountId, FundIndex, BalanceOf<T, I>, T::BlockNumber),
+		Refunded(FundIndex, u32),
+		IntentRegistered(T::AccountId, FundIndex, BalanceOf<T, I>),
+		VestingSet(FundIndex),
+		VestedClaimed(FundIndex, BalanceOf<T, I>, T::BlockNumber),
+		MilestonesSet(FundIndex),
+		MilestoneReleased(FundIndex, u32, BalanceOf<T, I>),
+		WhitelistSet(FundIndex, u32),
+		BonusTiersSet(FundIndex),
+		CapIncreased(FundIndex, BalanceOf<T, I>),
+		Claimed(FundIndex, BalanceOf<T, I>, T::AccountId),
+		PausedSet(FundIndex, bool),
+		FundsLost(FundIndex, BalanceOf<T, I>),
+		OwnershipTransferred(FundIndex, T::AccountId, T::AccountId),
+		CapReached(FundIndex, T::BlockNumber),
+		InstallmentsPledged(T::AccountId, FundIndex, BalanceOf<T, I>, BalanceOf<T, I>),
+		InstallmentPaid(T::AccountId, FundIndex, BalanceOf<T, I>),
+		InstallmentDelinquent(T::AccountId, FundIndex),
+		StatusChanged(FundIndex, FundPhase),
+		Referred(FundIndex, T::AccountId, BalanceOf<T, I>),
+		MatchPoolFunded(T::AccountId, FundIndex, BalanceOf<T, I>),
+		DepositSlashed(FundIndex, BalanceOf<T, I>),
+		/// Every reserved contribution to a fund has been moved into its account
+		ReservesCollected(FundIndex),
 	}
 
 	#[pallet::error]
-	pub enum Error<T> {
+	pub enum Error<T, I = ()> {
 		/// Crowdfund must end after it starts
 		EndTooEarly,
+		/// A fund's goal may not be above its cap, or it could never succeed
+		GoalAboveCap,
 		/// Must contribute at least the minimum amount of funds
 		ContributionTooSmall,
+		/// A fund's minimum contribution may not be below `MinContribution`
+		MinContributionTooLow,
+		/// Contributions must be a multiple of `ContributionIncrement`
+		NotAnIncrement,
 		/// The fund index specified does not exist
 		InvalidIndex,
 		/// The crowdfund's contribution period has ended; no more contributions will be accepted
@@ -116,253 +651,1359 @@ pub mod pallet {
 		FundNotRetired,
 		/// Cannot dispense funds from an unsuccessful fund
 		UnsuccessfulFund,
+		/// You cannot withdraw more than you have contributed
+		InsufficientContribution,
+		/// Only the owner of a fund may perform this action
+		NotOwner,
+		/// A fund's end may only be moved later
+		EndNotExtended,
+		/// The end would be pushed back more than `MaxExtension` past the original end
+		ExtensionTooLong,
+		/// Another account is already matching contributions to this fund
+		SponsorAlreadySet,
+		/// Only the sponsor of a fund may reclaim matched funds
+		NotSponsor,
+		/// Matched funds cannot be reclaimed from a successful fund
+		SuccessfulFund,
+		/// The contribution would raise more than the fund's cap
+		CapExceeded,
+		/// The contribution would overflow the fund's balance
+		Overflow,
+		/// You are already backing the maximum number of funds
+		TooManyBackedFunds,
+		/// The crowdfund has not started accepting contributions yet
+		FundNotStarted,
+		/// Intents may only be registered before the crowdfund starts
+		FundAlreadyStarted,
+		/// Retirement is measured in blocks, so it is reported without being asked
+		RetirementNotTimed,
+		/// The fund's retirement has already been reported
+		AlreadyRetiring,
+		/// A fund cannot be dissolved before it ends
+		CannotDissolveActiveFund,
+		/// The fund has already been dissolved or dispensed
+		AlreadyDissolved,
+		/// The withdrawal would exceed the amount that may leave the fund in this block
+		WithdrawRateLimited,
+		/// The contribution would take the account past the per-account contribution cap
+		ContributionCapExceeded,
+		/// The fund's terms cannot change once it has been backed
+		FundAlreadyBacked,
+		/// No more of the fund's proceeds have vested since the last claim
+		NothingToClaim,
+		/// The fund's proceeds must be fully claimed before it is dispensed
+		VestingIncomplete,
+		/// The fund's metadata is longer than `MaxMetadataLen`
+		MetadataTooLong,
+		/// A fund may not have more than `MaxMilestones` milestones
+		TooManyMilestones,
+		/// The milestones would release more than the fund raised
+		MilestonesExceedRaised,
+		/// The milestone index specified does not exist
+		InvalidMilestone,
+		/// The milestone's block has not been reached yet
+		MilestoneNotReached,
+		/// The milestone has already been released
+		MilestoneAlreadyReleased,
+		/// The fund's proceeds are released by milestone, and some milestones are outstanding
+		MilestonesPending,
+		/// The fund only accepts contributions from whitelisted accounts
+		NotWhitelisted,
+		/// A fund may not have more than `MaxBonusTiers` bonus tiers
+		TooManyBonusTiers,
+		/// A fund's whitelist may not have more than `MaxWhitelistLen` accounts
+		WhitelistTooLong,
+		/// The new cap must be higher than the current one
+		CapNotIncreased,
+		/// You already own the maximum number of funds
+		TooManyFunds,
+		/// The fund's owner has paused contributions
+		FundPaused,
+		/// The fund reached its cap and closed early; no more contributions will be accepted
+		FundClosed,
+		/// The owner of a fund may not contribute to it
+		OwnerCannotContribute,
+		/// Installments must be at least the fund's minimum contribution and divide the total
+		InvalidInstallments,
+		/// You cannot refer your own contribution
+		SelfReferral,
+		/// The fund has already been cancelled
+		AlreadyCancelled,
+		/// You have made as many contributions as you may in this block
+		TooManyContributionsThisBlock,
+		/// The retirement period is below the minimum, `RetirementPeriod`
+		RetirementPeriodTooShort,
+		/// The destination would hold less than the existential deposit once paid
+		DestinationBelowMinimum,
+		/// The fund already has `MaxInstallmentsPerFund` installment pledges
+		TooManyInstallments,
+		/// The fund's reserved contributions must be collected with `collect_reserves` first
+		ReservesNotCollected,
+		/// The fund holds no reserved contributions to collect
+		NoReservesToCollect,
+		/// The fund has no deposit left to slash
+		NoDepositToSlash,
+		/// Some of the fund's proceeds have already been paid out
+		ProceedsAlreadyClaimed,
+		/// Must withdraw a non-zero amount
+		ZeroWithdrawal,
 	}
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub (super) trait Store)]
-	pub struct Pallet<T>(PhantomData<T>);
+	pub struct Pallet<T, I = ()>(PhantomData<(T, I)>);
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config<I>, I: 'static = ()> {
+		/// Funds to create at genesis, as `(owner, beneficiary, goal, cap, start, end)`, with no
+		/// metadata, `MinContribution` as their minimum contribution, `RetirementPeriod` as their
+		/// retirement period, and `FundCategory::Other` as their category. Each owner pays the
+		/// submission deposit as usual. Funds are given indices in the order they are listed,
+		/// starting at 0, so the first dispatched `create` gets the index after the last of these.
+		pub funds: Vec<GenesisFundOf<T, I>>,
+		/// Contributions to record at genesis, as `(index, contributor, amount)`, for funds
+		/// created above. They are written straight into the fund's child trie and added to what
+		/// it has raised, without limits, fees or bonuses. Nothing is paid in for them, so the fund
+		/// account must be endowed separately for them to be withdrawn or paid out.
+		pub contributions: Vec<(FundIndex, AccountIdOf<T>, BalanceOf<T, I>)>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config<I>, I: 'static> Default for GenesisConfig<T, I> {
+		fn default() -> Self {
+			Self {
+				funds: Vec::new(),
+				contributions: Vec::new(),
+			}
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config<I>, I: 'static> GenesisBuild<T, I> for GenesisConfig<T, I> {
+		fn build(&self) {
+			// New chains start with the current layout
+			PalletVersion::<T, I>::put(StorageVersion::V3);
+			for (owner, beneficiary, goal, cap, start, end) in &self.funds {
+				Pallet::<T, I>::do_create(
+					owner.clone(),
+					beneficiary.clone(),
+					*goal,
+					*cap,
+					T::MinContribution::get(),
+					*start,
+					*end,
+					T::RetirementPeriod::get(),
+					FundCategory::Other,
+					Vec::new(),
+				)
+				.expect("genesis fund owners can pay the deposit and funds end after genesis");
+			}
+			for (index, who, amount) in &self.contributions {
+				let mut fund = Pallet::<T, I>::funds(index)
+					.expect("genesis contributions are to genesis funds");
+				let balance = Pallet::<T, I>::contribution_get(*index, who);
+				if balance.is_zero() {
+					fund.contributors = fund.contributors.saturating_add(1);
+					BackedFunds::<T, I>::mutate(who, |funds| funds.push(*index));
+				}
+				Pallet::<T, I>::contribution_put(*index, who, &balance.saturating_add(*amount));
+				fund.raised = fund.raised.saturating_add(*amount);
+				TotalRaised::<T, I>::mutate(|total| *total = total.saturating_add(*amount));
+				Funds::<T, I>::insert(index, &fund);
+			}
+		}
+	}
 
 	#[pallet::hooks]
-	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+	impl<T: Config<I>, I: 'static> Hooks<BlockNumberFor<T>> for Pallet<T, I> {
+		fn on_initialize(n: T::BlockNumber) -> Weight {
+			// Withdrawal and contribution limits apply per block, so whatever the last block
+			// recorded is cleared, one entry at a time
+			let cleared = WithdrawnThisBlock::<T, I>::drain()
+				.count()
+				.saturating_add(ContributionsThisBlock::<T, I>::drain().count())
+				as Weight;
+			T::DbWeight::get()
+				.reads_writes(cleared.saturating_add(2), cleared)
+				.saturating_add(Self::collect_installments(n))
+				.saturating_add(Self::report_status_changes(n))
+		}
+
+		fn on_runtime_upgrade() -> Weight {
+			let weight = match Self::pallet_version() {
+				// Contributions are moved straight into the current child tries
+				StorageVersion::V1 => crate::migrations::migrate_to_v2::<T, I>(),
+				StorageVersion::V2 => crate::migrations::migrate_to_v3::<T, I>(),
+				StorageVersion::V3 => return T::DbWeight::get().reads(1),
+			};
+			PalletVersion::<T, I>::put(StorageVersion::V3);
+			weight.saturating_add(T::DbWeight::get().reads_writes(1, 1))
+		}
+	}
 
 	#[pallet::call]
-	impl<T: Config> Pallet<T> {
-		/// Create a new fund
-		#[pallet::weight(10_000)]
+	impl<T: Config<I>, I: 'static> Pallet<T, I> {
+		/// Create a new fund. Contributions below `min_contribution` are rejected, and it may not
+		/// be below `MinContribution`. A cap given as a share of total issuance is fixed at
+		/// creation, and does not follow the issuance afterwards. A `start` in the past is
+		/// moved up to the current block.
+		#[pallet::weight(T::WeightInfo::create())]
+		#[allow(clippy::too_many_arguments)]
 		pub fn create(
 			origin: OriginFor<T>,
 			beneficiary: AccountIdOf<T>,
-			goal: BalanceOf<T>,
+			goal: BalanceOf<T, I>,
+			cap: CapMode<BalanceOf<T, I>>,
+			min_contribution: BalanceOf<T, I>,
+			start: T::BlockNumber,
 			end: T::BlockNumber,
+			retirement_period: T::BlockNumber,
+			category: FundCategory,
+			metadata: Vec<u8>,
 		) -> DispatchResultWithPostInfo {
-			let creator = ensure_signed(origin)?;
+			let creator = T::CreateOrigin::ensure_origin(origin)?;
 			let now = <frame_system::Module<T>>::block_number();
 
-			ensure!(end > now, <Error<T>>::EndTooEarly);
-
-			let deposit = T::SubmissionDeposit::get();
-			let imb = T::Currency::withdraw(
-				&creator,
-				deposit,
-				WithdrawReasons::TRANSFER,
-				ExistenceRequirement::AllowDeath,
+			let index = Self::do_create(
+				creator,
+				beneficiary,
+				goal,
+				Self::resolve_cap(cap),
+				min_contribution,
+				start,
+				end,
+				retirement_period,
+				category,
+				metadata,
 			)?;
 
-			let index = FundCount::<T>::get();
-			// not protected against overflow, see safemath section
-			FundCount::<T>::put(index + 1);
+			Self::deposit_event(Event::Created(index, now));
+			Ok(().into())
+		}
+
+		/// Contribute funds to an existing fund. The weight charged depends on how many
+		/// contributors the fund actually has, rather than the worst case.
+		#[pallet::weight(T::WeightInfo::contribute(CONTRIBUTE_WEIGHT_CONTRIBUTORS))]
+		pub fn contribute(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			value: BalanceOf<T, I>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			Self::do_contribute(who, index, value)?;
+			Ok(Some(Self::contribute_weight(index)).into())
+		}
+
+		/// Contribute funds to an existing fund, crediting `referrer` with what `value` brings in
+		/// once the fee is taken out
+		#[pallet::weight(T::WeightInfo::contribute(CONTRIBUTE_WEIGHT_CONTRIBUTORS))]
+		pub fn contribute_with_referral(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			value: BalanceOf<T, I>,
+			referrer: AccountIdOf<T>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			ensure!(who != referrer, Error::<T, I>::SelfReferral);
 
-			// No fees are paid here if we need to create this account; that's why we don't just
-			// use the stock `transfer`.
-			T::Currency::resolve_creating(&Self::fund_account_id(index), imb);
+			let net = Self::do_contribute(who, index, value)?;
+			Referrals::<T, I>::mutate(index, &referrer, |total| *total = total.saturating_add(net));
 
-			<Funds<T>>::insert(
-				index,
-				FundInfo {
-					beneficiary,
-					deposit,
-					raised: Zero::zero(),
-					end,
-					goal,
-				},
-			);
+			Self::deposit_event(Event::Referred(index, referrer, net));
+			Ok(Some(Self::contribute_weight(index)).into())
+		}
 
+		/// Create a new fund and back it in the same call. If the fund has already started, the
+		/// creator contributes `value`; otherwise `value` is registered as the creator's intent.
+		/// Nothing is created if the contribution fails.
+		#[pallet::weight(T::WeightInfo::create().saturating_add(T::WeightInfo::contribute(0)))]
+		#[transactional]
+		#[allow(clippy::too_many_arguments)]
+		pub fn create_and_contribute(
+			origin: OriginFor<T>,
+			beneficiary: AccountIdOf<T>,
+			goal: BalanceOf<T, I>,
+			cap: CapMode<BalanceOf<T, I>>,
+			min_contribution: BalanceOf<T, I>,
+			start: T::BlockNumber,
+			end: T::BlockNumber,
+			retirement_period: T::BlockNumber,
+			category: FundCategory,
+			metadata: Vec<u8>,
+			value: BalanceOf<T, I>,
+		) -> DispatchResultWithPostInfo {
+			let creator = T::CreateOrigin::ensure_origin(origin)?;
+			let now = <frame_system::Module<T>>::block_number();
+
+			let index = Self::do_create(
+				creator.clone(),
+				beneficiary,
+				goal,
+				Self::resolve_cap(cap),
+				min_contribution,
+				start,
+				end,
+				retirement_period,
+				category,
+				metadata,
+			)?;
 			Self::deposit_event(Event::Created(index, now));
+
+			if start <= now {
+				Self::do_contribute(creator, index, value)?;
+			} else {
+				Self::intent_put(index, &creator, &value);
+				Self::deposit_event(Event::IntentRegistered(creator, index, value));
+			}
 			Ok(().into())
 		}
 
-		/// Contribute funds to an existing fund
+		/// Register a non-binding pledge to a fund that has not started yet. No funds are moved,
+		/// and the intent does not count toward `raised`. Registering again replaces the amount.
 		#[pallet::weight(10_000)]
-		pub fn contribute(
+		pub fn register_intent(
 			origin: OriginFor<T>,
 			index: FundIndex,
-			value: BalanceOf<T>,
+			amount: BalanceOf<T, I>,
 		) -> DispatchResultWithPostInfo {
 			let who = ensure_signed(origin)?;
 
-			ensure!(
-				value >= T::MinContribution::get(),
-				Error::<T>::ContributionTooSmall
-			);
-			let mut fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+			let fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(now < fund.start, Error::<T, I>::FundAlreadyStarted);
+
+			Self::intent_put(index, &who, &amount);
+
+			Self::deposit_event(Event::IntentRegistered(who, index, amount));
+			Ok(().into())
+		}
+
+		/// Push back the end of a fund that is still running, to no more than `MaxExtension` blocks
+		/// past the end it was created with. Only the owner may do this.
+		#[pallet::weight(10_000)]
+		pub fn extend_end(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			new_end: T::BlockNumber,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let mut fund = Self::ensure_owner(index, &who)?;
 
 			// Make sure crowdfund has not ended
 			let now = <frame_system::Module<T>>::block_number();
-			ensure!(fund.end > now, Error::<T>::ContributionPeriodOver);
+			ensure!(fund.end > now, Error::<T, I>::ContributionPeriodOver);
 
-			// Add contribution to the fund
-			T::Currency::transfer(
-				&who,
-				&Self::fund_account_id(index),
-				value,
-				ExistenceRequirement::AllowDeath,
-			)?;
-			fund.raised += value;
-			Funds::<T>::insert(index, &fund);
+			ensure!(new_end > fund.end, Error::<T, I>::EndNotExtended);
+			let original_end = Self::original_end(index).unwrap_or(fund.end);
+			ensure!(
+				new_end <= original_end.saturating_add(T::MaxExtension::get()),
+				Error::<T, I>::ExtensionTooLong
+			);
 
-			let balance = Self::contribution_get(index, &who);
-			let balance = balance.saturating_add(value);
-			Self::contribution_put(index, &who, &balance);
+			OriginalEnd::<T, I>::insert(index, original_end);
+			fund.end = new_end;
+			<Funds<T, I>>::insert(index, &fund);
+			Self::schedule_status_checks(index, &fund);
 
-			Self::deposit_event(Event::Contributed(who, index, balance, now));
+			Self::deposit_event(Event::EndExtended(index, new_end));
 			Ok(().into())
 		}
 
-		/// Withdraw full balance of a contributor to a fund
+		/// Raise the cap of a fund that is still running. The cap can never be lowered, since
+		/// the fund may already have raised more than a lower cap. The owner tops up the deposit
+		/// to what a fund with the new cap would require. Only the owner may do this.
 		#[pallet::weight(10_000)]
-		pub fn withdraw(origin: OriginFor<T>, index: FundIndex) -> DispatchResultWithPostInfo {
+		pub fn increase_cap(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			new_cap: BalanceOf<T, I>,
+		) -> DispatchResultWithPostInfo {
 			let who = ensure_signed(origin)?;
 
-			let mut fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+			let mut fund = Self::ensure_owner(index, &who)?;
+
+			// Make sure crowdfund has not ended
 			let now = <frame_system::Module<T>>::block_number();
-			ensure!(fund.end < now, Error::<T>::FundStillActive);
+			ensure!(fund.end > now, Error::<T, I>::ContributionPeriodOver);
 
-			let balance = Self::contribution_get(index, &who);
-			ensure!(balance > Zero::zero(), Error::<T>::NoContribution);
+			ensure!(new_cap > fund.cap, Error::<T, I>::CapNotIncreased);
 
-			// Return funds to caller without charging a transfer fee
-			let _ = T::Currency::resolve_into_existing(
-				&who,
-				T::Currency::withdraw(
+			let extra = Self::deposit_for(new_cap).saturating_sub(fund.deposit);
+			if !extra.is_zero() {
+				T::Currency::transfer(
+					&who,
 					&Self::fund_account_id(index),
-					balance,
-					WithdrawReasons::TRANSFER,
+					extra,
 					ExistenceRequirement::AllowDeath,
-				)?,
-			);
+				)?;
+				fund.deposit = fund.deposit.saturating_add(extra);
+			}
+			fund.cap = new_cap;
+			<Funds<T, I>>::insert(index, &fund);
 
-			// Update storage
-			Self::contribution_kill(index, &who);
-			fund.raised = fund.raised.saturating_sub(balance);
-			<Funds<T>>::insert(index, &fund);
-
-			Self::deposit_event(Event::Withdrew(who, index, balance, now));
+			Self::deposit_event(Event::CapIncreased(index, new_cap));
 			Ok(().into())
 		}
 
-		/// Dissolve an entire crowdfund after its retirement period has expired.
-		/// Anyone can call this function, and they are incentivized to do so because
-		/// they inherit the deposit.
+		/// Hand the fund over to `new_owner`, who may then manage it in place of the caller. The
+		/// submission deposit stays with the fund, and is returned to whoever owns it when it is
+		/// claimed or dissolved. Only the owner may do this.
 		#[pallet::weight(10_000)]
-		pub fn dissolve(origin: OriginFor<T>, index: FundIndex) -> DispatchResultWithPostInfo {
-			let reporter = ensure_signed(origin)?;
+		pub fn transfer_ownership(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			new_owner: AccountIdOf<T>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
 
-			let fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+			let mut fund = Self::ensure_owner(index, &who)?;
+			if new_owner != who {
+				let owned = Self::owner_fund_count(&new_owner);
+				ensure!(
+					owned < T::MaxFundsPerOwner::get(),
+					Error::<T, I>::TooManyFunds
+				);
+				OwnerFundCount::<T, I>::mutate(&who, |count| *count = count.saturating_sub(1));
+				OwnerFundCount::<T, I>::insert(&new_owner, owned + 1);
+				OwnerFunds::<T, I>::remove(&who, index);
+				OwnerFunds::<T, I>::insert(&new_owner, index, ());
+			}
+			fund.owner = new_owner.clone();
+			<Funds<T, I>>::insert(index, &fund);
+
+			Self::deposit_event(Event::OwnershipTransferred(index, who, new_owner));
+			Ok(().into())
+		}
 
-			// Check that enough time has passed to remove from storage
+		/// Pledge to pay `total` into a fund in installments of `per_block`, collected at the
+		/// start of each block while the fund is open. Each installment counts as a contribution.
+		/// If one cannot be paid, the pledge is marked delinquent and no more are collected.
+		/// Pledging again replaces any earlier pledge to the same fund. A fund may have at most
+		/// `MaxInstallmentsPerFund` pledges, and when more than `MaxInstallmentFundsPerBlock` funds
+		/// exist, installments are collected from them in turn rather than every block.
+		#[pallet::weight(10_000)]
+		pub fn pledge_installments(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			total: BalanceOf<T, I>,
+			per_block: BalanceOf<T, I>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
 			let now = <frame_system::Module<T>>::block_number();
+			ensure!(fund.end > now, Error::<T, I>::ContributionPeriodOver);
 			ensure!(
-				now >= fund.end + T::RetirementPeriod::get(),
-				Error::<T>::FundNotRetired
+				!per_block.is_zero()
+					&& per_block >= fund.min_contribution
+					&& !total.is_zero()
+					&& (total % per_block).is_zero(),
+				Error::<T, I>::InvalidInstallments
 			);
-
-			let account = Self::fund_account_id(index);
-
-			// Dissolver collects the deposit and any remaining funds
-			let _ = T::Currency::resolve_creating(
-				&reporter,
-				T::Currency::withdraw(
-					&account,
-					fund.deposit + fund.raised,
-					WithdrawReasons::TRANSFER,
-					ExistenceRequirement::AllowDeath,
-				)?,
+			// Each installment is a contribution, so it has to be one `contribute` would accept
+			let increment = T::ContributionIncrement::get();
+			ensure!(
+				increment.is_zero() || (per_block % increment).is_zero(),
+				Error::<T, I>::NotAnIncrement
 			);
 
-			// Remove the fund info from storage
-			<Funds<T>>::remove(index);
-			// Remove all the contributor info from storage in a single write.
-			// This is possible thanks to the use of a child tree.
-			Self::crowdfund_kill(index);
-
-			Self::deposit_event(Event::Dissolved(index, now, reporter));
+			if !Installments::<T, I>::contains_key(index, &who) {
+				let count = Self::installment_count(index);
+				ensure!(
+					count < T::MaxInstallmentsPerFund::get(),
+					Error::<T, I>::TooManyInstallments
+				);
+				InstallmentCount::<T, I>::insert(index, count + 1);
+			}
+			let pledge = InstallmentPledge {
+				remaining: total,
+				per_block,
+				delinquent: false,
+			};
+			Installments::<T, I>::insert(index, &who, pledge);
+
+			Self::deposit_event(Event::InstallmentsPledged(who, index, total, per_block));
 			Ok(().into())
 		}
 
-		/// Dispense a payment to the beneficiary of a successful crowdfund.
-		/// The beneficiary receives the contributed funds and the caller receives
-		/// the deposit as a reward to incentivize clearing settled crowdfunds out of storage.
+		/// Halt or resume contributions to a fund, for example while it is under review. Backers
+		/// may still withdraw while the fund is paused. Only the owner may do this.
 		#[pallet::weight(10_000)]
-		pub fn dispense(origin: OriginFor<T>, index: FundIndex) -> DispatchResultWithPostInfo {
-			let caller = ensure_signed(origin)?;
-
-			let fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+		pub fn set_paused(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			paused: bool,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
 
-			// Check that enough time has passed to remove from storage
-			let now = <frame_system::Module<T>>::block_number();
+			let mut fund = Self::ensure_owner(index, &who)?;
 
-			ensure!(now >= fund.end, Error::<T>::FundStillActive);
+			fund.paused = paused;
+			<Funds<T, I>>::insert(index, &fund);
 
-			// Check that the fund was actually successful
-			ensure!(fund.raised >= fund.goal, Error::<T>::UnsuccessfulFund);
+			Self::deposit_event(Event::PausedSet(index, paused));
+			Ok(().into())
+		}
 
-			let account = Self::fund_account_id(index);
+		/// Match contributions to an existing fund as its sponsor. Matched funds count toward
+		/// `raised`, but are returned to the sponsor rather than the backers if the fund fails.
+		#[pallet::weight(10_000)]
+		pub fn match_funds(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			value: BalanceOf<T, I>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
 
-			// Beneficiary collects the contributed funds
-			let _ = T::Currency::resolve_creating(
-				&fund.beneficiary,
-				T::Currency::withdraw(
-					&account,
-					fund.raised,
-					WithdrawReasons::TRANSFER,
-					ExistenceRequirement::AllowDeath,
-				)?,
-			);
+			let mut fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(fund.start <= now, Error::<T, I>::FundNotStarted);
+			ensure!(!fund.paused, Error::<T, I>::FundPaused);
+
+			// Matched funds count toward the cap like any other contribution
+			let raised = fund
+				.raised
+				.checked_add(&value)
+				.ok_or(Error::<T, I>::Overflow)?;
+			ensure!(raised <= fund.cap, Error::<T, I>::CapExceeded);
+
+			Self::sponsor(index, &mut fund, &who, value)?;
+			fund.raised = raised;
+			TotalRaised::<T, I>::mutate(|total| *total = total.saturating_add(value));
+			fund.matched = fund.matched.saturating_add(value);
+			Funds::<T, I>::insert(index, &fund);
+
+			Self::deposit_event(Event::Matched(who, index, value, now));
+			Ok(().into())
+		}
 
-			// Caller collects the deposit
-			let _ = T::Currency::resolve_creating(
-				&caller,
-				T::Currency::withdraw(
-					&account,
-					fund.deposit,
-					WithdrawReasons::TRANSFER,
-					ExistenceRequirement::AllowDeath,
-				)?,
-			);
+		/// Set aside `amount` as the fund's sponsor to match future contributions, at
+		/// `MatchRatio` of each contribution until the pool runs out. What is drawn from the pool
+		/// counts as matched funds, as with `match_funds`, and whatever is left in it once the fund
+		/// ends goes back to the sponsor.
+		#[pallet::weight(10_000)]
+		pub fn fund_match_pool(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			amount: BalanceOf<T, I>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
 
-			// Remove the fund info from storage
-			<Funds<T>>::remove(index);
-			// Remove all the contributor info from storage in a single write.
-			// This is possible thanks to the use of a child tree.
-			Self::crowdfund_kill(index);
+			let mut fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+			Self::sponsor(index, &mut fund, &who, amount)?;
+			MatchPool::<T, I>::mutate(index, |pool| *pool = pool.saturating_add(amount));
+			Funds::<T, I>::insert(index, &fund);
 
-			Self::deposit_event(Event::Dispensed(index, now, caller));
+			Self::deposit_event(Event::MatchPoolFunded(who, index, amount));
+			Ok(().into())
+		}
+
+		/// Return what is left of the match pool of a fund that has ended to its sponsor, along
+		/// with the matched portion of `raised` if the fund failed
+		#[pallet::weight(10_000)]
+		pub fn withdraw_match(
+			origin: OriginFor<T>,
+			index: FundIndex,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let mut fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+			ensure!(
+				fund.sponsor.as_ref() == Some(&who),
+				Error::<T, I>::NotSponsor
+			);
+
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(
+				fund.end < now || fund.cancelled,
+				Error::<T, I>::FundStillActive
+			);
+			// Matched funds belong to the beneficiary of a successful fund
+			ensure!(
+				!Self::succeeded(&fund) || !Self::match_pool(index).is_zero(),
+				Error::<T, I>::SuccessfulFund
+			);
+			let amount = Self::refund_sponsor(index, &mut fund)?;
+			ensure!(amount > Zero::zero(), Error::<T, I>::NoContribution);
+			<Funds<T, I>>::insert(index, &fund);
+			Ok(().into())
+		}
+
+		/// Withdraw full balance of a contributor to a fund
+		#[pallet::weight(T::WeightInfo::withdraw(T::MaxFundsPerContributor::get()))]
+		pub fn withdraw(origin: OriginFor<T>, index: FundIndex) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			let weight = Self::withdraw_weight(&who);
+			Self::do_withdraw(who.clone(), index, who)?;
+			Ok(Some(weight).into())
+		}
+
+		/// Withdraw full balance of a contributor to a fund, paying it to `dest` rather than the
+		/// contributor, such as to move it from a hot key to cold storage. `dest` must hold at
+		/// least the existential deposit once paid.
+		#[pallet::weight(T::WeightInfo::withdraw(T::MaxFundsPerContributor::get()))]
+		pub fn withdraw_to(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			dest: AccountIdOf<T>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			let weight = Self::withdraw_weight(&who);
+			Self::do_withdraw(who, index, dest)?;
+			Ok(Some(weight).into())
+		}
+
+		/// Withdraw part of a contributor's balance from a fund
+		#[pallet::weight(10_000)]
+		pub fn withdraw_partial(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			amount: BalanceOf<T, I>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let mut fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(
+				fund.end < now || fund.cancelled,
+				Error::<T, I>::FundStillActive
+			);
+
+			let balance = Self::contribution_get(index, &who);
+			ensure!(balance > Zero::zero(), Error::<T, I>::NoContribution);
+			// Any bonus was never paid in, so it cannot be withdrawn
+			let bonus = Self::bonus(index, &who);
+			let paid = balance.saturating_sub(bonus);
+			ensure!(!amount.is_zero(), Error::<T, I>::ZeroWithdrawal);
+			ensure!(amount <= paid, Error::<T, I>::InsufficientContribution);
+			let withdrawn = Self::withdrawn_after(index, amount)?;
+
+			Self::refund(index, &who, &who, amount)?;
+			Self::note_withdrawn(index, withdrawn);
+
+			// Update storage, clearing the child trie entry and the bonus if nothing paid in is
+			// left
+			let remaining = paid.saturating_sub(amount);
+			if remaining.is_zero() {
+				Self::contribution_kill(index, &who);
+				Bonuses::<T, I>::remove(index, &who);
+				Self::unback(&who, index);
+				fund.contributors = fund.contributors.saturating_sub(1);
+			} else {
+				Self::contribution_put(index, &who, &remaining.saturating_add(bonus));
+			}
+			fund.raised = fund.raised.saturating_sub(amount);
+			TotalRaised::<T, I>::mutate(|total| *total = total.saturating_sub(amount));
+			<Funds<T, I>>::insert(index, &fund);
+
+			Self::deposit_event(Event::Withdrew(who.clone(), index, amount, now, who));
+			Ok(().into())
+		}
+
+		/// Refund up to `limit` contributors of a failed fund in a single call. Anyone can call
+		/// this function to settle a failed fund without waiting for every contributor to withdraw.
+		/// Refunds stop early once `MaxWithdrawPerBlock` has been reached for the fund, and if any
+		/// refund fails, none are made. Entries whose key is not an account cannot be refunded, so
+		/// they are skipped and left in place. The sponsor gets back the matched funds and whatever
+		/// is left of the match pool on the first call. Only the entries actually visited are
+		/// charged for.
+		#[pallet::weight((10_000 as Weight).saturating_mul(*limit as Weight))]
+		#[transactional]
+		pub fn refund_all(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			limit: u32,
+		) -> DispatchResultWithPostInfo {
+			ensure_signed(origin)?;
+
+			let mut fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(
+				fund.end < now || fund.cancelled,
+				Error::<T, I>::FundStillActive
+			);
+			ensure!(!Self::succeeded(&fund), Error::<T, I>::SuccessfulFund);
+			Self::refund_sponsor(index, &mut fund)?;
+
+			let id = Self::id_from_index(index);
+			let raised = fund.raised;
+			let mut refunded = 0u32;
+			let mut visited = 0u32;
+			let mut previous = Vec::new();
+			while visited < limit {
+				// Refunded entries are removed from the trie, but the next key after one that was
+				// removed is found all the same.
+				let key = match sp_io::default_child_storage::next_key(id.storage_key(), &previous)
+				{
+					Some(key) => key,
+					None => break,
+				};
+				visited += 1;
+				let who = match T::AccountId::decode(&mut &key[..]) {
+					Ok(who) => who,
+					Err(_) => {
+						previous = key;
+						continue;
+					}
+				};
+				let (balance, _) = child::get_raw(&id, &key)
+					.map(|raw| Self::contribution_from_raw(&raw))
+					.unwrap_or_default();
+				// Any bonus was never paid in, so only the rest is returned
+				let paid = balance.saturating_sub(Self::bonus(index, &who));
+				let withdrawn = match Self::withdrawn_after(index, paid) {
+					Ok(withdrawn) => withdrawn,
+					Err(_) => break,
+				};
+				Self::refund(index, &who, &who, paid)?;
+				Bonuses::<T, I>::remove(index, &who);
+				Self::unback(&who, index);
+				child::kill(&id, &key);
+				previous = key;
+				Self::note_withdrawn(index, withdrawn);
+
+				fund.raised = fund.raised.saturating_sub(paid);
+				fund.contributors = fund.contributors.saturating_sub(1);
+				refunded += 1;
+			}
+			<Funds<T, I>>::insert(index, &fund);
+			let refunded_total = raised.saturating_sub(fund.raised);
+			TotalRaised::<T, I>::mutate(|total| *total = total.saturating_sub(refunded_total));
+
+			Self::deposit_event(Event::Refunded(index, refunded));
+			Ok(Some((10_000 as Weight).saturating_mul(visited as Weight)).into())
+		}
+
+		/// Move the reserved contributions of up to `limit` backers of an ended fund into its
+		/// account, carrying on from where the last call stopped. When `ReserveContributions` is
+		/// set, this has to be done for every backer before the fund pays out or is dissolved, and
+		/// anyone can call this function to do it. Only the entries actually visited are charged
+		/// for.
+		#[pallet::weight((10_000 as Weight).saturating_mul(*limit as Weight))]
+		#[transactional]
+		pub fn collect_reserves(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			limit: u32,
+		) -> DispatchResultWithPostInfo {
+			ensure_signed(origin)?;
+
+			let fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(
+				now >= fund.end || fund.cancelled,
+				Error::<T, I>::FundStillActive
+			);
+			ensure!(
+				Self::holds_reserves(index),
+				Error::<T, I>::NoReservesToCollect
+			);
+
+			let id = Self::id_from_index(index);
+			let account = Self::fund_account_id(index);
+			let mut visited = 0u32;
+			let mut previous = ReserveCursor::<T, I>::get(index).unwrap_or_default();
+			while visited < limit {
+				let key = match sp_io::default_child_storage::next_key(id.storage_key(), &previous)
+				{
+					Some(key) => key,
+					None => break,
+				};
+				visited += 1;
+				if let Ok(who) = T::AccountId::decode(&mut &key[..]) {
+					let (balance, _) = child::get_raw(&id, &key)
+						.map(|raw| Self::contribution_from_raw(&raw))
+						.unwrap_or_default();
+					// Any bonus was never paid in, so only the rest is reserved
+					let paid = balance.saturating_sub(Self::bonus(index, &who));
+					T::Currency::repatriate_reserved(&who, &account, paid, BalanceStatus::Free)?;
+				}
+				previous = key;
+			}
+
+			if sp_io::default_child_storage::next_key(id.storage_key(), &previous).is_none() {
+				ReserveCursor::<T, I>::remove(index);
+				ReservesCollected::<T, I>::insert(index, ());
+				Self::deposit_event(Event::ReservesCollected(index));
+			} else {
+				ReserveCursor::<T, I>::insert(index, previous);
+			}
+			Ok(Some((10_000 as Weight).saturating_mul(visited as Weight)).into())
+		}
+
+		/// Release the proceeds of the fund to its beneficiary gradually once it succeeds. Only the
+		/// owner may do this, and only before the fund is backed, so that backers know the terms.
+		/// `None` removes the schedule, so that the proceeds can be claimed at once.
+		#[pallet::weight(10_000)]
+		pub fn set_vesting(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			vesting: Option<VestingSchedule<T::BlockNumber, BalanceOf<T, I>>>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let mut fund = Self::ensure_owner(index, &who)?;
+			ensure!(fund.raised.is_zero(), Error::<T, I>::FundAlreadyBacked);
+
+			fund.vesting = vesting;
+			<Funds<T, I>>::insert(index, &fund);
+
+			Self::deposit_event(Event::VestingSet(index));
+			Ok(().into())
+		}
+
+		/// Pay the beneficiary of a successful fund whatever has vested since the last claim. A
+		/// fund without a vesting schedule vests in full as soon as it ends. Only the owner may do
+		/// this.
+		#[pallet::weight(10_000)]
+		pub fn claim_vested(origin: OriginFor<T>, index: FundIndex) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let mut fund = Self::ensure_owner(index, &who)?;
+
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(now >= fund.end, Error::<T, I>::FundStillActive);
+			ensure!(Self::succeeded(&fund), Error::<T, I>::UnsuccessfulFund);
+			ensure!(
+				!Self::milestones_pending(index),
+				Error::<T, I>::MilestonesPending
+			);
+
+			let amount = Self::vested(&fund, now).saturating_sub(fund.claimed);
+			ensure!(!amount.is_zero(), Error::<T, I>::NothingToClaim);
+
+			ensure!(
+				!Self::holds_reserves(index),
+				Error::<T, I>::ReservesNotCollected
+			);
+
+			let _ = T::Currency::resolve_creating(
+				&fund.beneficiary,
+				T::Currency::withdraw(
+					&Self::fund_account_id(index),
+					amount,
+					WithdrawReasons::TRANSFER,
+					ExistenceRequirement::AllowDeath,
+				)?,
+			);
+
+			fund.claimed = fund.claimed.saturating_add(amount);
+			<Funds<T, I>>::insert(index, &fund);
+
+			Self::deposit_event(Event::VestedClaimed(index, amount, now));
+			Ok(().into())
+		}
+
+		/// Split the release of a successful fund's proceeds into milestones, each paying out a
+		/// portion of `raised` once its block has passed. Whatever the milestones leave over is
+		/// paid out when the fund is dispensed. Only the owner may do this, and only before the
+		/// fund starts. An empty list removes the milestones.
+		#[pallet::weight(10_000)]
+		pub fn set_milestones(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			milestones: Vec<(T::BlockNumber, Perbill)>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let fund = Self::ensure_owner(index, &who)?;
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(now < fund.start, Error::<T, I>::FundAlreadyStarted);
+
+			ensure!(
+				milestones.len() <= T::MaxMilestones::get() as usize,
+				Error::<T, I>::TooManyMilestones
+			);
+			let total: u64 = milestones
+				.iter()
+				.map(|(_, portion)| u64::from(portion.deconstruct()))
+				.sum();
+			ensure!(
+				total <= u64::from(Perbill::one().deconstruct()),
+				Error::<T, I>::MilestonesExceedRaised
+			);
+
+			Milestones::<T, I>::insert(index, milestones);
+
+			Self::deposit_event(Event::MilestonesSet(index));
+			Ok(().into())
+		}
+
+		/// Pay the beneficiary of a successful fund the portion of `raised` released by the
+		/// milestone at `milestone_idx`, once its block has passed. Each milestone is released
+		/// once. Only the owner may do this.
+		#[pallet::weight(10_000)]
+		pub fn release_milestone(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			milestone_idx: u32,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let mut fund = Self::ensure_owner(index, &who)?;
+
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(now >= fund.end, Error::<T, I>::FundStillActive);
+			ensure!(Self::succeeded(&fund), Error::<T, I>::UnsuccessfulFund);
+
+			let (at, portion) = *Self::milestones(index)
+				.get(milestone_idx as usize)
+				.ok_or(Error::<T, I>::InvalidMilestone)?;
+			ensure!(now >= at, Error::<T, I>::MilestoneNotReached);
+			let mut released = Self::released_milestones(index);
+			ensure!(
+				!released.contains(&milestone_idx),
+				Error::<T, I>::MilestoneAlreadyReleased
+			);
+
+			let amount = portion
+				.mul_floor(fund.raised)
+				.min(fund.raised.saturating_sub(fund.claimed));
+			ensure!(
+				!Self::holds_reserves(index),
+				Error::<T, I>::ReservesNotCollected
+			);
+			let _ = T::Currency::resolve_creating(
+				&fund.beneficiary,
+				T::Currency::withdraw(
+					&Self::fund_account_id(index),
+					amount,
+					WithdrawReasons::TRANSFER,
+					ExistenceRequirement::AllowDeath,
+				)?,
+			);
+
+			fund.claimed = fund.claimed.saturating_add(amount);
+			<Funds<T, I>>::insert(index, &fund);
+			released.push(milestone_idx);
+			ReleasedMilestones::<T, I>::insert(index, released);
+
+			Self::deposit_event(Event::MilestoneReleased(index, milestone_idx, amount));
+			Ok(().into())
+		}
+
+		/// Restrict contributions to the fund to the given accounts, replacing any previous
+		/// whitelist. An empty list opens the fund to everyone. Only the owner may do this, and
+		/// only before the fund starts. The weight assumes a full whitelist is replaced, and only
+		/// the accounts actually removed are charged for.
+		#[pallet::weight(
+			T::DbWeight::get()
+				.reads_writes(
+					T::MaxWhitelistLen::get() as Weight,
+					(accounts.len() as Weight).saturating_add(T::MaxWhitelistLen::get() as Weight),
+				)
+				.saturating_add(10_000)
+		)]
+		pub fn set_whitelist(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			accounts: Vec<AccountIdOf<T>>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let fund = Self::ensure_owner(index, &who)?;
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(now < fund.start, Error::<T, I>::FundAlreadyStarted);
+			ensure!(
+				accounts.len() <= T::MaxWhitelistLen::get() as usize,
+				Error::<T, I>::WhitelistTooLong
+			);
+
+			let removed = Whitelist::<T, I>::drain_prefix(index).count() as Weight;
+			for account in &accounts {
+				Whitelist::<T, I>::insert(index, account, ());
+			}
+
+			Self::deposit_event(Event::WhitelistSet(index, accounts.len() as u32));
+			let weight = T::DbWeight::get()
+				.reads_writes(removed, (accounts.len() as Weight).saturating_add(removed))
+				.saturating_add(10_000);
+			Ok(Some(weight).into())
+		}
+
+		/// Credit contributions made while a tier lasts with a bonus on top of what is paid in. A
+		/// contribution earns the bonus of the earliest-ending tier that has not ended yet. Only
+		/// the owner may do this, and only before the fund starts. An empty list removes the tiers.
+		#[pallet::weight(10_000)]
+		pub fn set_bonus_tiers(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			tiers: Vec<(T::BlockNumber, Perbill)>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let fund = Self::ensure_owner(index, &who)?;
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(now < fund.start, Error::<T, I>::FundAlreadyStarted);
+			ensure!(
+				tiers.len() <= T::MaxBonusTiers::get() as usize,
+				Error::<T, I>::TooManyBonusTiers
+			);
+
+			BonusTiers::<T, I>::insert(index, tiers);
+
+			Self::deposit_event(Event::BonusTiersSet(index));
+			Ok(().into())
+		}
+
+		/// Report that an ended fund has started retiring under timed retirement, once both its
+		/// retirement period and `RetirementDuration` have passed since it ended. The moment this
+		/// happens cannot be told from the block number, so anyone can call this function, and
+		/// those who intend to dissolve the fund have reason to do so as soon as possible. If the
+		/// moment the fund ended was never seen, `RetirementDuration` starts now, and is kept
+		/// even though the call then fails.
+		#[pallet::weight(10_000)]
+		pub fn retire(origin: OriginFor<T>, index: FundIndex) -> DispatchResultWithPostInfo {
+			ensure_signed(origin)?;
+
+			let duration = T::RetirementDuration::get().ok_or(Error::<T, I>::RetirementNotTimed)?;
+			let fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(now >= fund.end, Error::<T, I>::FundStillActive);
+			if !RetireAfter::<T, I>::contains_key(index) {
+				RetireAfter::<T, I>::insert(index, T::Time::now().saturating_add(duration));
+			}
+			ensure!(
+				Self::status(index) == Some(FundPhase::Retiring),
+				Error::<T, I>::FundNotRetired
+			);
+			ensure!(
+				ReportedStatus::<T, I>::get(index) != Some(FundPhase::Retiring),
+				Error::<T, I>::AlreadyRetiring
+			);
+
+			Self::report_status(index);
+			Self::deposit_event(Event::Retiring(index, now));
+			Ok(().into())
+		}
+
+		/// Dissolve an entire crowdfund after its retirement period has expired. Only
+		/// `DissolveOrigin` may call this function, and is paid `MinKeeperReward` for it. The owner
+		/// gets the rest of the deposit back, the sponsor gets back whatever is left of the match
+		/// pool and, if the fund failed, its matched funds, and any other contributions or dust
+		/// left in the fund account go to `DissolveDestination`.
+		#[pallet::weight(T::WeightInfo::dissolve())]
+		pub fn dissolve(origin: OriginFor<T>, index: FundIndex) -> DispatchResultWithPostInfo {
+			let keeper = T::DissolveOrigin::ensure_origin(origin)?;
+
+			// Indices are never reused, so a missing fund below the count has been removed
+			let mut fund = Self::funds(index).ok_or_else(|| {
+				if index < Self::fund_count() {
+					Error::<T, I>::AlreadyDissolved
+				} else {
+					Error::<T, I>::InvalidIndex
+				}
+			})?;
+
+			// Check that enough time has passed to remove from storage
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(now >= fund.end, Error::<T, I>::CannotDissolveActiveFund);
+			ensure!(
+				Self::status(index) == Some(FundPhase::Retiring),
+				Error::<T, I>::FundNotRetired
+			);
+
+			let account = Self::fund_account_id(index);
+			ensure!(
+				!Self::holds_reserves(index),
+				Error::<T, I>::ReservesNotCollected
+			);
+			Self::refund_sponsor(index, &mut fund)?;
+
+			// Sweep the whole account, so that any dust left by rounding or sent to it directly
+			// goes to `DissolveDestination` with the leftover contributions and the account is
+			// reaped
+			let owed = fund
+				.deposit
+				.saturating_add(fund.raised.saturating_sub(fund.claimed));
+			let total = T::Currency::free_balance(&account).max(owed);
+			let dust = total.saturating_sub(owed);
+			let imb = T::Currency::withdraw(
+				&account,
+				total,
+				WithdrawReasons::TRANSFER,
+				ExistenceRequirement::AllowDeath,
+			)?;
+			let (deposit, leftover) = imb.split(fund.deposit);
+			let lost = leftover.peek().saturating_sub(dust);
+			// The keeper is paid out of the leftover first, and the deposit makes up the rest
+			let floor = T::MinKeeperReward::get();
+			let (reward, leftover) = leftover.split(floor);
+			let (top_up, deposit) = deposit.split(floor.saturating_sub(reward.peek()));
+			T::Currency::resolve_creating(&keeper, reward.merge(top_up));
+			T::Currency::resolve_creating(&fund.owner, deposit);
+			T::DissolveDestination::on_unbalanced(leftover);
+			if !lost.is_zero() {
+				LostFunds::<T, I>::mutate(|total| *total = total.saturating_add(lost));
+				Self::deposit_event(Event::FundsLost(index, lost));
+			}
+
+			// Remove the fund info from storage
+			Self::remove_fund(index, &fund);
+
+			Self::deposit_event(Event::Dissolved(index, now, keeper, owed));
+			Ok(().into())
+		}
+
+		/// Cancel a fund and confiscate its owner's deposit to `SlashDestination`, such as in
+		/// response to fraud. The fund ends at once and counts as failed, so backers can withdraw
+		/// their contributions. Only `SlashOrigin` may call this function, and only before any of
+		/// the proceeds are claimed.
+		#[pallet::weight(10_000)]
+		pub fn slash_deposit(origin: OriginFor<T>, index: FundIndex) -> DispatchResultWithPostInfo {
+			T::SlashOrigin::ensure_origin(origin)?;
+
+			let mut fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+			ensure!(!fund.cancelled, Error::<T, I>::AlreadyCancelled);
+			ensure!(!fund.deposit.is_zero(), Error::<T, I>::NoDepositToSlash);
+			ensure!(
+				fund.claimed.is_zero(),
+				Error::<T, I>::ProceedsAlreadyClaimed
+			);
+
+			// The fund account may hold less than the deposit, in which case only what it holds
+			// is slashed
+			let (imb, _) = T::Currency::slash(&Self::fund_account_id(index), fund.deposit);
+			let slashed = imb.peek();
+			T::SlashDestination::on_unbalanced(imb);
+
+			let now = <frame_system::Module<T>>::block_number();
+			fund.deposit = Zero::zero();
+			fund.cancelled = true;
+			fund.end = fund.end.min(now);
+			<Funds<T, I>>::insert(index, &fund);
+			Self::schedule_status_checks(index, &fund);
+
+			Self::deposit_event(Event::DepositSlashed(index, slashed));
+			Ok(().into())
+		}
+
+		/// Dispense a payment to the beneficiary of a successful crowdfund.
+		/// The beneficiary receives the contributed funds and the caller receives
+		/// the deposit as a reward to incentivize clearing settled crowdfunds out of storage.
+		#[pallet::weight(10_000)]
+		pub fn dispense(origin: OriginFor<T>, index: FundIndex) -> DispatchResultWithPostInfo {
+			let caller = ensure_signed(origin)?;
+
+			let mut fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+
+			// Check that enough time has passed to remove from storage
+			let now = <frame_system::Module<T>>::block_number();
+
+			ensure!(now >= fund.end, Error::<T, I>::FundStillActive);
+
+			// Check that the fund was actually successful
+			ensure!(Self::succeeded(&fund), Error::<T, I>::UnsuccessfulFund);
+			// Vested proceeds are paid out through `claim_vested`
+			ensure!(
+				fund.vesting.is_none() || fund.claimed >= fund.raised,
+				Error::<T, I>::VestingIncomplete
+			);
+			ensure!(
+				!Self::milestones_pending(index),
+				Error::<T, I>::MilestonesPending
+			);
+
+			let account = Self::fund_account_id(index);
+			ensure!(
+				!Self::holds_reserves(index),
+				Error::<T, I>::ReservesNotCollected
+			);
+			Self::refund_sponsor(index, &mut fund)?;
+
+			// Beneficiary collects the contributed funds that have not been claimed yet
+			let _ = T::Currency::resolve_creating(
+				&fund.beneficiary,
+				T::Currency::withdraw(
+					&account,
+					fund.raised.saturating_sub(fund.claimed),
+					WithdrawReasons::TRANSFER,
+					ExistenceRequirement::AllowDeath,
+				)?,
+			);
+
+			// Caller collects the deposit
+			let _ = T::Currency::resolve_creating(
+				&caller,
+				T::Currency::withdraw(
+					&account,
+					fund.deposit,
+					WithdrawReasons::TRANSFER,
+					ExistenceRequirement::AllowDeath,
+				)?,
+			);
+
+			// Remove the fund info from storage
+			Self::remove_fund(index, &fund);
+
+			Self::deposit_event(Event::Dispensed(index, now, caller));
+			Ok(().into())
+		}
+
+		/// Collect the proceeds of a successful fund as its owner, along with the deposit. Like
+		/// `dispense`, this removes the fund, so it can only be done once.
+		#[pallet::weight(10_000)]
+		pub fn claim(origin: OriginFor<T>, index: FundIndex) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let mut fund = Self::ensure_owner(index, &who)?;
+
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(now >= fund.end, Error::<T, I>::FundStillActive);
+			ensure!(Self::succeeded(&fund), Error::<T, I>::UnsuccessfulFund);
+			// Vested and milestone proceeds are paid out as they are released
+			ensure!(
+				fund.vesting.is_none() || fund.claimed >= fund.raised,
+				Error::<T, I>::VestingIncomplete
+			);
+			ensure!(
+				!Self::milestones_pending(index),
+				Error::<T, I>::MilestonesPending
+			);
+
+			// Owner collects the contributed funds that have not been claimed yet, and the deposit
+			let amount = fund.raised.saturating_sub(fund.claimed);
+			ensure!(
+				!Self::holds_reserves(index),
+				Error::<T, I>::ReservesNotCollected
+			);
+			Self::refund_sponsor(index, &mut fund)?;
+			let _ = T::Currency::resolve_creating(
+				&who,
+				T::Currency::withdraw(
+					&Self::fund_account_id(index),
+					amount.saturating_add(fund.deposit),
+					WithdrawReasons::TRANSFER,
+					ExistenceRequirement::AllowDeath,
+				)?,
+			);
+
+			Self::remove_fund(index, &fund);
+
+			Self::deposit_event(Event::Claimed(index, amount, who));
 			Ok(().into())
 		}
 	}
 }
 
-impl<T: Config> Pallet<T> {
+impl<T: Config<I>, I: 'static> Pallet<T, I> {
+	/// The weight of a contribution to the fund at `index`, given how many contributors it has
+	fn contribute_weight(index: FundIndex) -> Weight {
+		let contributors = Self::funds(index).map_or(0, |fund| fund.contributors);
+		T::WeightInfo::contribute(contributors.min(CONTRIBUTE_WEIGHT_CONTRIBUTORS))
+	}
+
+	/// The weight of a withdrawal by `who`, given how many funds they back
+	fn withdraw_weight(who: &T::AccountId) -> Weight {
+		let backed = Self::backed_funds(who).len() as u32;
+		T::WeightInfo::withdraw(backed.min(T::MaxFundsPerContributor::get()))
+	}
+
+	/// Every fund `owner` owns that has not been dispensed or dissolved, in no particular order
+	pub fn funds_of(owner: &T::AccountId) -> Vec<FundIndex> {
+		OwnerFunds::<T, I>::iter_prefix(owner)
+			.map(|(index, ())| index)
+			.collect()
+	}
+
+	/// Every fund in `category` that has not been dispensed or dissolved, in no particular order
+	pub fn funds_in_category(category: FundCategory) -> Vec<FundIndex> {
+		CategoryIndex::<T, I>::iter_prefix(category)
+			.map(|(index, ())| index)
+			.collect()
+	}
+
+	/// The fund at `index`, provided `who` owns it. Every owner-only call checks ownership this
+	/// way, so any account can own a fund. A multisig account works like any other: the call is
+	/// dispatched as the multisig once enough of its members approve, and passes this check.
+	pub fn ensure_owner(
+		index: FundIndex,
+		who: &T::AccountId,
+	) -> Result<FundInfoOf<T, I>, DispatchError> {
+		let fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+		ensure!(fund.owner == *who, Error::<T, I>::NotOwner);
+		Ok(fund)
+	}
+
 	/// The account ID of the fund pot.
 	///
 	/// This actually does computation. If you need to keep using it, then make sure you cache the
 	/// value and only call this once.
 	pub fn fund_account_id(index: FundIndex) -> T::AccountId {
-		PALLET_ID.into_sub_account(index)
+		T::ModuleId::get().into_sub_account(index)
+	}
+
+	/// The absolute cap described by `cap` as of the current block
+	pub fn resolve_cap(cap: CapMode<BalanceOf<T, I>>) -> BalanceOf<T, I> {
+		match cap {
+			CapMode::Absolute(cap) => cap,
+			CapMode::IssuanceFraction(fraction) => fraction * T::Currency::total_issuance(),
+		}
+	}
+
+	/// The deposit held by the owner of a fund with the given cap: `SubmissionDeposit`, plus
+	/// `DepositPerCapUnit` for each unit of the cap
+	pub fn deposit_for(cap: BalanceOf<T, I>) -> BalanceOf<T, I> {
+		T::SubmissionDeposit::get().saturating_add(cap.saturating_mul(T::DepositPerCapUnit::get()))
 	}
 
 	/// Find the ID associated with the fund
 	///
 	/// Each fund stores information about its contributors and their contributions in a child trie
 	/// This helper function calculates the id of the associated child trie.
+	///
+	/// The id is the hash of `"crowdfnd"`, the pallet's `ModuleId`, the SCALE-encoded name the
+	/// pallet instance was given in `construct_runtime!`, and the little-endian fund index. Every
+	/// part but the last has a fixed or encoded length, so no two instances or indices can hash
+	/// the same input, even if two instances are misconfigured with the same `ModuleId`.
 	pub fn id_from_index(index: FundIndex) -> child::ChildInfo {
+		let name = <T::PalletInfo as PalletInfo>::name::<Self>().unwrap_or_default();
 		let mut buf = Vec::new();
 		buf.extend_from_slice(b"crowdfnd");
+		buf.extend_from_slice(&T::ModuleId::get().0[..]);
+		buf.extend_from_slice(&name.encode());
 		buf.extend_from_slice(&index.to_le_bytes()[..]);
 
 		child::ChildInfo::new_default(T::Hashing::hash(&buf[..]).as_ref())
 	}
 
-	/// Record a contribution in the associated child trie.
-	pub fn contribution_put(index: FundIndex, who: &T::AccountId, balance: &BalanceOf<T>) {
+	/// Decode a contribution as stored in the child trie: the balance, and the block of the
+	/// contributor's first contribution. Entries written before the block was recorded hold only
+	/// the balance, and read as if they were first made at block zero.
+	fn contribution_from_raw(raw: &[u8]) -> (BalanceOf<T, I>, T::BlockNumber) {
+		<(BalanceOf<T, I>, T::BlockNumber)>::decode(&mut &raw[..])
+			.or_else(|_| BalanceOf::<T, I>::decode(&mut &raw[..]).map(|b| (b, Zero::zero())))
+			.unwrap_or_default()
+	}
+
+	/// Record a contribution in the associated child trie. The block of the contributor's first
+	/// contribution is kept, or set to the current block for a new contributor.
+	pub fn contribution_put(index: FundIndex, who: &T::AccountId, balance: &BalanceOf<T, I>) {
 		let id = Self::id_from_index(index);
-		who.using_encoded(|b| child::put(&id, b, &balance));
+		let since = Self::contribution_record(index, who)
+			.map(|(_, since)| since)
+			.unwrap_or_else(<frame_system::Module<T>>::block_number);
+		who.using_encoded(|b| child::put(&id, b, &(balance, since)));
 	}
 
 	/// Lookup a contribution in the associated child trie.
-	pub fn contribution_get(index: FundIndex, who: &T::AccountId) -> BalanceOf<T> {
+	pub fn contribution_get(index: FundIndex, who: &T::AccountId) -> BalanceOf<T, I> {
+		Self::contribution_record(index, who)
+			.map(|(balance, _)| balance)
+			.unwrap_or_default()
+	}
+
+	/// Lookup a contribution in the associated child trie, along with the block in which the
+	/// contributor first contributed.
+	pub fn contribution_record(
+		index: FundIndex,
+		who: &T::AccountId,
+	) -> Option<(BalanceOf<T, I>, T::BlockNumber)> {
 		let id = Self::id_from_index(index);
-		who.using_encoded(|b| child::get_or_default::<BalanceOf<T>>(&id, b))
+		who.using_encoded(|b| child::get_raw(&id, b))
+			.map(|raw| Self::contribution_from_raw(&raw))
 	}
 
 	/// Remove a contribution from an associated child trie.
@@ -371,6 +2012,712 @@ impl<T: Config> Pallet<T> {
 		who.using_encoded(|b| child::kill(&id, b));
 	}
 
+	/// Every contribution to a fund, in the order of the child trie's keys. This reads the whole
+	/// trie, one key at a time, so it is costly for large funds. Avoid it in dispatchables unless
+	/// the weight accounts for the number of contributors.
+	pub fn contributions_iter(index: FundIndex) -> Vec<(T::AccountId, BalanceOf<T, I>)> {
+		let id = Self::id_from_index(index);
+		let mut contributions = Vec::new();
+		let mut key = Vec::new();
+		while let Some(next) = sp_io::default_child_storage::next_key(id.storage_key(), &key) {
+			if let Ok(who) = T::AccountId::decode(&mut &next[..]) {
+				let (balance, _) = child::get_raw(&id, &next)
+					.map(|raw| Self::contribution_from_raw(&raw))
+					.unwrap_or_default();
+				contributions.push((who, balance));
+			}
+			key = next;
+		}
+		contributions
+	}
+
+	/// Find the ID of the child trie holding the intents registered for a fund
+	pub fn intent_id_from_index(index: FundIndex) -> child::ChildInfo {
+		let mut buf = Vec::new();
+		buf.extend_from_slice(&T::ModuleId::get().0[..]);
+		buf.extend_from_slice(b"cfintent");
+		buf.extend_from_slice(&index.to_le_bytes()[..]);
+
+		child::ChildInfo::new_default(T::Hashing::hash(&buf[..]).as_ref())
+	}
+
+	/// Record an intended pledge in the associated child trie.
+	pub fn intent_put(index: FundIndex, who: &T::AccountId, amount: &BalanceOf<T, I>) {
+		let id = Self::intent_id_from_index(index);
+		who.using_encoded(|b| child::put(&id, b, &amount));
+	}
+
+	/// Lookup an intended pledge in the associated child trie.
+	pub fn intent_get(index: FundIndex, who: &T::AccountId) -> BalanceOf<T, I> {
+		let id = Self::intent_id_from_index(index);
+		who.using_encoded(|b| child::get_or_default::<BalanceOf<T, I>>(&id, b))
+	}
+
+	/// Forget that `who` backs the fund at `index`, once their contribution is gone.
+	fn unback(who: &T::AccountId, index: FundIndex) {
+		BackedFunds::<T, I>::mutate(who, |funds| funds.retain(|i| *i != index));
+	}
+
+	/// Whether the contributions to the fund at `index` are still reserved in the backers'
+	/// accounts.
+	fn holds_reserves(index: FundIndex) -> bool {
+		T::ReserveContributions::get() && !ReservesCollected::<T, I>::contains_key(index)
+	}
+
+	/// Whether the contribution `who` made to the fund at `index` is still reserved in their
+	/// account, rather than held by the fund.
+	fn holds_reserve_of(index: FundIndex, who: &T::AccountId) -> bool {
+		Self::holds_reserves(index)
+			&& ReserveCursor::<T, I>::get(index).map_or(true, |last| who.encode() > last)
+	}
+
+	/// Return `amount` of the contribution `who` made to the fund at `index`, paying it to
+	/// `dest`, without charging a transfer fee.
+	fn refund(
+		index: FundIndex,
+		who: &T::AccountId,
+		dest: &T::AccountId,
+		amount: BalanceOf<T, I>,
+	) -> DispatchResult {
+		if Self::holds_reserve_of(index, who) {
+			if who == dest {
+				T::Currency::unreserve(who, amount);
+			} else {
+				T::Currency::repatriate_reserved(who, dest, amount, BalanceStatus::Free)?;
+			}
+		} else {
+			let _ = T::Currency::resolve_creating(
+				dest,
+				T::Currency::withdraw(
+					&Self::fund_account_id(index),
+					amount,
+					WithdrawReasons::TRANSFER,
+					ExistenceRequirement::AllowDeath,
+				)?,
+			);
+		}
+		Ok(())
+	}
+
+	/// Remove the fund at `index` and everything stored about it.
+	fn remove_fund(index: FundIndex, fund: &FundInfoOf<T, I>) {
+		<Funds<T, I>>::remove(index);
+		ReservesCollected::<T, I>::remove(index);
+		ReserveCursor::<T, I>::remove(index);
+		OwnerFundCount::<T, I>::mutate(&fund.owner, |count| *count = count.saturating_sub(1));
+		OwnerFunds::<T, I>::remove(&fund.owner, index);
+		CategoryIndex::<T, I>::remove(fund.category, index);
+		TotalRaised::<T, I>::mutate(|total| *total = total.saturating_sub(fund.raised));
+		RetireAfter::<T, I>::remove(index);
+		OriginalEnd::<T, I>::remove(index);
+		Milestones::<T, I>::remove(index);
+		ReleasedMilestones::<T, I>::remove(index);
+		Whitelist::<T, I>::remove_prefix(index);
+		BonusTiers::<T, I>::remove(index);
+		Bonuses::<T, I>::remove_prefix(index);
+		Installments::<T, I>::remove_prefix(index);
+		InstallmentCount::<T, I>::remove(index);
+		ReportedStatus::<T, I>::remove(index);
+		Referrals::<T, I>::remove_prefix(index);
+		MatchPool::<T, I>::remove(index);
+		// Remove all the contributor info from storage in a single write.
+		// This is possible thanks to the use of a child tree.
+		Self::crowdfund_kill(index);
+	}
+
+	/// Take the submission deposit from `owner` and record a new fund with the next free index.
+	#[allow(clippy::too_many_arguments)]
+	fn do_create(
+		owner: T::AccountId,
+		beneficiary: T::AccountId,
+		goal: BalanceOf<T, I>,
+		cap: BalanceOf<T, I>,
+		min_contribution: BalanceOf<T, I>,
+		start: T::BlockNumber,
+		end: T::BlockNumber,
+		retirement_period: T::BlockNumber,
+		category: FundCategory,
+		metadata: Vec<u8>,
+	) -> Result<FundIndex, DispatchError> {
+		let now = <frame_system::Module<T>>::block_number();
+		// A fund cannot have been open before it existed, so a past start means it opens now
+		let start = start.max(now);
+		ensure!(end > start, Error::<T, I>::EndTooEarly);
+		ensure!(goal <= cap, Error::<T, I>::GoalAboveCap);
+		ensure!(
+			retirement_period >= T::RetirementPeriod::get(),
+			Error::<T, I>::RetirementPeriodTooShort
+		);
+		ensure!(
+			min_contribution >= T::MinContribution::get(),
+			Error::<T, I>::MinContributionTooLow
+		);
+		ensure!(
+			metadata.len() <= T::MaxMetadataLen::get() as usize,
+			Error::<T, I>::MetadataTooLong
+		);
+		let owned = Self::owner_fund_count(&owner);
+		ensure!(
+			owned < T::MaxFundsPerOwner::get(),
+			Error::<T, I>::TooManyFunds
+		);
+
+		let deposit = Self::deposit_for(cap);
+		let imb = T::Currency::withdraw(
+			&owner,
+			deposit,
+			WithdrawReasons::TRANSFER,
+			ExistenceRequirement::AllowDeath,
+		)?;
+
+		let index = FundCount::<T, I>::get();
+		// not protected against overflow, see safemath section
+		FundCount::<T, I>::put(index + 1);
+
+		// No fees are paid here if we need to create this account; that's why we don't just
+		// use the stock `transfer`.
+		T::Currency::resolve_creating(&Self::fund_account_id(index), imb);
+		OwnerFundCount::<T, I>::insert(&owner, owned + 1);
+		OwnerFunds::<T, I>::insert(&owner, index, ());
+		CategoryIndex::<T, I>::insert(category, index, ());
+
+		Funds::<T, I>::insert(
+			index,
+			FundInfo {
+				owner,
+				beneficiary,
+				deposit,
+				raised: Zero::zero(),
+				contributors: 0,
+				start,
+				end,
+				retirement_period,
+				goal,
+				cap,
+				min_contribution,
+				sponsor: None,
+				matched: Zero::zero(),
+				vesting: None,
+				claimed: Zero::zero(),
+				category,
+				metadata,
+				paused: false,
+				cancelled: false,
+			},
+		);
+		if let Some(status) = Self::status(index) {
+			ReportedStatus::<T, I>::insert(index, status);
+		}
+		if let Some(fund) = Self::funds(index) {
+			Self::schedule_status_checks(index, &fund);
+		}
+
+		Ok(index)
+	}
+
+	/// Check the phase of the fund at `index` at the start of block `at`
+	fn schedule_status_check(index: FundIndex, at: T::BlockNumber) {
+		StatusSchedule::<T, I>::mutate(at, |due| {
+			if !due.contains(&index) {
+				due.push(index);
+			}
+		});
+	}
+
+	/// Check the phase of the fund at `index` whenever the block number may change it: when it
+	/// starts, when it ends and when its retirement period is over. Any of these already past
+	/// are checked in the next block instead.
+	pub(crate) fn schedule_status_checks(index: FundIndex, fund: &FundInfoOf<T, I>) {
+		let next = <frame_system::Module<T>>::block_number().saturating_add(One::one());
+		let blocks = [
+			fund.start,
+			fund.end,
+			fund.end.saturating_add(fund.retirement_period),
+		];
+		for &block in blocks.iter() {
+			Self::schedule_status_check(index, block.max(next));
+		}
+	}
+
+	/// Report the phase of every fund scheduled for a check in block `now`. Phases change with
+	/// the block number, and with calls such as `contribute` and `retire`, so a change is
+	/// reported at the start of the block after it happens. With timed retirement, this is also
+	/// where the moment a fund ended is seen, and its `RetireAfter` recorded. Returns the weight
+	/// used.
+	fn report_status_changes(now: T::BlockNumber) -> Weight {
+		let mut weight = T::DbWeight::get().reads_writes(1, 1);
+		for index in StatusSchedule::<T, I>::take(now) {
+			weight = weight.saturating_add(T::DbWeight::get().reads_writes(5, 2));
+			if let Some(duration) = T::RetirementDuration::get() {
+				let ended = Self::funds(index).map_or(false, |fund| now >= fund.end);
+				if ended && !RetireAfter::<T, I>::contains_key(index) {
+					RetireAfter::<T, I>::insert(index, T::Time::now().saturating_add(duration));
+				}
+			}
+			Self::report_status(index);
+		}
+		weight
+	}
+
+	/// Emit `StatusChanged` if the phase of the fund at `index` differs from the one last
+	/// reported.
+	fn report_status(index: FundIndex) {
+		let status = match Self::status(index) {
+			Some(status) => status,
+			None => return,
+		};
+		let reported = ReportedStatus::<T, I>::get(index);
+		if reported != Some(status) {
+			ReportedStatus::<T, I>::insert(index, status);
+			// Funds from before statuses were reported start being tracked silently
+			if reported.is_some() {
+				Self::deposit_event(Event::StatusChanged(index, status));
+			}
+		}
+	}
+
+	/// Collect the installments due to up to `MaxInstallmentFundsPerBlock` funds, starting from
+	/// `InstallmentCursor` and wrapping around, so that every fund is reached in turn. Returns
+	/// the weight used.
+	fn collect_installments(now: T::BlockNumber) -> Weight {
+		let fund_count = Self::fund_count();
+		let mut weight = T::DbWeight::get().reads(2);
+		if fund_count == 0 {
+			return weight;
+		}
+
+		let cursor = InstallmentCursor::<T, I>::get() % fund_count;
+		let batch = T::MaxInstallmentFundsPerBlock::get().min(fund_count);
+		for offset in 0..batch {
+			let index = (cursor + offset) % fund_count;
+			weight = weight.saturating_add(Self::collect_fund_installments(index, now));
+		}
+		InstallmentCursor::<T, I>::put((cursor + batch) % fund_count);
+		weight.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	/// Collect the installment due from every pledge to the fund at `index` that is not
+	/// delinquent. Pledges to a fund that has not started or is paused wait; pledges to a fund
+	/// that has ended are dropped. There are at most `MaxInstallmentsPerFund` of them. Returns
+	/// the weight used.
+	fn collect_fund_installments(index: FundIndex, now: T::BlockNumber) -> Weight {
+		let mut weight = T::DbWeight::get().reads(1);
+		if Self::installment_count(index) == 0 {
+			return weight;
+		}
+
+		weight = weight.saturating_add(T::DbWeight::get().reads(1));
+		let fund = match Self::funds(index) {
+			Some(fund) if now < fund.end => fund,
+			_ => {
+				Installments::<T, I>::remove_prefix(index);
+				InstallmentCount::<T, I>::remove(index);
+				return weight.saturating_add(T::DbWeight::get().writes(2));
+			}
+		};
+		if now < fund.start || fund.paused {
+			return weight;
+		}
+
+		for (who, mut pledge) in Installments::<T, I>::iter_prefix(index).collect::<Vec<_>>() {
+			weight = weight.saturating_add(T::DbWeight::get().reads(1));
+			if pledge.delinquent {
+				continue;
+			}
+
+			let amount = pledge.per_block.min(pledge.remaining);
+			weight =
+				weight.saturating_add(T::WeightInfo::contribute(CONTRIBUTE_WEIGHT_CONTRIBUTORS));
+			match Self::do_contribute(who.clone(), index, amount) {
+				Ok(_) => {
+					pledge.remaining = pledge.remaining.saturating_sub(amount);
+					if pledge.remaining.is_zero() {
+						Installments::<T, I>::remove(index, &who);
+						InstallmentCount::<T, I>::mutate(index, |count| {
+							*count = count.saturating_sub(1)
+						});
+					} else {
+						Installments::<T, I>::insert(index, &who, &pledge);
+					}
+					Self::deposit_event(Event::InstallmentPaid(who, index, amount));
+				}
+				Err(_) => {
+					pledge.delinquent = true;
+					Installments::<T, I>::insert(index, &who, &pledge);
+					Self::deposit_event(Event::InstallmentDelinquent(who, index));
+				}
+			}
+			weight = weight.saturating_add(T::DbWeight::get().writes(1));
+		}
+		weight
+	}
+
+	/// Move `value` from `who` into the fund at `index`, or reserve it if `ReserveContributions`
+	/// is set, and record the contribution. The fee is taken out of `value`, so only the
+	/// remainder counts toward the fund. Returns that remainder.
+	fn do_contribute(
+		who: T::AccountId,
+		index: FundIndex,
+		value: BalanceOf<T, I>,
+	) -> Result<BalanceOf<T, I>, DispatchError> {
+		let mut fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+		ensure!(
+			value >= fund.min_contribution,
+			Error::<T, I>::ContributionTooSmall
+		);
+		let increment = T::ContributionIncrement::get();
+		ensure!(
+			increment.is_zero() || (value % increment).is_zero(),
+			Error::<T, I>::NotAnIncrement
+		);
+		let fee = T::FeePercent::get().mul_floor(value);
+		let net = value.saturating_sub(fee);
+
+		// Make sure crowdfund has started and not ended
+		let now = <frame_system::Module<T>>::block_number();
+		ensure!(fund.start <= now, Error::<T, I>::FundNotStarted);
+		ensure!(fund.raised < fund.cap, Error::<T, I>::FundClosed);
+		ensure!(fund.end > now, Error::<T, I>::ContributionPeriodOver);
+		ensure!(!fund.paused, Error::<T, I>::FundPaused);
+		ensure!(
+			T::AllowOwnerContribution::get() || who != fund.owner,
+			Error::<T, I>::OwnerCannotContribute
+		);
+
+		// A whitelisted fund only accepts contributions from the accounts on its whitelist
+		ensure!(
+			Whitelist::<T, I>::contains_key(index, &who)
+				|| Whitelist::<T, I>::iter_prefix_values(index)
+					.next()
+					.is_none(),
+			Error::<T, I>::NotWhitelisted
+		);
+
+		// The fund may be filled up to, but not beyond, its cap
+		let raised = fund
+			.raised
+			.checked_add(&net)
+			.ok_or(Error::<T, I>::Overflow)?;
+		ensure!(raised <= fund.cap, Error::<T, I>::CapExceeded);
+
+		// The sponsor's pool matches the contribution while it lasts, as far as the cap allows
+		let pool = Self::match_pool(index);
+		let matched = T::MatchRatio::get()
+			.mul_floor(net)
+			.min(pool)
+			.min(fund.cap.saturating_sub(raised));
+		let raised = raised.saturating_add(matched);
+
+		// No single account may pay in more than the per-account cap, if there is one
+		let balance = Self::contribution_get(index, &who);
+		let paid = balance.saturating_sub(Self::bonus(index, &who));
+		let max_per_account = T::MaxContributionPerAccount::get();
+		ensure!(
+			max_per_account.is_zero() || paid.saturating_add(net) <= max_per_account,
+			Error::<T, I>::ContributionCapExceeded
+		);
+
+		// No single account may contribute more often than the per-block limit, if there is one
+		let max_per_block = T::MaxContributionsPerBlock::get();
+		let contributions = Self::contributions_this_block(&who);
+		ensure!(
+			max_per_block == 0 || contributions < max_per_block,
+			Error::<T, I>::TooManyContributionsThisBlock
+		);
+
+		// A new backer must have room to back another fund
+		let mut backed = Self::backed_funds(&who);
+		if balance.is_zero() {
+			backed.retain(|i| Funds::<T, I>::contains_key(i));
+			ensure!(
+				(backed.len() as u32) < T::MaxFundsPerContributor::get(),
+				Error::<T, I>::TooManyBackedFunds
+			);
+			backed.push(index);
+		}
+
+		if Self::holds_reserves(index) {
+			// Reserve the whole amount at once, then take the fee out of the reserve
+			T::Currency::reserve(&who, value)?;
+			let (fee, _) = T::Currency::slash_reserved(&who, fee);
+			T::FeeCollector::on_unbalanced(fee);
+		} else {
+			// Take the whole amount at once, then split the fee off from the contribution
+			let imb = T::Currency::withdraw(
+				&who,
+				value,
+				WithdrawReasons::TRANSFER,
+				ExistenceRequirement::AllowDeath,
+			)?;
+			let (fee, contribution) = imb.split(fee);
+			T::FeeCollector::on_unbalanced(fee);
+			T::Currency::resolve_creating(&Self::fund_account_id(index), contribution);
+		}
+		fund.raised = raised;
+		TotalRaised::<T, I>::mutate(|total| {
+			*total = total.saturating_add(net).saturating_add(matched)
+		});
+		if !matched.is_zero() {
+			fund.matched = fund.matched.saturating_add(matched);
+			MatchPool::<T, I>::insert(index, pool.saturating_sub(matched));
+		}
+		// A full fund has no reason to stay open, so it ends now
+		let closed = fund.raised == fund.cap;
+		if closed {
+			fund.end = now;
+		}
+
+		if balance.is_zero() {
+			fund.contributors = fund.contributors.saturating_add(1);
+			BackedFunds::<T, I>::insert(&who, backed);
+		}
+		Funds::<T, I>::insert(index, &fund);
+		if closed {
+			Self::schedule_status_checks(index, &fund);
+		}
+		if max_per_block != 0 {
+			ContributionsThisBlock::<T, I>::insert(&who, contributions + 1);
+		}
+
+		// Early contributions are credited a bonus on top of what is paid in
+		let bonus = Self::bonus_tiers(index)
+			.iter()
+			.filter(|(until, _)| now <= *until)
+			.min_by_key(|(until, _)| *until)
+			.map(|(_, portion)| portion.mul_floor(net))
+			.unwrap_or_else(Zero::zero);
+		if !bonus.is_zero() {
+			Bonuses::<T, I>::mutate(index, &who, |b| *b = b.saturating_add(bonus));
+		}
+
+		let balance = balance.saturating_add(net).saturating_add(bonus);
+		Self::contribution_put(index, &who, &balance);
+
+		// Rewards are minted on the net contribution
+		let reward: u128 = T::RewardRate::get().saturating_mul_int(net.saturated_into::<u128>());
+		let reward: RewardBalanceOf<T, I> = reward.saturated_into();
+		// A reward below the existential deposit of a new account is not minted at all
+		let reward = if reward.is_zero() {
+			reward
+		} else {
+			T::RewardCurrency::deposit_creating(&who, reward).peek()
+		};
+
+		let progress = Perbill::from_rational_approximation(fund.raised, fund.cap);
+		Self::deposit_event(Event::Contributed(
+			who,
+			index,
+			net,
+			matched,
+			reward,
+			fund.raised,
+			progress,
+			now,
+		));
+		if closed {
+			Self::deposit_event(Event::CapReached(index, now));
+		}
+		Ok(net)
+	}
+
+	/// Withdraw the whole contribution `who` made to the fund at `index`, paying it to `dest`
+	fn do_withdraw(who: T::AccountId, index: FundIndex, dest: T::AccountId) -> DispatchResult {
+		let mut fund = Self::funds(index).ok_or(Error::<T, I>::InvalidIndex)?;
+		let now = <frame_system::Module<T>>::block_number();
+		ensure!(
+			fund.end < now || fund.cancelled,
+			Error::<T, I>::FundStillActive
+		);
+
+		let balance = Self::contribution_get(index, &who);
+		ensure!(balance > Zero::zero(), Error::<T, I>::NoContribution);
+		// Any bonus was never paid in, so only the rest is returned
+		let paid = balance.saturating_sub(Self::bonus(index, &who));
+		let withdrawn = Self::withdrawn_after(index, paid)?;
+		// A payment too small to open the destination account would be lost
+		ensure!(
+			dest == who
+				|| T::Currency::free_balance(&dest).saturating_add(paid)
+					>= T::Currency::minimum_balance(),
+			Error::<T, I>::DestinationBelowMinimum
+		);
+
+		Self::refund(index, &who, &dest, paid)?;
+
+		// Update storage
+		Self::note_withdrawn(index, withdrawn);
+		Self::contribution_kill(index, &who);
+		Bonuses::<T, I>::remove(index, &who);
+		Self::unback(&who, index);
+		fund.raised = fund.raised.saturating_sub(paid);
+		TotalRaised::<T, I>::mutate(|total| *total = total.saturating_sub(paid));
+		fund.contributors = fund.contributors.saturating_sub(1);
+		<Funds<T, I>>::insert(index, &fund);
+
+		Self::deposit_event(Event::Withdrew(who, index, paid, now, dest));
+		Ok(())
+	}
+
+	/// Move `amount` from `who` into the account of the fund at `index` as its sponsor. The fund
+	/// must not have ended, and each fund has at most one sponsor.
+	fn sponsor(
+		index: FundIndex,
+		fund: &mut FundInfoOf<T, I>,
+		who: &T::AccountId,
+		amount: BalanceOf<T, I>,
+	) -> DispatchResult {
+		if let Some(sponsor) = &fund.sponsor {
+			ensure!(sponsor == who, Error::<T, I>::SponsorAlreadySet);
+		}
+		let now = <frame_system::Module<T>>::block_number();
+		ensure!(fund.end > now, Error::<T, I>::ContributionPeriodOver);
+
+		T::Currency::transfer(
+			who,
+			&Self::fund_account_id(index),
+			amount,
+			ExistenceRequirement::AllowDeath,
+		)?;
+		fund.sponsor = Some(who.clone());
+		Ok(())
+	}
+
+	/// Return to the sponsor of the fund at `index` whatever is left of its match pool, along with
+	/// the matched portion of `raised` if the fund failed, and return the amount. The caller is
+	/// left to store `fund`.
+	fn refund_sponsor(
+		index: FundIndex,
+		fund: &mut FundInfoOf<T, I>,
+	) -> Result<BalanceOf<T, I>, DispatchError> {
+		let sponsor = match &fund.sponsor {
+			Some(sponsor) => sponsor.clone(),
+			None => return Ok(Zero::zero()),
+		};
+		let matched = if Self::succeeded(fund) {
+			Zero::zero()
+		} else {
+			fund.matched
+		};
+		let amount = matched.saturating_add(Self::match_pool(index));
+		if amount.is_zero() {
+			return Ok(amount);
+		}
+
+		// Return funds to the sponsor without charging a transfer fee
+		let _ = T::Currency::resolve_creating(
+			&sponsor,
+			T::Currency::withdraw(
+				&Self::fund_account_id(index),
+				amount,
+				WithdrawReasons::TRANSFER,
+				ExistenceRequirement::AllowDeath,
+			)?,
+		);
+		MatchPool::<T, I>::remove(index);
+		fund.matched = fund.matched.saturating_sub(matched);
+		fund.raised = fund.raised.saturating_sub(matched);
+		TotalRaised::<T, I>::mutate(|total| *total = total.saturating_sub(matched));
+
+		let now = <frame_system::Module<T>>::block_number();
+		Self::deposit_event(Event::MatchRefunded(sponsor, index, amount, now));
+		Ok(amount)
+	}
+
+	/// The total withdrawn from the fund at `index` this block once `amount` more is taken, or
+	/// `None` if withdrawals are not limited. Fails if the total would exceed `MaxWithdrawPerBlock`.
+	fn withdrawn_after(
+		index: FundIndex,
+		amount: BalanceOf<T, I>,
+	) -> Result<Option<BalanceOf<T, I>>, DispatchError> {
+		T::MaxWithdrawPerBlock::get()
+			.map(|max| {
+				let withdrawn = Self::withdrawn_this_block(index).saturating_add(amount);
+				ensure!(withdrawn <= max, Error::<T, I>::WithdrawRateLimited);
+				Ok(withdrawn)
+			})
+			.transpose()
+	}
+
+	/// Record the total computed by `withdrawn_after` once the withdrawal has gone through.
+	fn note_withdrawn(index: FundIndex, withdrawn: Option<BalanceOf<T, I>>) {
+		if let Some(withdrawn) = withdrawn {
+			WithdrawnThisBlock::<T, I>::insert(index, withdrawn);
+		}
+	}
+
+	/// The portion of a fund's proceeds that has vested as of `now`, including anything already
+	/// claimed.
+	pub fn vested(fund: &FundInfoOf<T, I>, now: T::BlockNumber) -> BalanceOf<T, I> {
+		match &fund.vesting {
+			None => fund.raised,
+			Some(schedule) => {
+				let elapsed: u128 = now.saturating_sub(schedule.cliff).saturated_into();
+				let elapsed: BalanceOf<T, I> = elapsed.saturated_into();
+				schedule.per_block.saturating_mul(elapsed).min(fund.raised)
+			}
+		}
+	}
+
+	/// Whether the fund at `index` has milestones that have not been released yet.
+	fn milestones_pending(index: FundIndex) -> bool {
+		Self::released_milestones(index).len() < Self::milestones(index).len()
+	}
+
+	/// Whether `fund` reached its goal without being cancelled. Only meaningful once it has ended.
+	fn succeeded(fund: &FundInfoOf<T, I>) -> bool {
+		!fund.cancelled && fund.raised >= fund.goal
+	}
+
+	/// The state of a fund as of the current block.
+	pub fn fund_state(fund: &FundInfoOf<T, I>) -> FundState {
+		let now = <frame_system::Module<T>>::block_number();
+		if now < fund.end {
+			FundState::Active
+		} else if Self::succeeded(fund) {
+			FundState::Succeeded
+		} else {
+			FundState::Failed
+		}
+	}
+
+	/// The phase of the fund at `index` as of the current block, or `None` if there is no such
+	/// fund.
+	pub fn status(index: FundIndex) -> Option<FundPhase> {
+		let fund = Self::funds(index)?;
+		let now = <frame_system::Module<T>>::block_number();
+		let retiring = now >= fund.end.saturating_add(fund.retirement_period)
+			&& (T::RetirementDuration::get().is_none()
+				|| Self::retire_after(index).map_or(false, |after| T::Time::now() >= after));
+		Some(if now < fund.start {
+			FundPhase::NotStarted
+		} else if now < fund.end {
+			FundPhase::Active
+		} else if retiring {
+			FundPhase::Retiring
+		} else if Self::succeeded(&fund) {
+			FundPhase::SucceededPendingClaim
+		} else {
+			FundPhase::Failed
+		})
+	}
+
+	/// Every fund `who` holds a contribution in, along with that contribution and the state of
+	/// the fund. The list is bounded by `MaxFundsPerContributor`.
+	pub fn backer_portfolio(who: &T::AccountId) -> Vec<(FundIndex, BalanceOf<T, I>, FundState)> {
+		Self::backed_funds(who)
+			.into_iter()
+			.filter_map(|index| {
+				Self::funds(index).map(|fund| {
+					(
+						index,
+						Self::contribution_get(index, who),
+						Self::fund_state(&fund),
+					)
+				})
+			})
+			.collect()
+	}
+
 	/// Remove the entire record of contributions in the associated child trie in a single
 	/// storage write.
 	pub fn crowdfund_kill(index: FundIndex) {
@@ -379,5 +2726,6 @@ impl<T: Config> Pallet<T> {
 		// Limiting can be useful, but is beyond the scope of this recipe. For more info, see
 		// https://crates.parity.io/frame_support/storage/child/fn.kill_storage.html
 		child::kill_storage(&id, None);
+		child::kill_storage(&Self::intent_id_from_index(index), None);
 	}
 }
@@ -0,0 +1,135 @@
+//! Storage migrations for the simple crowdfund pallet
+
+use crate::{
+	BackedFunds, BalanceOf, CategoryIndex, Config, FundCategory, FundIndex, FundInfo, Funds,
+	OwnerFundCount, OwnerFunds, Pallet, TotalRaised,
+};
+use frame_support::{storage::child, traits::Get, weights::Weight};
+use parity_scale_codec::{Decode, Encode};
+use sp_runtime::traits::{Bounded, Hash, Saturating, Zero};
+use sp_std::prelude::*;
+
+/// The layout of `FundInfo` before the pallet tracked owners, contributors, caps and payouts.
+/// Back then, `goal` was the amount that had to be raised for the fund to succeed.
+#[derive(Encode, Decode)]
+pub struct OldFundInfo<AccountId, Balance, BlockNumber> {
+	pub beneficiary: AccountId,
+	pub deposit: Balance,
+	pub raised: Balance,
+	pub end: BlockNumber,
+	pub goal: Balance,
+}
+
+type OldFundInfoOf<T, I> = OldFundInfo<
+	<T as frame_system::Config>::AccountId,
+	BalanceOf<T, I>,
+	<T as frame_system::Config>::BlockNumber,
+>;
+
+/// The child trie that held a fund's contributions before the trie id included the module id.
+fn old_id_from_index<T: Config<I>, I: 'static>(index: FundIndex) -> child::ChildInfo {
+	let mut buf = Vec::new();
+	buf.extend_from_slice(b"crowdfnd");
+	buf.extend_from_slice(&index.to_le_bytes()[..]);
+
+	child::ChildInfo::new_default(T::Hashing::hash(&buf[..]).as_ref())
+}
+
+/// The child trie that held a fund's contributions before the trie id included the name of the
+/// pallet instance.
+fn v2_id_from_index<T: Config<I>, I: 'static>(index: FundIndex) -> child::ChildInfo {
+	let mut buf = Vec::new();
+	buf.extend_from_slice(&T::ModuleId::get().0[..]);
+	buf.extend_from_slice(b"crowdfnd");
+	buf.extend_from_slice(&index.to_le_bytes()[..]);
+
+	child::ChildInfo::new_default(T::Hashing::hash(&buf[..]).as_ref())
+}
+
+/// Move every contribution in the `old_id` child trie into the current child trie of the fund at
+/// `index`, calling `moved` with each contributor. Returns the number of contributions moved.
+fn move_child_trie<T: Config<I>, I: 'static>(
+	index: FundIndex,
+	old_id: &child::ChildInfo,
+	mut moved: impl FnMut(T::AccountId),
+) -> u32 {
+	let new_id = Pallet::<T, I>::id_from_index(index);
+	let mut contributors = 0u32;
+	let mut key = Vec::new();
+	while let Some(next) = sp_io::default_child_storage::next_key(old_id.storage_key(), &key) {
+		if let Some(contribution) = child::get_raw(old_id, &next) {
+			child::put_raw(&new_id, &next, &contribution);
+			if let Ok(who) = T::AccountId::decode(&mut &next[..]) {
+				moved(who);
+			}
+			contributors = contributors.saturating_add(1);
+		}
+		key = next;
+	}
+	child::kill_storage(old_id, None);
+	contributors
+}
+
+/// Move the contributions to the fund at `index` into its current child trie, recording each
+/// contributor as a backer of the fund. Returns the number of contributors.
+fn move_contributions<T: Config<I>, I: 'static>(index: FundIndex) -> u32 {
+	move_child_trie::<T, I>(index, &old_id_from_index::<T, I>(index), |who| {
+		BackedFunds::<T, I>::mutate(&who, |funds| funds.push(index))
+	})
+}
+
+/// Upgrade every fund to the current `FundInfo` layout. Funds keep their beneficiary, deposit,
+/// raised amount, end and goal. The beneficiary becomes the owner, funds have no cap, the global
+/// `MinContribution` becomes each fund's minimum, every fund is in `FundCategory::Other`, and
+/// every other new field takes its empty value. Every fund has its phase checked from now on.
+pub fn migrate_to_v2<T: Config<I>, I: 'static>() -> Weight {
+	let mut reads_writes: Weight = 0;
+	Funds::<T, I>::translate::<OldFundInfoOf<T, I>, _>(|index, old| {
+		let contributors = move_contributions::<T, I>(index);
+		reads_writes = reads_writes.saturating_add(Weight::from(contributors).saturating_mul(2));
+
+		OwnerFundCount::<T, I>::mutate(&old.beneficiary, |count| *count = count.saturating_add(1));
+		OwnerFunds::<T, I>::insert(&old.beneficiary, index, ());
+		CategoryIndex::<T, I>::insert(FundCategory::Other, index, ());
+		TotalRaised::<T, I>::mutate(|total| *total = total.saturating_add(old.raised));
+		reads_writes = reads_writes.saturating_add(8);
+
+		let fund = FundInfo {
+			owner: old.beneficiary.clone(),
+			beneficiary: old.beneficiary,
+			deposit: old.deposit,
+			raised: old.raised,
+			contributors,
+			start: Zero::zero(),
+			end: old.end,
+			retirement_period: T::RetirementPeriod::get(),
+			goal: old.goal,
+			cap: Bounded::max_value(),
+			min_contribution: T::MinContribution::get(),
+			sponsor: None,
+			matched: Zero::zero(),
+			vesting: None,
+			claimed: Zero::zero(),
+			category: FundCategory::Other,
+			metadata: Vec::new(),
+			paused: false,
+			cancelled: false,
+		};
+		Pallet::<T, I>::schedule_status_checks(index, &fund);
+		Some(fund)
+	});
+	T::DbWeight::get().reads_writes(reads_writes, reads_writes)
+}
+
+/// Move each fund's contributions into the child trie whose id includes the name of the pallet
+/// instance, and have its phase checked from now on. Nothing else about the fund changes.
+pub fn migrate_to_v3<T: Config<I>, I: 'static>() -> Weight {
+	let mut reads_writes: Weight = 0;
+	for (index, fund) in Funds::<T, I>::iter().collect::<Vec<_>>() {
+		let moved = move_child_trie::<T, I>(index, &v2_id_from_index::<T, I>(index), |_| ());
+		Pallet::<T, I>::schedule_status_checks(index, &fund);
+		reads_writes =
+			reads_writes.saturating_add(Weight::from(moved).saturating_mul(2).saturating_add(4));
+	}
+	T::DbWeight::get().reads_writes(reads_writes, reads_writes)
+}
@@ -1,16 +1,27 @@
-use crate::{self as simple_crowdfund, Config, Error, FundInfo, ModuleId};
+use crate::{
+	self as simple_crowdfund, CapMode, Config, Error, FundCategory, FundIndex, FundInfo, FundPhase,
+	FundState, InstallmentPledge, Instance1, NegativeImbalanceOf, StorageVersion, VestingSchedule,
+	WeightInfo,
+};
 
 use frame_support::{
 	assert_noop, assert_ok, construct_runtime, parameter_types,
-	traits::{OnFinalize, OnInitialize},
+	storage::child,
+	traits::{
+		Currency, EnsureOrigin, GenesisBuild, Imbalance, OnFinalize, OnInitialize,
+		OnRuntimeUpgrade, OnUnbalanced, StorageMapShim,
+	},
+	weights::{constants::RocksDbWeight, GetDispatchInfo},
 };
+use frame_system::{EnsureRoot, EnsureSigned};
+use parity_scale_codec::Encode;
 use sp_core::H256;
 // The testing primitives are very useful for avoiding having to work with signatures
 // or public keys. `u64` is used as the `AccountId` and no `Signature`s are requried.
 use sp_runtime::{
 	testing::Header,
-	traits::{BlakeTwo256, IdentityLookup},
-	Percent, Permill,
+	traits::{BlakeTwo256, Hash, IdentityLookup},
+	DispatchError, FixedPointNumber, FixedU128, ModuleId, Perbill, Percent, Permill,
 };
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<TestRuntime>;
@@ -24,7 +35,10 @@ construct_runtime!(
 	{
 		System: frame_system::{Module, Call, Config, Storage, Event<T>},
 		Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
-		Crowdfund: simple_crowdfund::{Module, Call, Storage, Event<T>},
+		RewardBalances: pallet_balances::<Instance1>::{Module, Call, Storage, Event<T>},
+		Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
+		Crowdfund: simple_crowdfund::{Module, Call, Storage, Config<T>, Event<T>},
+		RewardCrowdfund: simple_crowdfund::<Instance1>::{Module, Call, Storage, Event<T>},
 	}
 );
 
@@ -48,7 +62,7 @@ impl frame_system::Config for TestRuntime {
 	type Header = Header;
 	type Event = Event;
 	type BlockHashCount = BlockHashCount;
-	type DbWeight = ();
+	type DbWeight = RocksDbWeight;
 	type Version = ();
 	type PalletInfo = PalletInfo;
 	type AccountData = pallet_balances::AccountData<u64>;
@@ -58,7 +72,7 @@ impl frame_system::Config for TestRuntime {
 	type SS58Prefix = ();
 }
 parameter_types! {
-	pub const ExistentialDeposit: u64 = 1;
+	pub static ExistentialDeposit: u64 = 1;
 }
 impl pallet_balances::Config for TestRuntime {
 	type Balance = u64;
@@ -69,6 +83,30 @@ impl pallet_balances::Config for TestRuntime {
 	type AccountStore = System;
 	type WeightInfo = ();
 }
+impl pallet_balances::Config<pallet_balances::Instance1> for TestRuntime {
+	type Balance = u64;
+	type MaxLocks = ();
+	type Event = Event;
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = StorageMapShim<
+		pallet_balances::Account<TestRuntime, pallet_balances::Instance1>,
+		frame_system::Provider<TestRuntime>,
+		u64,
+		pallet_balances::AccountData<u64>,
+	>;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 5;
+}
+impl pallet_timestamp::Config for TestRuntime {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
 
 parameter_types! {
 	pub const ProposalBond: Permill = Permill::from_percent(5);
@@ -83,23 +121,170 @@ parameter_types! {
 }
 
 parameter_types! {
+	pub const CrowdfundModuleId: ModuleId = ModuleId(*b"ex/cfund");
+	pub const RewardCrowdfundModuleId: ModuleId = ModuleId(*b"ex2/cfnd");
 	pub const SubmissionDeposit: u64 = 1;
 	pub const MinContribution: u64 = 10;
 	pub const RetirementPeriod: u64 = 5;
+	pub const MaxExtension: u64 = 10;
+	pub const MaxFundsPerContributor: u32 = 3;
+	pub const MaxFundsPerOwner: u32 = 4;
+	pub const MaxMetadataLen: u32 = 16;
+	pub const MaxMilestones: u32 = 3;
+	pub const MaxBonusTiers: u32 = 2;
+	pub const MaxWhitelistLen: u32 = 2;
+	pub const MaxInstallmentsPerFund: u32 = 2;
+	pub const MaxInstallmentFundsPerBlock: u32 = 2;
+	pub static RetirementDuration: Option<u64> = None;
+	pub static MaxContributionPerAccount: u64 = 0;
+	pub static MaxWithdrawPerBlock: Option<u64> = None;
+	pub static MaxContributionsPerBlock: u32 = 0;
+	pub static FeePercent: Perbill = Perbill::zero();
+	pub static CollectedFees: u64 = 0;
+	pub static DissolvedFunds: u64 = 0;
+	pub static RewardRate: FixedU128 = FixedU128::zero();
+	pub static ReserveContributions: bool = false;
+	pub static AllowOwnerContribution: bool = true;
+	pub static DepositPerCapUnit: u64 = 0;
+	pub static ContributionIncrement: u64 = 0;
+	pub static MatchRatio: Perbill = Perbill::zero();
+	pub static MinKeeperReward: u64 = 0;
+}
+
+/// Keeps a running total of the fees it is handed
+pub struct FeeCollector;
+impl OnUnbalanced<NegativeImbalanceOf<TestRuntime>> for FeeCollector {
+	fn on_nonzero_unbalanced(fee: NegativeImbalanceOf<TestRuntime>) {
+		CollectedFees::set(CollectedFees::get() + fee.peek());
+	}
 }
+
+/// Keeps a running total of the contributions left in dissolved funds
+pub struct DissolveDestination;
+impl OnUnbalanced<NegativeImbalanceOf<TestRuntime>> for DissolveDestination {
+	fn on_nonzero_unbalanced(leftover: NegativeImbalanceOf<TestRuntime>) {
+		DissolvedFunds::set(DissolvedFunds::get() + leftover.peek());
+	}
+}
+
 impl Config for TestRuntime {
 	type Event = Event;
 	type Currency = Balances;
+	type ReserveContributions = ReserveContributions;
+	type ModuleId = CrowdfundModuleId;
+	type SubmissionDeposit = SubmissionDeposit;
+	type DepositPerCapUnit = DepositPerCapUnit;
+	type MinContribution = MinContribution;
+	type ContributionIncrement = ContributionIncrement;
+	type RetirementPeriod = RetirementPeriod;
+	type Time = Timestamp;
+	type RetirementDuration = RetirementDuration;
+	type CreateOrigin = EnsureSigned<u64>;
+	type DissolveOrigin = EnsureSigned<u64>;
+	type DissolveDestination = DissolveDestination;
+	type MinKeeperReward = MinKeeperReward;
+	type SlashOrigin = EnsureRoot<u64>;
+	type SlashDestination = ();
+	type MaxExtension = MaxExtension;
+	type MaxFundsPerContributor = MaxFundsPerContributor;
+	type MaxFundsPerOwner = MaxFundsPerOwner;
+	type AllowOwnerContribution = AllowOwnerContribution;
+	type MaxContributionPerAccount = MaxContributionPerAccount;
+	type MaxWithdrawPerBlock = MaxWithdrawPerBlock;
+	type MaxContributionsPerBlock = MaxContributionsPerBlock;
+	type FeePercent = FeePercent;
+	type FeeCollector = FeeCollector;
+	type RewardCurrency = RewardBalances;
+	type RewardRate = RewardRate;
+	type MatchRatio = MatchRatio;
+	type MaxMetadataLen = MaxMetadataLen;
+	type MaxMilestones = MaxMilestones;
+	type MaxBonusTiers = MaxBonusTiers;
+	type MaxWhitelistLen = MaxWhitelistLen;
+	type MaxInstallmentsPerFund = MaxInstallmentsPerFund;
+	type MaxInstallmentFundsPerBlock = MaxInstallmentFundsPerBlock;
+	type WeightInfo = ();
+}
+
+/// Only lets account 1 through
+pub struct EnsureCurator;
+impl EnsureOrigin<Origin> for EnsureCurator {
+	type Success = u64;
+
+	fn try_origin(o: Origin) -> Result<u64, Origin> {
+		Into::<Result<frame_system::RawOrigin<u64>, Origin>>::into(o).and_then(|o| match o {
+			frame_system::RawOrigin::Signed(1) => Ok(1),
+			r => Err(Origin::from(r)),
+		})
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin() -> Origin {
+		Origin::signed(1)
+	}
+}
+
+// A second crowdfund, denominated in the reward token
+impl Config<Instance1> for TestRuntime {
+	type Event = Event;
+	type Currency = RewardBalances;
+	type ReserveContributions = ReserveContributions;
+	type ModuleId = RewardCrowdfundModuleId;
 	type SubmissionDeposit = SubmissionDeposit;
+	type DepositPerCapUnit = DepositPerCapUnit;
 	type MinContribution = MinContribution;
+	type ContributionIncrement = ContributionIncrement;
 	type RetirementPeriod = RetirementPeriod;
+	type Time = Timestamp;
+	type RetirementDuration = RetirementDuration;
+	type CreateOrigin = EnsureCurator;
+	// Only the curator may dissolve funds of this instance, and whatever is left is burned
+	type DissolveOrigin = EnsureCurator;
+	type DissolveDestination = ();
+	type MinKeeperReward = MinKeeperReward;
+	type SlashOrigin = EnsureRoot<u64>;
+	type SlashDestination = ();
+	type MaxExtension = MaxExtension;
+	type MaxFundsPerContributor = MaxFundsPerContributor;
+	type MaxFundsPerOwner = MaxFundsPerOwner;
+	type AllowOwnerContribution = AllowOwnerContribution;
+	type MaxContributionPerAccount = MaxContributionPerAccount;
+	type MaxWithdrawPerBlock = MaxWithdrawPerBlock;
+	type MaxContributionsPerBlock = MaxContributionsPerBlock;
+	type FeePercent = FeePercent;
+	type FeeCollector = ();
+	type RewardCurrency = Balances;
+	type RewardRate = RewardRate;
+	type MatchRatio = MatchRatio;
+	type MaxMetadataLen = MaxMetadataLen;
+	type MaxMilestones = MaxMilestones;
+	type MaxBonusTiers = MaxBonusTiers;
+	type MaxWhitelistLen = MaxWhitelistLen;
+	type MaxInstallmentsPerFund = MaxInstallmentsPerFund;
+	type MaxInstallmentFundsPerBlock = MaxInstallmentFundsPerBlock;
+	type WeightInfo = ();
 }
 
 use pallet_balances::Error as BalancesError;
 
 // This function basically just builds a genesis storage key/value store according to
 // our desired mockup.
-fn new_test_ext() -> sp_io::TestExternalities {
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default()
+		.build_storage::<TestRuntime>()
+		.unwrap();
+	pallet_balances::GenesisConfig::<TestRuntime> {
+		balances: vec![(1, 1000), (2, 2000), (3, 3000), (4, 4000)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	t.into()
+}
+
+fn new_test_ext_with_funds(
+	funds: Vec<(u64, u64, u64, u64, u64, u64)>,
+	contributions: Vec<(FundIndex, u64, u64)>,
+) -> sp_io::TestExternalities {
 	let mut t = frame_system::GenesisConfig::default()
 		.build_storage::<TestRuntime>()
 		.unwrap();
@@ -108,6 +293,19 @@ fn new_test_ext() -> sp_io::TestExternalities {
 	}
 	.assimilate_storage(&mut t)
 	.unwrap();
+	simple_crowdfund::GenesisConfig::<TestRuntime> {
+		funds,
+		contributions,
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	// Genesis building keys child tries by their unprefixed storage key, while test externalities
+	// expect them keyed as they are in the top trie
+	t.children_default = t
+		.children_default
+		.into_values()
+		.map(|child| (child.child_info.prefixed_storage_key().into_inner(), child))
+		.collect();
 	t.into()
 }
 
@@ -137,16 +335,41 @@ fn basic_setup_works() {
 fn create_works() {
 	new_test_ext().execute_with(|| {
 		// Now try to create a crowdfund campaign
-		assert_ok!(Crowdfund::create(Origin::signed(1), 2, 1000, 9));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
 		assert_eq!(Crowdfund::fund_count(), 1);
 		// This is what the initial `fund_info` should look like
 		let fund_info = FundInfo {
+			owner: 1,
 			beneficiary: 2,
 			deposit: 1,
 			raised: 0,
+			contributors: 0,
+			start: 0,
 			// 5 blocks length + 3 block ending period + 1 starting block
 			end: 9,
+			retirement_period: 5,
 			goal: 1000,
+			cap: 2000,
+			min_contribution: 10,
+			sponsor: None,
+			matched: 0,
+			vesting: None,
+			claimed: 0,
+			category: FundCategory::Other,
+			metadata: Vec::new(),
+			paused: false,
+			cancelled: false,
 		};
 		assert_eq!(Crowdfund::funds(0), Some(fund_info));
 		// User has deposit removed from their free balance
@@ -157,259 +380,4659 @@ fn create_works() {
 }
 
 #[test]
-fn create_handles_insufficient_balance() {
+fn create_moves_past_start_to_now() {
 	new_test_ext().execute_with(|| {
+		run_to_block(5);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			2,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Crowdfund::funds(0).unwrap().start, 5);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 100));
+
+		// The end is still checked against the current block
 		assert_noop!(
-			Crowdfund::create(Origin::signed(1337), 2, 1000, 9),
-			BalancesError::<TestRuntime, _>::InsufficientBalance
+			Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				2,
+				5,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			),
+			Error::<TestRuntime>::EndTooEarly
 		);
 	});
 }
 
 #[test]
-fn contribute_works() {
+fn create_stores_metadata() {
 	new_test_ext().execute_with(|| {
-		// Set up a crowdfund
-		assert_ok!(Crowdfund::create(Origin::signed(1), 2, 1000, 9));
-		assert_eq!(Balances::free_balance(1), 999);
-		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 1);
+		// Exactly at the limit is fine
+		let metadata = b"Save the whales!".to_vec();
+		assert_eq!(metadata.len(), 16);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			metadata.clone()
+		));
+		assert_eq!(Crowdfund::funds(0).unwrap().metadata, metadata);
+	});
+}
 
-		// No contributions yet
-		assert_eq!(Crowdfund::contribution_get(0, &1), 0);
+#[test]
+fn create_rejects_long_metadata() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				vec![0; 17]
+			),
+			Error::<TestRuntime>::MetadataTooLong
+		);
+		assert_eq!(Crowdfund::fund_count(), 0);
+	});
+}
 
-		// User 1 contributes to their own crowdfund
-		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 49));
-		// User 1 has spent some funds to do this, transfer fees **are** taken
-		assert_eq!(Balances::free_balance(1), 950);
-		// Contributions are stored in the trie
-		assert_eq!(Crowdfund::contribution_get(0, &1), 49);
-		// Contributions appear in free balance of crowdfund
-		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 50);
-		// Last contribution time recorded
-		assert_eq!(Crowdfund::funds(0).unwrap().raised, 49);
+#[test]
+fn create_rejects_minimum_below_global() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				9,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			),
+			Error::<TestRuntime>::MinContributionTooLow
+		);
+		assert_eq!(Crowdfund::fund_count(), 0);
 	});
 }
 
 #[test]
-fn contribute_handles_basic_errors() {
+fn create_requires_configured_origin() {
 	new_test_ext().execute_with(|| {
-		// Cannot contribute to non-existing fund
+		let _ = RewardBalances::deposit_creating(&1, 1000);
+		let _ = RewardBalances::deposit_creating(&3, 1000);
+
+		// This instance only lets its curator create funds
 		assert_noop!(
-			Crowdfund::contribute(Origin::signed(1), 0, 49),
-			Error::<TestRuntime>::InvalidIndex
+			RewardCrowdfund::create(
+				Origin::signed(3),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			),
+			DispatchError::BadOrigin
 		);
-		// Cannot contribute below minimum contribution
 		assert_noop!(
-			Crowdfund::contribute(Origin::signed(1), 0, 9),
-			Error::<TestRuntime>::ContributionTooSmall
+			RewardCrowdfund::create(
+				Origin::root(),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			),
+			DispatchError::BadOrigin
 		);
+		assert_ok!(RewardCrowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(RewardCrowdfund::funds(0).unwrap().owner, 1);
+		assert_eq!(RewardBalances::free_balance(1), 999);
 
-		// Set up a crowdfund
-		assert_ok!(Crowdfund::create(Origin::signed(1), 2, 1000, 9));
-		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 101));
-
-		// Move past end date
-		run_to_block(10);
+		// Anyone may create funds in the default instance
+		assert_ok!(Crowdfund::create(
+			Origin::signed(3),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Crowdfund::funds(0).unwrap().owner, 3);
+	});
+}
 
-		// Cannot contribute to ended fund
+#[test]
+fn create_rejects_goal_above_cap() {
+	new_test_ext().execute_with(|| {
 		assert_noop!(
-			Crowdfund::contribute(Origin::signed(1), 0, 49),
-			Error::<TestRuntime>::ContributionPeriodOver
+			Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(999),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			),
+			Error::<TestRuntime>::GoalAboveCap
 		);
+		// A fund may need to be filled to the brim to succeed
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(1000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
 	});
 }
 
 #[test]
-fn withdraw_works() {
+fn status_follows_fund_lifecycle() {
 	new_test_ext().execute_with(|| {
-		// Set up a crowdfund
-		assert_ok!(Crowdfund::create(Origin::signed(1), 2, 1000, 9));
-		// Transfer fees are taken here
-		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
-		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
-		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		assert_eq!(Crowdfund::status(0), None);
+		for _ in 0..2 {
+			assert_ok!(Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				5,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			));
+		}
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::NotStarted));
 
-		// Skip all the way to the end
-		// Crowdfund is unsuccessful 100 + 200 + 300 < 1000
-		run_to_block(50);
+		run_to_block(5);
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::Active));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 1000));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 1, 999));
 
-		// User can withdraw their full balance without fees
-		assert_ok!(Crowdfund::withdraw(Origin::signed(1), 0));
-		assert_eq!(Balances::free_balance(1), 999);
+		// Success is judged against the goal once the fund ends
+		run_to_block(9);
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::SucceededPendingClaim));
+		assert_eq!(Crowdfund::status(1), Some(FundPhase::Failed));
 
-		assert_ok!(Crowdfund::withdraw(Origin::signed(2), 0));
-		assert_eq!(Balances::free_balance(2), 2000);
+		// Both funds may be dissolved once the retirement period has passed
+		run_to_block(14);
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::Retiring));
+		assert_eq!(Crowdfund::status(1), Some(FundPhase::Retiring));
 
-		assert_ok!(Crowdfund::withdraw(Origin::signed(3), 0));
-		assert_eq!(Balances::free_balance(3), 3000);
+		assert_ok!(Crowdfund::claim(Origin::signed(1), 0));
+		assert_eq!(Crowdfund::status(0), None);
 	});
 }
 
 #[test]
-fn withdraw_handles_basic_errors() {
+fn status_changes_are_reported() {
 	new_test_ext().execute_with(|| {
-		// Set up a crowdfund
-		assert_ok!(Crowdfund::create(Origin::signed(1), 2, 1000, 9));
-		// Transfer fee is taken here
-		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 49));
-		assert_eq!(Balances::free_balance(1), 950);
+		run_to_block(1);
+		for _ in 0..2 {
+			assert_ok!(Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				3,
+				6,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			));
+		}
+		let changes = || {
+			System::events()
+				.into_iter()
+				.filter_map(|record| match record.event {
+					Event::simple_crowdfund(crate::Event::StatusChanged(index, status)) => {
+						Some((index, status))
+					}
+					_ => None,
+				})
+				.collect::<Vec<_>>()
+		};
 
-		run_to_block(5);
+		// Funds are only checked in the blocks they may change phase in
+		assert_eq!(Crowdfund::status_schedule(3), vec![0, 1]);
+		assert_eq!(Crowdfund::status_schedule(6), vec![0, 1]);
+		assert_eq!(Crowdfund::status_schedule(11), vec![0, 1]);
+		assert!(Crowdfund::status_schedule(4).is_empty());
 
-		// Cannot withdraw before fund ends
-		assert_noop!(
-			Crowdfund::withdraw(Origin::signed(1), 0),
-			Error::<TestRuntime>::FundStillActive
+		// Nothing is reported while funds stay in the phase they were created in
+		run_to_block(2);
+		assert_eq!(changes(), vec![]);
+
+		run_to_block(3);
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::Active));
+		assert_eq!(
+			changes(),
+			vec![(0, FundPhase::Active), (1, FundPhase::Active)]
 		);
+		assert!(Crowdfund::status_schedule(3).is_empty());
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 1000));
 
-		// Skip to the retirement period
-		// Crowdfund is unsuccessful 100 + 200 + 300 < 1000
-		run_to_block(10);
+		// Extending a fund moves its checks along with its end
+		assert_ok!(Crowdfund::extend_end(Origin::signed(1), 1, 7));
+		assert_eq!(Crowdfund::status_schedule(7), vec![1]);
+		assert_eq!(Crowdfund::status_schedule(12), vec![1]);
+		run_to_block(6);
+		assert_eq!(Crowdfund::status(1), Some(FundPhase::Active));
 
-		// Cannot withdraw if they did not contribute
-		assert_noop!(
-			Crowdfund::withdraw(Origin::signed(2), 0),
-			Error::<TestRuntime>::NoContribution
+		run_to_block(7);
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::SucceededPendingClaim));
+		assert_eq!(Crowdfund::status(1), Some(FundPhase::Failed));
+		assert_eq!(
+			changes()[2..],
+			[
+				(0, FundPhase::SucceededPendingClaim),
+				(1, FundPhase::Failed)
+			]
 		);
-		// Cannot withdraw from a non-existent fund
-		assert_noop!(
-			Crowdfund::withdraw(Origin::signed(1), 1),
-			Error::<TestRuntime>::InvalidIndex
+
+		// The retirement period passes
+		run_to_block(12);
+		assert_eq!(Crowdfund::status(1), Some(FundPhase::Retiring));
+		assert_eq!(
+			changes()[4..],
+			[(0, FundPhase::Retiring), (1, FundPhase::Retiring)]
 		);
+
+		// Nothing more is reported once the phase settles
+		run_to_block(15);
+		assert_eq!(changes().len(), 6);
 	});
 }
 
 #[test]
-fn dissolve_works() {
+fn create_with_issuance_fraction_cap() {
 	new_test_ext().execute_with(|| {
-		// Set up a crowdfund
-		assert_ok!(Crowdfund::create(Origin::signed(1), 2, 1000, 9));
-		// Transfer fee is taken here
-		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
-		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
-		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
-
-		// Skip all the way to the end
-		// Crowdfund is unsuccessful 100 + 200 + 300 < 1000
-		run_to_block(50);
-
-		// Check initiator's balance.
-		assert_eq!(Balances::free_balance(1), 899);
-		// Check current funds (contributions + deposit)
-		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 601);
-
-		// Account 7 dissolves the crowdfund claiming the remaining funds
-		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 0));
+		assert_eq!(Balances::total_issuance(), 10000);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			500,
+			CapMode::IssuanceFraction(Perbill::from_percent(10)),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Crowdfund::funds(0).unwrap().cap, 1000);
 
-		// Fund account is emptied
-		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 0);
-		// Dissolver account is rewarded
-		assert_eq!(Balances::free_balance(7), 601);
+		// The cap is fixed when the fund is created
+		let _ = Balances::deposit_creating(&5, 10000);
+		assert_eq!(Crowdfund::funds(0).unwrap().cap, 1000);
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 1000));
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(3), 0, 10),
+			Error::<TestRuntime>::FundClosed
+		);
 
-		// Storage trie is removed
-		assert_eq!(Crowdfund::contribution_get(0, &0), 0);
-		// Fund storage is removed
-		assert_eq!(Crowdfund::funds(0), None);
+		// The goal still may not be above the resolved cap
+		assert_noop!(
+			Crowdfund::create(
+				Origin::signed(1),
+				2,
+				2001,
+				CapMode::IssuanceFraction(Perbill::from_percent(10)),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			),
+			Error::<TestRuntime>::GoalAboveCap
+		);
 	});
 }
 
 #[test]
-fn dissolve_handles_basic_errors() {
+fn create_handles_insufficient_balance() {
 	new_test_ext().execute_with(|| {
-		// Set up a crowdfund
-		assert_ok!(Crowdfund::create(Origin::signed(1), 2, 1000, 9));
-		// Transfer fee is taken here
-		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
-		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
-		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		assert_noop!(
+			Crowdfund::create(
+				Origin::signed(1337),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			),
+			BalancesError::<TestRuntime, ()>::InsufficientBalance
+		);
+	});
+}
 
-		// Cannot dissolve an invalid fund index
+#[test]
+fn ensure_owner_works() {
+	new_test_ext().execute_with(|| {
 		assert_noop!(
-			Crowdfund::dissolve(Origin::signed(1), 1),
+			Crowdfund::ensure_owner(0, &1),
 			Error::<TestRuntime>::InvalidIndex
 		);
-		// Cannot dissolve an active fund
+		// Any account may own a fund. A multisig owner is just another account id here, so it
+		// passes the same check once its members approve the call.
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(
+			Crowdfund::ensure_owner(0, &1),
+			Ok(Crowdfund::funds(0).unwrap())
+		);
 		assert_noop!(
-			Crowdfund::dissolve(Origin::signed(1), 0),
-			Error::<TestRuntime>::FundNotRetired
+			Crowdfund::ensure_owner(0, &2),
+			Error::<TestRuntime>::NotOwner
 		);
 
-		run_to_block(10);
-
-		// Cannot disolve an ended but not yet retired fund
+		// Ownership moves with the fund
+		assert_ok!(Crowdfund::transfer_ownership(Origin::signed(1), 0, 2));
+		assert_ok!(Crowdfund::ensure_owner(0, &2));
 		assert_noop!(
-			Crowdfund::dissolve(Origin::signed(1), 0),
-			Error::<TestRuntime>::FundNotRetired
+			Crowdfund::set_paused(Origin::signed(1), 0, true),
+			Error::<TestRuntime>::NotOwner
 		);
 	});
 }
 
 #[test]
-fn dispense_works() {
+fn funds_of_tracks_owned_funds() {
 	new_test_ext().execute_with(|| {
-		// Set up a crowdfund
-		assert_ok!(Crowdfund::create(Origin::signed(1), 20, 1000, 9));
-		// Transfer fee is taken here
-		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
-		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
-		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
-		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 400));
+		for _ in 0..2 {
+			assert_ok!(Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			));
+		}
+		let mut owned = Crowdfund::funds_of(&1);
+		owned.sort();
+		assert_eq!(owned, vec![0, 1]);
+		assert_eq!(Crowdfund::funds_of(&3), Vec::<FundIndex>::new());
 
-		// Skip to the retirement period
-		// Crowdfund is successful 100 + 200 + 300 + 400  >= 1000
-		run_to_block(10);
+		assert_ok!(Crowdfund::transfer_ownership(Origin::signed(1), 1, 3));
+		assert_eq!(Crowdfund::funds_of(&1), vec![0]);
+		assert_eq!(Crowdfund::funds_of(&3), vec![1]);
 
-		// Check initiator's balance.
-		assert_eq!(Balances::free_balance(1), 899);
-		// Check current funds (contributions + deposit)
-		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 1001);
+		// Removed funds leave the index
+		run_to_block(20);
+		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 1));
+		assert_eq!(Crowdfund::funds_of(&3), Vec::<FundIndex>::new());
+		assert_eq!(Crowdfund::funds_of(&1), vec![0]);
+	});
+}
 
-		// Account 7 dispenses the crowdfund
-		assert_ok!(Crowdfund::dispense(Origin::signed(7), 0));
+#[test]
+fn funds_are_indexed_by_category() {
+	new_test_ext().execute_with(|| {
+		for (owner, category) in &[
+			(1, FundCategory::Tech),
+			(2, FundCategory::Art),
+			(3, FundCategory::Tech),
+		] {
+			assert_ok!(Crowdfund::create(
+				Origin::signed(*owner),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				*category,
+				Vec::new()
+			));
+		}
+		assert_eq!(Crowdfund::funds(1).unwrap().category, FundCategory::Art);
 
-		// Fund account is emptied
-		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 0);
-		// Beneficiary account is funded
-		assert_eq!(Balances::free_balance(20), 1000);
-		// Dispensor account is rewarded deposit
-		assert_eq!(Balances::free_balance(7), 1);
+		let mut tech = Crowdfund::funds_in_category(FundCategory::Tech);
+		tech.sort();
+		assert_eq!(tech, vec![0, 2]);
+		assert_eq!(Crowdfund::funds_in_category(FundCategory::Art), vec![1]);
+		assert!(Crowdfund::funds_in_category(FundCategory::Community).is_empty());
 
-		// Storage trie is removed
-		assert_eq!(Crowdfund::contribution_get(0, &0), 0);
-		// Fund storage is removed
-		assert_eq!(Crowdfund::funds(0), None);
+		// Dissolved funds leave their category
+		run_to_block(50);
+		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 0));
+		assert_eq!(Crowdfund::funds_in_category(FundCategory::Tech), vec![2]);
 	});
 }
 
 #[test]
-fn dispense_handles_basic_errors() {
+fn contribute_works() {
 	new_test_ext().execute_with(|| {
 		// Set up a crowdfund
-		assert_ok!(Crowdfund::create(Origin::signed(1), 2, 1000, 9));
-		// Transfer fee is taken here
-		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
-		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
-		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
-
-		// Cannot dispense an invalid fund index
-		assert_noop!(
-			Crowdfund::dispense(Origin::signed(1), 1),
-			Error::<TestRuntime>::InvalidIndex
-		);
-		// Cannot dispense an active fund
-		assert_noop!(
-			Crowdfund::dispense(Origin::signed(1), 0),
-			Error::<TestRuntime>::FundStillActive
-		);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Balances::free_balance(1), 999);
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 1);
 
-		// Skip to the retirement period
-		// Crowdfund is unsuccessful 100 + 200 + 300 < 1000
-		run_to_block(10);
+		// No contributions yet
+		assert_eq!(Crowdfund::contribution_get(0, &1), 0);
 
-		// Cannot disopens an ended but unsuccessful fund
-		assert_noop!(
-			Crowdfund::dispense(Origin::signed(1), 0),
-			Error::<TestRuntime>::UnsuccessfulFund
-		);
+		// User 1 contributes to their own crowdfund
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 49));
+		// User 1 has spent some funds to do this, transfer fees **are** taken
+		assert_eq!(Balances::free_balance(1), 950);
+		// Contributions are stored in the trie
+		assert_eq!(Crowdfund::contribution_get(0, &1), 49);
+		// Contributions appear in free balance of crowdfund
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 50);
+		// Last contribution time recorded
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 49);
+	});
+}
+
+#[test]
+fn contribute_with_referral_works() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute_with_referral(
+			Origin::signed(2),
+			0,
+			100,
+			4
+		));
+		assert_ok!(Crowdfund::contribute_with_referral(
+			Origin::signed(3),
+			0,
+			250,
+			4
+		));
+
+		// Both contributions count as usual, and the referrer is credited with both
+		assert_eq!(Crowdfund::contribution_get(0, &2), 100);
+		assert_eq!(Crowdfund::contribution_get(0, &3), 250);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 350);
+		assert_eq!(Crowdfund::referrals(0, 4), 350);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Referred(0, 4, 250))
+		);
+
+		// Contributors cannot refer themselves
+		assert_noop!(
+			Crowdfund::contribute_with_referral(Origin::signed(2), 0, 100, 2),
+			Error::<TestRuntime>::SelfReferral
+		);
+		// Failed contributions credit nobody
+		assert_noop!(
+			Crowdfund::contribute_with_referral(Origin::signed(2), 0, 5, 4),
+			Error::<TestRuntime>::ContributionTooSmall
+		);
+		assert_eq!(Crowdfund::referrals(0, 4), 350);
+
+		// The referrer is only credited with what reaches the fund after the fee
+		FeePercent::set(Perbill::from_percent(10));
+		assert_ok!(Crowdfund::contribute_with_referral(
+			Origin::signed(3),
+			0,
+			200,
+			4
+		));
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 530);
+		assert_eq!(Crowdfund::referrals(0, 4), 530);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Referred(0, 4, 180))
+		);
+		FeePercent::set(Perbill::zero());
+	});
+}
+
+#[test]
+fn contributions_record_first_block() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Crowdfund::contribution_record(0, &2), None);
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+
+		run_to_block(3);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 200));
+		assert_eq!(Crowdfund::contribution_record(0, &2), Some((100, 1)));
+		assert_eq!(Crowdfund::contribution_record(0, &3), Some((200, 3)));
+
+		// Later contributions keep the block of the first
+		run_to_block(5);
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 50));
+		assert_eq!(Crowdfund::contribution_record(0, &2), Some((150, 1)));
+
+		// Contributions stored as a bare balance read as made at block zero
+		2u64.using_encoded(|b| child::put(&Crowdfund::id_from_index(0), b, &150u64));
+		assert_eq!(Crowdfund::contribution_record(0, &2), Some((150, 0)));
+		assert_eq!(Crowdfund::contribution_get(0, &2), 150);
+	});
+}
+
+#[test]
+fn contribute_emits_fund_total() {
+	new_test_ext().execute_with(|| {
+		// Events are not recorded at genesis
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+
+		// The last event carries the fund total after both contributions
+		let raised = Crowdfund::funds(0).unwrap().raised;
+		assert_eq!(raised, 300);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Contributed(
+				2,
+				0,
+				200,
+				0,
+				0,
+				raised,
+				Perbill::from_percent(15),
+				1
+			))
+		);
+	});
+}
+
+#[test]
+fn contribute_emits_cap_progress() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			500,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 400));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 600));
+
+		// 1000 of the 2000 cap has been raised
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Contributed(
+				4,
+				0,
+				600,
+				0,
+				0,
+				1000,
+				Perbill::from_percent(50),
+				1
+			))
+		);
+	});
+}
+
+#[test]
+fn contribute_routes_fee_to_collector() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		FeePercent::set(Perbill::from_percent(3));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// 3% of 105 is 3.15, rounded down in the contributor's favour
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 105));
+		assert_eq!(CollectedFees::get(), 3);
+		assert_eq!(Balances::free_balance(1), 999 - 105);
+		assert_eq!(Crowdfund::contribution_get(0, &1), 102);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 102);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Contributed(
+				1,
+				0,
+				102,
+				0,
+				0,
+				102,
+				Perbill::from_rational_approximation(102u64, 2000),
+				1
+			))
+		);
+
+		// The fee accumulates across contributions and only the net amount counts
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 10));
+		assert_eq!(CollectedFees::get(), 3);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 10);
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_eq!(CollectedFees::get(), 9);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 204);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 306);
+	});
+}
+
+#[test]
+fn contribute_mints_rewards() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		RewardRate::set(FixedU128::saturating_from_rational(1, 4));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// A quarter of 105 is 26.25, rounded down
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 105));
+		assert_eq!(RewardBalances::free_balance(3), 26);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Contributed(
+				3,
+				0,
+				105,
+				0,
+				26,
+				105,
+				Perbill::from_rational_approximation(105u64, 2000),
+				1
+			))
+		);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 40));
+		assert_eq!(RewardBalances::free_balance(3), 36);
+
+		// Contributions too small to earn a whole unit earn nothing
+		RewardRate::set(FixedU128::saturating_from_rational(1, 20));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 10));
+		assert_eq!(RewardBalances::free_balance(4), 0);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Contributed(
+				4,
+				0,
+				10,
+				0,
+				0,
+				155,
+				Perbill::from_rational_approximation(155u64, 2000),
+				1
+			))
+		);
+		assert_eq!(RewardBalances::total_issuance(), 36);
+	});
+}
+
+#[test]
+fn contribute_can_reach_cap_exactly() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund with a cap of 500
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			100,
+			CapMode::Absolute(500),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 300));
+
+		// Contributing exactly up to the cap is allowed
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 200));
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 500);
+	});
+}
+
+#[test]
+fn fund_closes_once_cap_is_reached() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			100,
+			CapMode::Absolute(500),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 300));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 200));
+
+		// Filling the fund ends it at once
+		assert_eq!(Crowdfund::funds(0).unwrap().end, 1);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::CapReached(0, 1))
+		);
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(4), 0, 10),
+			Error::<TestRuntime>::FundClosed
+		);
+		run_to_block(2);
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(4), 0, 10),
+			Error::<TestRuntime>::FundClosed
+		);
+		let cap_reached = System::events()
+			.iter()
+			.filter(|record| {
+				record.event == Event::simple_crowdfund(crate::Event::CapReached(0, 1))
+			})
+			.count();
+		assert_eq!(cap_reached, 1);
+
+		// The owner does not have to wait for the original end to claim the proceeds
+		assert_ok!(Crowdfund::claim(Origin::signed(1), 0));
+		assert_eq!(Balances::free_balance(1), 1500);
+	});
+}
+
+#[test]
+fn contribute_cannot_exceed_cap() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund with a cap of 500
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			100,
+			CapMode::Absolute(500),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 300));
+
+		// One unit over the cap is rejected
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(3), 0, 201),
+			Error::<TestRuntime>::CapExceeded
+		);
+		// Neither can a sponsor match beyond the cap
+		assert_noop!(
+			Crowdfund::match_funds(Origin::signed(3), 0, 201),
+			Error::<TestRuntime>::CapExceeded
+		);
+	});
+}
+
+#[test]
+fn owner_contribution_can_be_forbidden() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// Owners may back their own funds by default
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+
+		AllowOwnerContribution::set(false);
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(1), 0, 100),
+			Error::<TestRuntime>::OwnerCannotContribute
+		);
+		// Everyone else, including the beneficiary, still may
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+		assert_eq!(Crowdfund::contribution_get(0, &1), 100);
+		AllowOwnerContribution::set(true);
+	});
+}
+
+#[test]
+fn contribute_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		// Cannot contribute to non-existing fund
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(1), 0, 49),
+			Error::<TestRuntime>::InvalidIndex
+		);
+
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// Cannot contribute below minimum contribution
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(1), 0, 9),
+			Error::<TestRuntime>::ContributionTooSmall
+		);
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 101));
+
+		// Move past end date
+		run_to_block(10);
+
+		// Cannot contribute to ended fund
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(1), 0, 49),
+			Error::<TestRuntime>::ContributionPeriodOver
+		);
+	});
+}
+
+#[test]
+fn contributions_must_be_an_increment() {
+	new_test_ext().execute_with(|| {
+		ContributionIncrement::set(25);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// Exact multiples are accepted
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 25));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 150));
+		assert_eq!(Crowdfund::contribution_get(0, &3), 150);
+
+		// Anything else is rejected
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(2), 0, 30),
+			Error::<TestRuntime>::NotAnIncrement
+		);
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(4), 0, 101),
+			Error::<TestRuntime>::NotAnIncrement
+		);
+
+		// A zero increment allows any amount
+		ContributionIncrement::set(0);
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 101));
+	});
+}
+
+#[test]
+fn extend_end_works() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// Owner pushes the end back by the maximum allowed extension
+		assert_ok!(Crowdfund::extend_end(Origin::signed(1), 0, 19));
+		assert_eq!(Crowdfund::funds(0).unwrap().end, 19);
+
+		// Contributions are still accepted after the original end
+		run_to_block(10);
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+	});
+}
+
+#[test]
+fn increase_cap_works() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 1950));
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(3), 0, 100),
+			Error::<TestRuntime>::CapExceeded
+		);
+
+		// A higher cap makes room for more contributions
+		assert_ok!(Crowdfund::increase_cap(Origin::signed(1), 0, 2500));
+		assert_eq!(Crowdfund::funds(0).unwrap().cap, 2500);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::CapIncreased(0, 2500))
+		);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 100));
+	});
+}
+
+#[test]
+fn increase_cap_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Crowdfund::increase_cap(Origin::signed(1), 0, 2500),
+			Error::<TestRuntime>::InvalidIndex
+		);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// Only the owner may raise the cap
+		assert_noop!(
+			Crowdfund::increase_cap(Origin::signed(2), 0, 2500),
+			Error::<TestRuntime>::NotOwner
+		);
+		// The cap may not be lowered or left unchanged
+		assert_noop!(
+			Crowdfund::increase_cap(Origin::signed(1), 0, 2000),
+			Error::<TestRuntime>::CapNotIncreased
+		);
+		assert_noop!(
+			Crowdfund::increase_cap(Origin::signed(1), 0, 1500),
+			Error::<TestRuntime>::CapNotIncreased
+		);
+
+		// The cap is fixed once the fund ends
+		run_to_block(10);
+		assert_noop!(
+			Crowdfund::increase_cap(Origin::signed(1), 0, 2500),
+			Error::<TestRuntime>::ContributionPeriodOver
+		);
+	});
+}
+
+#[test]
+fn transfer_ownership_works() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		assert_ok!(Crowdfund::transfer_ownership(Origin::signed(1), 0, 4));
+		assert_eq!(Crowdfund::funds(0).unwrap().owner, 4);
+		assert_eq!(Crowdfund::owner_fund_count(1), 0);
+		assert_eq!(Crowdfund::owner_fund_count(4), 1);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::OwnershipTransferred(0, 1, 4))
+		);
+
+		// The new owner manages the fund, and the old one no longer can
+		assert_noop!(
+			Crowdfund::increase_cap(Origin::signed(1), 0, 2500),
+			Error::<TestRuntime>::NotOwner
+		);
+		assert_ok!(Crowdfund::increase_cap(Origin::signed(4), 0, 2500));
+
+		// The deposit goes to the new owner when they claim the proceeds
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 1000));
+		run_to_block(10);
+		assert_ok!(Crowdfund::claim(Origin::signed(4), 0));
+		assert_eq!(Balances::free_balance(4), 5001);
+		assert_eq!(Balances::free_balance(1), 999);
+	});
+}
+
+#[test]
+fn transfer_ownership_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Crowdfund::transfer_ownership(Origin::signed(1), 0, 4),
+			Error::<TestRuntime>::InvalidIndex
+		);
+		for _ in 0..4 {
+			assert_ok!(Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			));
+		}
+
+		// Only the owner may hand the fund over
+		assert_noop!(
+			Crowdfund::transfer_ownership(Origin::signed(2), 0, 4),
+			Error::<TestRuntime>::NotOwner
+		);
+		// The new owner must have room for another fund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(4),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::transfer_ownership(Origin::signed(1), 0, 4));
+		assert_ok!(Crowdfund::transfer_ownership(Origin::signed(1), 1, 4));
+		assert_ok!(Crowdfund::transfer_ownership(Origin::signed(1), 2, 4));
+		assert_noop!(
+			Crowdfund::transfer_ownership(Origin::signed(1), 3, 4),
+			Error::<TestRuntime>::TooManyFunds
+		);
+	});
+}
+
+#[test]
+fn set_paused_works() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 100));
+
+		// A paused fund takes no contributions or matched funds
+		assert_ok!(Crowdfund::set_paused(Origin::signed(1), 0, true));
+		assert!(Crowdfund::funds(0).unwrap().paused);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::PausedSet(0, true))
+		);
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(3), 0, 100),
+			Error::<TestRuntime>::FundPaused
+		);
+		assert_noop!(
+			Crowdfund::match_funds(Origin::signed(4), 0, 100),
+			Error::<TestRuntime>::FundPaused
+		);
+
+		// Contributions resume once the owner unpauses
+		assert_ok!(Crowdfund::set_paused(Origin::signed(1), 0, false));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 100));
+		assert_eq!(Crowdfund::contribution_get(0, &3), 200);
+
+		// Backers can still get their money out of a paused fund that failed
+		assert_ok!(Crowdfund::set_paused(Origin::signed(1), 0, true));
+		run_to_block(10);
+		assert_ok!(Crowdfund::withdraw(Origin::signed(3), 0));
+		assert_eq!(Balances::free_balance(3), 3000);
+	});
+}
+
+#[test]
+fn set_paused_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Crowdfund::set_paused(Origin::signed(1), 0, true),
+			Error::<TestRuntime>::InvalidIndex
+		);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// Only the owner may pause a fund
+		assert_noop!(
+			Crowdfund::set_paused(Origin::signed(2), 0, true),
+			Error::<TestRuntime>::NotOwner
+		);
+	});
+}
+
+#[test]
+fn extend_end_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		// Cannot extend a non-existent fund
+		assert_noop!(
+			Crowdfund::extend_end(Origin::signed(1), 0, 15),
+			Error::<TestRuntime>::InvalidIndex
+		);
+
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// Only the owner may extend the fund
+		assert_noop!(
+			Crowdfund::extend_end(Origin::signed(2), 0, 15),
+			Error::<TestRuntime>::NotOwner
+		);
+		// The end may not be moved earlier or left unchanged
+		assert_noop!(
+			Crowdfund::extend_end(Origin::signed(1), 0, 9),
+			Error::<TestRuntime>::EndNotExtended
+		);
+		assert_noop!(
+			Crowdfund::extend_end(Origin::signed(1), 0, 5),
+			Error::<TestRuntime>::EndNotExtended
+		);
+		// The end may not be pushed back further than `MaxExtension`
+		assert_noop!(
+			Crowdfund::extend_end(Origin::signed(1), 0, 20),
+			Error::<TestRuntime>::ExtensionTooLong
+		);
+		// Nor may it be by extending it several times, each within `MaxExtension`
+		assert_ok!(Crowdfund::extend_end(Origin::signed(1), 0, 15));
+		assert_eq!(Crowdfund::original_end(0), Some(9));
+		assert_ok!(Crowdfund::extend_end(Origin::signed(1), 0, 19));
+		assert_noop!(
+			Crowdfund::extend_end(Origin::signed(1), 0, 20),
+			Error::<TestRuntime>::ExtensionTooLong
+		);
+
+		run_to_block(19);
+
+		// Cannot extend a fund that has already ended
+		assert_noop!(
+			Crowdfund::extend_end(Origin::signed(1), 0, 25),
+			Error::<TestRuntime>::ContributionPeriodOver
+		);
+	});
+}
+
+#[test]
+fn withdraw_works() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// Transfer fees are taken here
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+
+		// Skip all the way to the end
+		// Crowdfund is unsuccessful 100 + 200 + 300 < 1000
+		run_to_block(50);
+
+		// User can withdraw their full balance without fees
+		assert_ok!(Crowdfund::withdraw(Origin::signed(1), 0));
+		assert_eq!(Balances::free_balance(1), 999);
+
+		assert_ok!(Crowdfund::withdraw(Origin::signed(2), 0));
+		assert_eq!(Balances::free_balance(2), 2000);
+
+		assert_ok!(Crowdfund::withdraw(Origin::signed(3), 0));
+		assert_eq!(Balances::free_balance(3), 3000);
+	});
+}
+
+#[test]
+fn total_raised_is_tracked_across_funds() {
+	new_test_ext().execute_with(|| {
+		for _ in 0..2 {
+			assert_ok!(Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			));
+		}
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 1, 200));
+		assert_eq!(Crowdfund::total_raised(), 600);
+
+		// Both funds fail
+		run_to_block(50);
+		assert_ok!(Crowdfund::withdraw(Origin::signed(3), 0));
+		assert_eq!(Crowdfund::total_raised(), 300);
+		assert_ok!(Crowdfund::withdraw_partial(Origin::signed(4), 0, 40));
+		assert_eq!(Crowdfund::total_raised(), 260);
+
+		// Whatever is left in a dissolved fund no longer counts
+		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 1));
+		assert_eq!(Crowdfund::total_raised(), 60);
+		assert_eq!(
+			Crowdfund::total_raised(),
+			Crowdfund::funds(0).unwrap().raised
+		);
+	});
+}
+
+#[test]
+fn contributions_iter_lists_every_backer() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Crowdfund::contributions_iter(0), vec![]);
+
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 50));
+
+		let mut contributions = Crowdfund::contributions_iter(0);
+		contributions.sort();
+		assert_eq!(contributions, vec![(1, 100), (2, 250), (3, 300)]);
+	});
+}
+
+#[test]
+fn contributors_are_counted() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Crowdfund::funds(0).unwrap().contributors, 0);
+
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_eq!(Crowdfund::funds(0).unwrap().contributors, 1);
+
+		// Contributing again does not count twice
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_eq!(Crowdfund::funds(0).unwrap().contributors, 2);
+
+		run_to_block(10);
+
+		// A partial withdrawal keeps the contributor
+		assert_ok!(Crowdfund::withdraw_partial(Origin::signed(2), 0, 50));
+		assert_eq!(Crowdfund::funds(0).unwrap().contributors, 2);
+
+		// A full withdrawal removes the contributor
+		assert_ok!(Crowdfund::withdraw(Origin::signed(1), 0));
+		assert_eq!(Crowdfund::funds(0).unwrap().contributors, 1);
+
+		// Withdrawing the remainder removes the contributor as well
+		assert_ok!(Crowdfund::withdraw_partial(Origin::signed(2), 0, 150));
+		assert_eq!(Crowdfund::funds(0).unwrap().contributors, 0);
+	});
+}
+
+#[test]
+fn withdraw_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// Transfer fee is taken here
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 49));
+		assert_eq!(Balances::free_balance(1), 950);
+
+		run_to_block(5);
+
+		// Cannot withdraw before fund ends
+		assert_noop!(
+			Crowdfund::withdraw(Origin::signed(1), 0),
+			Error::<TestRuntime>::FundStillActive
+		);
+
+		// Skip to the retirement period
+		// Crowdfund is unsuccessful 100 + 200 + 300 < 1000
+		run_to_block(10);
+
+		// Cannot withdraw if they did not contribute
+		assert_noop!(
+			Crowdfund::withdraw(Origin::signed(2), 0),
+			Error::<TestRuntime>::NoContribution
+		);
+		// Cannot withdraw from a non-existent fund
+		assert_noop!(
+			Crowdfund::withdraw(Origin::signed(1), 1),
+			Error::<TestRuntime>::InvalidIndex
+		);
+	});
+}
+
+#[test]
+fn withdraw_partial_works() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+
+		// Crowdfund is unsuccessful 100 + 200 < 1000
+		run_to_block(50);
+
+		// User can withdraw less than their balance
+		assert_ok!(Crowdfund::withdraw_partial(Origin::signed(1), 0, 40));
+		assert_eq!(Balances::free_balance(1), 939);
+		assert_eq!(Crowdfund::contribution_get(0, &1), 60);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 260);
+
+		// User can withdraw exactly their remaining balance
+		assert_ok!(Crowdfund::withdraw_partial(Origin::signed(1), 0, 60));
+		assert_eq!(Balances::free_balance(1), 999);
+		assert_eq!(Crowdfund::contribution_get(0, &1), 0);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 200);
+	});
+}
+
+#[test]
+fn withdraw_partial_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+
+		// Cannot withdraw before fund ends
+		assert_noop!(
+			Crowdfund::withdraw_partial(Origin::signed(1), 0, 50),
+			Error::<TestRuntime>::FundStillActive
+		);
+
+		run_to_block(10);
+
+		// Cannot withdraw more than the stored balance
+		assert_noop!(
+			Crowdfund::withdraw_partial(Origin::signed(1), 0, 101),
+			Error::<TestRuntime>::InsufficientContribution
+		);
+		// Cannot withdraw nothing
+		assert_noop!(
+			Crowdfund::withdraw_partial(Origin::signed(1), 0, 0),
+			Error::<TestRuntime>::ZeroWithdrawal
+		);
+		// Cannot withdraw if they did not contribute
+		assert_noop!(
+			Crowdfund::withdraw_partial(Origin::signed(2), 0, 50),
+			Error::<TestRuntime>::NoContribution
+		);
+		// Cannot withdraw from a non-existent fund
+		assert_noop!(
+			Crowdfund::withdraw_partial(Origin::signed(1), 1, 50),
+			Error::<TestRuntime>::InvalidIndex
+		);
+	});
+}
+
+#[test]
+fn failed_matched_fund_refunds_sponsor_and_backers() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// Account 4 sponsors the fund
+		assert_ok!(Crowdfund::match_funds(Origin::signed(4), 0, 300));
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+
+		let fund = Crowdfund::funds(0).unwrap();
+		assert_eq!(fund.raised, 600);
+		assert_eq!(fund.matched, 300);
+		assert_eq!(fund.sponsor, Some(4));
+
+		// Crowdfund is unsuccessful 300 + 100 + 200 < 1000
+		run_to_block(50);
+
+		// Backers get back only what they contributed
+		assert_ok!(Crowdfund::withdraw(Origin::signed(1), 0));
+		assert_eq!(Balances::free_balance(1), 999);
+		assert_ok!(Crowdfund::withdraw(Origin::signed(2), 0));
+		assert_eq!(Balances::free_balance(2), 2000);
+
+		// The sponsor gets back the matched amount
+		assert_ok!(Crowdfund::withdraw_match(Origin::signed(4), 0));
+		assert_eq!(Balances::free_balance(4), 4000);
+
+		// Only the deposit is left in the fund
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 0);
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 1);
+	});
+}
+
+#[test]
+fn matching_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::match_funds(Origin::signed(4), 0, 300));
+
+		// A fund has a single sponsor
+		assert_noop!(
+			Crowdfund::match_funds(Origin::signed(3), 0, 300),
+			Error::<TestRuntime>::SponsorAlreadySet
+		);
+		// Matched funds cannot be reclaimed while the fund is running
+		assert_noop!(
+			Crowdfund::withdraw_match(Origin::signed(4), 0),
+			Error::<TestRuntime>::FundStillActive
+		);
+
+		// Crowdfund is successful 300 + 700 >= 1000
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 700));
+		run_to_block(10);
+
+		// Only the sponsor may reclaim matched funds
+		assert_noop!(
+			Crowdfund::withdraw_match(Origin::signed(3), 0),
+			Error::<TestRuntime>::NotSponsor
+		);
+		// Matched funds of a successful fund go to the beneficiary
+		assert_noop!(
+			Crowdfund::withdraw_match(Origin::signed(4), 0),
+			Error::<TestRuntime>::SuccessfulFund
+		);
+	});
+}
+
+#[test]
+fn match_pool_matches_contributions() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		MatchRatio::set(Perbill::from_percent(50));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::fund_match_pool(Origin::signed(4), 0, 150));
+		assert_eq!(Crowdfund::match_pool(0), 150);
+		assert_eq!(Balances::free_balance(4), 3850);
+		// The pool does not count toward the fund until it is drawn
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 0);
+
+		// Half of each contribution is matched
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 150);
+		assert_eq!(Crowdfund::match_pool(0), 100);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Contributed(
+				2,
+				0,
+				100,
+				50,
+				0,
+				150,
+				Perbill::from_rational_approximation(150u64, 2000),
+				1
+			))
+		);
+
+		// Until the pool runs out
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 400));
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 650);
+		assert_eq!(Crowdfund::match_pool(0), 0);
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 750);
+		assert_eq!(Crowdfund::funds(0).unwrap().matched, 150);
+		assert_eq!(Crowdfund::total_raised(), 750);
+
+		// Contributions are recorded without the match
+		assert_eq!(Crowdfund::contribution_get(0, &2), 200);
+		assert_eq!(Crowdfund::contribution_get(0, &3), 400);
+
+		// The pool has a single sponsor, shared with direct matching
+		assert_noop!(
+			Crowdfund::fund_match_pool(Origin::signed(3), 0, 100),
+			Error::<TestRuntime>::SponsorAlreadySet
+		);
+
+		MatchRatio::set(Perbill::zero());
+	});
+}
+
+#[test]
+fn unused_match_pool_returns_to_sponsor() {
+	new_test_ext().execute_with(|| {
+		MatchRatio::set(Perbill::from_percent(50));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::fund_match_pool(Origin::signed(4), 0, 500));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 800));
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 1200);
+
+		// The fund succeeds, so the sponsor gets back only what was not drawn
+		run_to_block(10);
+		assert_ok!(Crowdfund::withdraw_match(Origin::signed(4), 0));
+		assert_eq!(Balances::free_balance(4), 3600);
+		assert_eq!(Crowdfund::match_pool(0), 0);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 1200);
+
+		// Nothing more can be reclaimed
+		assert_noop!(
+			Crowdfund::withdraw_match(Origin::signed(4), 0),
+			Error::<TestRuntime>::SuccessfulFund
+		);
+		MatchRatio::set(Perbill::zero());
+	});
+}
+
+#[test]
+fn claim_returns_unused_match_pool() {
+	new_test_ext().execute_with(|| {
+		MatchRatio::set(Perbill::from_percent(50));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::fund_match_pool(Origin::signed(4), 0, 500));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 800));
+
+		run_to_block(10);
+		assert_ok!(Crowdfund::claim(Origin::signed(1), 0));
+		// The owner gets the proceeds, including what was matched, and the deposit
+		assert_eq!(Balances::free_balance(1), 2200);
+		assert_eq!(Balances::free_balance(4), 3600);
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 0);
+		MatchRatio::set(Perbill::zero());
+	});
+}
+
+#[test]
+fn failed_fund_returns_match_funds_to_sponsor() {
+	// Refunding the backers of a failed fund returns what was drawn from the pool along with the
+	// rest of it
+	new_test_ext().execute_with(|| {
+		MatchRatio::set(Perbill::from_percent(50));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::fund_match_pool(Origin::signed(4), 0, 500));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 200));
+		assert_eq!(Crowdfund::funds(0).unwrap().matched, 100);
+		assert_eq!(Crowdfund::match_pool(0), 400);
+
+		run_to_block(11);
+		assert_ok!(Crowdfund::refund_all(Origin::signed(1), 0, 10));
+		assert_eq!(Balances::free_balance(3), 3000);
+		assert_eq!(Balances::free_balance(4), 4000);
+		assert!(System::events().iter().any(|record| record.event
+			== Event::simple_crowdfund(crate::Event::MatchRefunded(4, 0, 500, 11))));
+		let fund = Crowdfund::funds(0).unwrap();
+		assert_eq!((fund.raised, fund.matched), (0, 0));
+		assert_eq!(Crowdfund::match_pool(0), 0);
+		assert_eq!(Crowdfund::total_raised(), 0);
+		MatchRatio::set(Perbill::zero());
+	});
+
+	// As does dissolving it, rather than sending the matched funds to `DissolveDestination`
+	new_test_ext().execute_with(|| {
+		MatchRatio::set(Perbill::from_percent(50));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::fund_match_pool(Origin::signed(4), 0, 500));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 200));
+		assert_eq!(Crowdfund::funds(0).unwrap().matched, 100);
+		assert_eq!(Crowdfund::match_pool(0), 400);
+
+		run_to_block(50);
+		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 0));
+		assert_eq!(Balances::free_balance(4), 4000);
+		assert_eq!(DissolvedFunds::get(), 200);
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 0);
+		MatchRatio::set(Perbill::zero());
+	});
+}
+
+#[test]
+fn refund_all_works_in_batches() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 150));
+
+		// Crowdfund is unsuccessful 100 + 200 + 300 + 150 < 1000
+		run_to_block(10);
+
+		// Anyone can refund the first batch
+		assert_ok!(Crowdfund::refund_all(Origin::signed(7), 0, 3));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Refunded(0, 3))
+		);
+		assert_eq!(Crowdfund::funds(0).unwrap().contributors, 1);
+
+		// The second batch refunds whoever is left
+		assert_ok!(Crowdfund::refund_all(Origin::signed(7), 0, 3));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Refunded(0, 1))
+		);
+
+		// Every contributor has their funds back
+		assert_eq!(Balances::free_balance(1), 999);
+		assert_eq!(Balances::free_balance(2), 2000);
+		assert_eq!(Balances::free_balance(3), 3000);
+		assert_eq!(Balances::free_balance(4), 4000);
+		for who in 1..=4 {
+			assert_eq!(Crowdfund::contribution_get(0, &who), 0);
+		}
+		let fund = Crowdfund::funds(0).unwrap();
+		assert_eq!(fund.raised, 0);
+		assert_eq!(fund.contributors, 0);
+		// Only the deposit is left in the fund
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 1);
+	});
+}
+
+#[test]
+fn refund_all_skips_entries_it_cannot_refund() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		// An entry whose key is not an account sorts before every contributor
+		let id = Crowdfund::id_from_index(0);
+		child::put(&id, &[0u8], &(50u64, 0u64));
+
+		run_to_block(10);
+		let post = Crowdfund::refund_all(Origin::signed(7), 0, 10).unwrap();
+		assert_eq!(post.actual_weight, Some(30_000));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Refunded(0, 2))
+		);
+		assert_eq!(Balances::free_balance(2), 2000);
+		assert_eq!(Balances::free_balance(3), 3000);
+		// The entry is left alone, and nothing is taken off `raised` for it
+		assert_eq!(child::get::<(u64, u64)>(&id, &[0u8]), Some((50, 0)));
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 0);
+	});
+}
+
+#[test]
+fn refund_all_refunds_no_one_if_a_refund_fails() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		// The fund account only holds enough to refund the first contributor
+		Balances::make_free_balance_be(&Crowdfund::fund_account_id(0), 250);
+
+		run_to_block(10);
+		assert_noop!(
+			Crowdfund::refund_all(Origin::signed(7), 0, 10),
+			BalancesError::<TestRuntime, ()>::InsufficientBalance
+		);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 200);
+		assert_eq!(Balances::free_balance(2), 1800);
+	});
+}
+
+#[test]
+fn calls_report_actual_weight() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// A contribution is charged for the fund's actual contributors, not the worst case
+		let declared = simple_crowdfund::Call::<TestRuntime>::contribute(0, 100)
+			.get_dispatch_info()
+			.weight;
+		let post = Crowdfund::contribute(Origin::signed(2), 0, 100).unwrap();
+		assert_eq!(post.actual_weight, Some(<() as WeightInfo>::contribute(1)));
+		assert!(post.actual_weight.unwrap() < declared);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 100));
+
+		// A withdrawal is charged for the funds the contributor actually backs
+		run_to_block(10);
+		let declared = simple_crowdfund::Call::<TestRuntime>::withdraw(0)
+			.get_dispatch_info()
+			.weight;
+		let post = Crowdfund::withdraw(Origin::signed(4), 0).unwrap();
+		assert_eq!(post.actual_weight, Some(<() as WeightInfo>::withdraw(1)));
+		assert!(post.actual_weight.unwrap() < declared);
+
+		// A refund is charged only for the contributors it refunds
+		let declared = simple_crowdfund::Call::<TestRuntime>::refund_all(0, 10)
+			.get_dispatch_info()
+			.weight;
+		let post = Crowdfund::refund_all(Origin::signed(7), 0, 10).unwrap();
+		assert_eq!(post.actual_weight, Some(20_000));
+		assert!(post.actual_weight.unwrap() < declared);
+	});
+}
+
+#[test]
+fn refund_all_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		// Cannot refund a non-existent fund
+		assert_noop!(
+			Crowdfund::refund_all(Origin::signed(7), 0, 10),
+			Error::<TestRuntime>::InvalidIndex
+		);
+
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 1000));
+
+		// Cannot refund an active fund
+		assert_noop!(
+			Crowdfund::refund_all(Origin::signed(7), 0, 10),
+			Error::<TestRuntime>::FundStillActive
+		);
+
+		run_to_block(10);
+
+		// Cannot refund a successful fund
+		assert_noop!(
+			Crowdfund::refund_all(Origin::signed(7), 0, 10),
+			Error::<TestRuntime>::SuccessfulFund
+		);
+	});
+}
+
+#[test]
+fn backer_portfolio_works() {
+	new_test_ext().execute_with(|| {
+		// Set up three crowdfunds
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			5,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			5,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// Account 4 backs all three
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 1000));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 1, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 2, 50));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 2, 50));
+
+		// Fund 0 succeeds, fund 1 fails and fund 2 is still running
+		run_to_block(10);
+
+		assert_eq!(
+			Crowdfund::backer_portfolio(&4),
+			vec![
+				(0, 1000, FundState::Succeeded),
+				(1, 100, FundState::Failed),
+				(2, 100, FundState::Active),
+			]
+		);
+		assert_eq!(Crowdfund::backer_portfolio(&3), vec![]);
+	});
+}
+
+#[test]
+fn owned_funds_are_bounded() {
+	new_test_ext().execute_with(|| {
+		for _ in 0..4 {
+			assert_ok!(Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			));
+		}
+		assert_eq!(Crowdfund::owner_fund_count(1), 4);
+		assert_noop!(
+			Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			),
+			Error::<TestRuntime>::TooManyFunds
+		);
+		// Other owners are unaffected
+		assert_ok!(Crowdfund::create(
+			Origin::signed(2),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// Dissolving a fund frees up a slot
+		run_to_block(50);
+		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 0));
+		assert_eq!(Crowdfund::owner_fund_count(1), 3);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			50,
+			59,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Crowdfund::owner_fund_count(1), 4);
+	});
+}
+
+#[test]
+fn backed_funds_are_bounded() {
+	new_test_ext().execute_with(|| {
+		// Set up four crowdfunds
+		for _ in 0..4 {
+			assert_ok!(Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			));
+		}
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 1, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 2, 100));
+
+		// Account 4 is already backing the maximum number of funds
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(4), 3, 100),
+			Error::<TestRuntime>::TooManyBackedFunds
+		);
+		// But may still add to a fund it already backs
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 100));
+		assert_eq!(Crowdfund::backed_funds(4), vec![0, 1, 2]);
+	});
+}
+
+#[test]
+fn installments_are_collected_each_block() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::pledge_installments(
+			Origin::signed(2),
+			0,
+			300,
+			100
+		));
+		assert_eq!(
+			Crowdfund::installments(0, 2),
+			Some(InstallmentPledge {
+				remaining: 300,
+				per_block: 100,
+				delinquent: false,
+			})
+		);
+
+		// One installment is collected at the start of each block
+		run_to_block(2);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 100);
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 101);
+		assert_eq!(Balances::free_balance(2), 1900);
+		assert_eq!(Crowdfund::installments(0, 2).unwrap().remaining, 200);
+
+		run_to_block(4);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 300);
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 301);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 300);
+		// The pledge is removed once it is paid
+		assert_eq!(Crowdfund::installments(0, 2), None);
+
+		let paid = System::events()
+			.into_iter()
+			.filter(|record| {
+				record.event == Event::simple_crowdfund(crate::Event::InstallmentPaid(2, 0, 100))
+			})
+			.count();
+		assert_eq!(paid, 3);
+
+		// Nothing more is collected
+		run_to_block(6);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 300);
+	});
+}
+
+#[test]
+fn installment_pledge_becomes_delinquent() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(10000),
+			10,
+			0,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// Account 2 only has 2000 to pay the pledge with
+		assert_ok!(Crowdfund::pledge_installments(
+			Origin::signed(2),
+			0,
+			3000,
+			1000
+		));
+
+		run_to_block(4);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 2000);
+		assert_eq!(
+			Crowdfund::installments(0, 2),
+			Some(InstallmentPledge {
+				remaining: 1000,
+				per_block: 1000,
+				delinquent: true,
+			})
+		);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::InstallmentDelinquent(2, 0))
+		);
+
+		// Delinquent pledges are not collected, even once the pledger can pay
+		let _ = Balances::deposit_creating(&2, 1000);
+		run_to_block(6);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 2000);
+	});
+}
+
+#[test]
+fn installments_are_collected_from_a_batch_of_funds_each_block() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		for _ in 0..3 {
+			assert_ok!(Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				20,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			));
+		}
+		for index in 0..3 {
+			assert_ok!(Crowdfund::pledge_installments(
+				Origin::signed(2),
+				index,
+				300,
+				100
+			));
+		}
+
+		// Only `MaxInstallmentFundsPerBlock` funds are collected from in a block
+		run_to_block(2);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 100);
+		assert_eq!(Crowdfund::contribution_get(1, &2), 100);
+		assert_eq!(Crowdfund::contribution_get(2, &2), 0);
+
+		// The next block picks up where the last one stopped, wrapping around
+		run_to_block(3);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 200);
+		assert_eq!(Crowdfund::contribution_get(1, &2), 100);
+		assert_eq!(Crowdfund::contribution_get(2, &2), 100);
+
+		// Every pledge is eventually paid, and the counts go with them
+		run_to_block(6);
+		for index in 0..3 {
+			assert_eq!(Crowdfund::contribution_get(index, &2), 300);
+			assert_eq!(Crowdfund::installment_count(index), 0);
+		}
+	});
+}
+
+#[test]
+fn pledge_installments_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// Cannot pledge to a non-existent fund
+		assert_noop!(
+			Crowdfund::pledge_installments(Origin::signed(2), 1, 100, 10),
+			Error::<TestRuntime>::InvalidIndex
+		);
+		// Installments must meet the fund's minimum contribution
+		assert_noop!(
+			Crowdfund::pledge_installments(Origin::signed(2), 0, 100, 5),
+			Error::<TestRuntime>::InvalidInstallments
+		);
+		// Installments must divide the total
+		assert_noop!(
+			Crowdfund::pledge_installments(Origin::signed(2), 0, 100, 30),
+			Error::<TestRuntime>::InvalidInstallments
+		);
+		assert_noop!(
+			Crowdfund::pledge_installments(Origin::signed(2), 0, 0, 10),
+			Error::<TestRuntime>::InvalidInstallments
+		);
+		// Installments must be a multiple of `ContributionIncrement`, like any contribution
+		ContributionIncrement::set(20);
+		assert_noop!(
+			Crowdfund::pledge_installments(Origin::signed(2), 0, 90, 15),
+			Error::<TestRuntime>::NotAnIncrement
+		);
+		ContributionIncrement::set(0);
+		// A fund may only have `MaxInstallmentsPerFund` pledges, though they may be replaced
+		assert_ok!(Crowdfund::pledge_installments(
+			Origin::signed(2),
+			0,
+			100,
+			10
+		));
+		assert_ok!(Crowdfund::pledge_installments(
+			Origin::signed(3),
+			0,
+			100,
+			10
+		));
+		assert_noop!(
+			Crowdfund::pledge_installments(Origin::signed(4), 0, 100, 10),
+			Error::<TestRuntime>::TooManyInstallments
+		);
+		assert_ok!(Crowdfund::pledge_installments(
+			Origin::signed(2),
+			0,
+			200,
+			20
+		));
+		assert_eq!(Crowdfund::installment_count(0), 2);
+		// Cannot pledge once the fund has ended
+		System::set_block_number(10);
+		assert_noop!(
+			Crowdfund::pledge_installments(Origin::signed(2), 0, 100, 10),
+			Error::<TestRuntime>::ContributionPeriodOver
+		);
+	});
+}
+
+#[test]
+fn dissolve_works() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// Transfer fee is taken here
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+
+		// Skip all the way to the end
+		// Crowdfund is unsuccessful 100 + 200 + 300 < 1000
+		run_to_block(50);
+
+		// Check initiator's balance.
+		assert_eq!(Balances::free_balance(1), 899);
+		// Check current funds (contributions + deposit)
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 601);
+
+		// Any signed account may dissolve funds in this configuration
+		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 0));
+
+		// Fund account is emptied
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 0);
+		// The owner gets the deposit back, and the remaining funds go to the configured
+		// destination rather than the dissolver
+		assert_eq!(Balances::free_balance(1), 900);
+		assert_eq!(DissolvedFunds::get(), 600);
+		assert_eq!(Balances::free_balance(7), 0);
+
+		// Storage trie is removed
+		assert_eq!(Crowdfund::contribution_get(0, &0), 0);
+		// Fund storage is removed
+		assert_eq!(Crowdfund::funds(0), None);
+	});
+}
+
+#[test]
+fn deposit_scales_with_cap() {
+	new_test_ext().execute_with(|| {
+		DepositPerCapUnit::set(1);
+		assert_eq!(Crowdfund::deposit_for(100), 101);
+
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			100,
+			CapMode::Absolute(100),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(3),
+			2,
+			100,
+			CapMode::Absolute(500),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// A fund with five times the cap holds five times the per-unit deposit
+		assert_eq!(Crowdfund::funds(0).unwrap().deposit, 101);
+		assert_eq!(Balances::free_balance(1), 899);
+		assert_eq!(Crowdfund::funds(1).unwrap().deposit, 501);
+		assert_eq!(Balances::free_balance(3), 2499);
+
+		// Raising the cap tops up the deposit
+		assert_ok!(Crowdfund::increase_cap(Origin::signed(1), 0, 300));
+		assert_eq!(Crowdfund::funds(0).unwrap().deposit, 301);
+		assert_eq!(Balances::free_balance(1), 699);
+
+		// The owner must be able to pay for the whole cap
+		assert_noop!(
+			Crowdfund::create(
+				Origin::signed(1),
+				2,
+				100,
+				CapMode::Absolute(5000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			),
+			BalancesError::<TestRuntime, ()>::InsufficientBalance
+		);
+
+		DepositPerCapUnit::set(0);
+	});
+}
+
+#[test]
+fn dissolve_returns_stored_deposit() {
+	new_test_ext().execute_with(|| {
+		DepositPerCapUnit::set(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(3),
+			2,
+			100,
+			CapMode::Absolute(500),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Balances::free_balance(3), 2499);
+
+		// The deposit charged at creation is returned, even if the rate has since changed
+		DepositPerCapUnit::set(0);
+		run_to_block(50);
+		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 0));
+		assert_eq!(Balances::free_balance(3), 3000);
+		assert_eq!(DissolvedFunds::get(), 0);
+	});
+}
+
+#[test]
+fn dissolve_records_lost_funds() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		assert_eq!(Crowdfund::lost_funds(), 0);
+
+		// The fund fails, and the backer never withdraws before retirement
+		run_to_block(50);
+		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 0));
+
+		assert_eq!(Crowdfund::lost_funds(), 300);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Dissolved(0, 50, 7, 301))
+		);
+		assert!(System::events()
+			.iter()
+			.any(|record| record.event == Event::simple_crowdfund(crate::Event::FundsLost(0, 300))));
+	});
+}
+
+#[test]
+fn dissolve_reports_amount_paid_out() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+
+		// One backer withdraws from the failed fund before it is dissolved
+		run_to_block(10);
+		assert_ok!(Crowdfund::withdraw(Origin::signed(2), 0));
+		let fund = Crowdfund::funds(0).unwrap();
+		assert_eq!(fund.deposit + fund.raised, 301);
+
+		run_to_block(50);
+		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 0));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Dissolved(0, 50, 7, 301))
+		);
+	});
+}
+
+#[test]
+fn dissolve_sweeps_dust_from_fund_account() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		run_to_block(10);
+		assert_ok!(Crowdfund::withdraw(Origin::signed(3), 0));
+
+		// Dust the fund never accounted for ends up in its account
+		let account = Crowdfund::fund_account_id(0);
+		assert_ok!(Balances::transfer(Origin::signed(4), account, 7));
+		assert_eq!(Balances::free_balance(account), 8);
+
+		run_to_block(50);
+		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 0));
+
+		// The account is reaped, and the dust goes to the destination without counting as lost
+		assert_eq!(Balances::total_balance(&account), 0);
+		assert!(!System::account_exists(&account));
+		assert_eq!(Balances::free_balance(1), 1000);
+		assert_eq!(DissolvedFunds::get(), 7);
+		assert_eq!(Crowdfund::lost_funds(), 0);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Dissolved(0, 50, 7, 1))
+		);
+	});
+}
+
+#[test]
+fn dissolve_pays_keeper_reward_floor() {
+	// For funds of various sizes: the cap, what is left in the fund, and what the keeper, owner
+	// and destination get from it
+	for &(cap, left, keeper, owner, destination) in &[
+		(500, 0, 50, 451, 0),
+		(10, 0, 11, 0, 0),
+		(500, 30, 50, 481, 0),
+		(500, 300, 50, 501, 250),
+	] {
+		new_test_ext().execute_with(|| {
+			MinKeeperReward::set(50);
+			DepositPerCapUnit::set(1);
+			assert_ok!(Crowdfund::create(
+				Origin::signed(3),
+				2,
+				cap,
+				CapMode::Absolute(cap),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			));
+			if left > 0 {
+				assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, left));
+			}
+			// The fund fails, and the backer never withdraws before retirement
+			run_to_block(50);
+			let before = Balances::free_balance(3);
+			assert_ok!(Crowdfund::dissolve(Origin::signed(7), 0));
+
+			assert_eq!(Balances::free_balance(7), keeper);
+			assert_eq!(Balances::free_balance(3) - before, owner);
+			assert_eq!(DissolvedFunds::get(), destination);
+
+			MinKeeperReward::set(0);
+			DepositPerCapUnit::set(0);
+		});
+	}
+}
+
+#[test]
+fn slash_deposit_cancels_fund() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			500,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+
+		// Only the slash origin may slash
+		assert_noop!(
+			Crowdfund::slash_deposit(Origin::signed(1), 0),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(Crowdfund::slash_deposit(Origin::root(), 0));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::DepositSlashed(0, 1))
+		);
+		let fund = Crowdfund::funds(0).unwrap();
+		assert!(fund.cancelled);
+		assert_eq!(fund.deposit, 0);
+		assert_eq!(fund.end, 1);
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 500);
+		assert_noop!(
+			Crowdfund::slash_deposit(Origin::root(), 0),
+			Error::<TestRuntime>::AlreadyCancelled
+		);
+
+		// The fund takes no more contributions and counts as failed, though it met its goal
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(4), 0, 100),
+			Error::<TestRuntime>::ContributionPeriodOver
+		);
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::Failed));
+		assert_noop!(
+			Crowdfund::claim(Origin::signed(1), 0),
+			Error::<TestRuntime>::UnsuccessfulFund
+		);
+
+		// Backers recover their contributions straight away
+		assert_ok!(Crowdfund::withdraw(Origin::signed(2), 0));
+		assert_ok!(Crowdfund::withdraw(Origin::signed(3), 0));
+		assert_eq!(Balances::free_balance(2), 2000);
+		assert_eq!(Balances::free_balance(3), 3000);
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 0);
+	});
+}
+
+#[test]
+fn slash_deposit_requires_an_unclaimed_deposit() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::set_vesting(
+			Origin::signed(1),
+			0,
+			Some(VestingSchedule {
+				cliff: 20,
+				per_block: 100
+			})
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 1000));
+
+		// Once some of the proceeds are paid out, the deposit can no longer be slashed
+		run_to_block(21);
+		assert_ok!(Crowdfund::claim_vested(Origin::signed(1), 0));
+		assert_noop!(
+			Crowdfund::slash_deposit(Origin::root(), 0),
+			Error::<TestRuntime>::ProceedsAlreadyClaimed
+		);
+
+		// Nor can a deposit that is not there
+		simple_crowdfund::Funds::<TestRuntime>::mutate(0, |fund| {
+			let fund = fund.as_mut().unwrap();
+			fund.claimed = 0;
+			fund.deposit = 0;
+		});
+		assert_noop!(
+			Crowdfund::slash_deposit(Origin::root(), 0),
+			Error::<TestRuntime>::NoDepositToSlash
+		);
+	});
+}
+
+#[test]
+fn funds_set_their_own_retirement_period() {
+	new_test_ext().execute_with(|| {
+		// The period may not be below the configured minimum
+		assert_noop!(
+			Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				4,
+				FundCategory::Other,
+				Vec::new()
+			),
+			Error::<TestRuntime>::RetirementPeriodTooShort
+		);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			20,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Crowdfund::funds(0).unwrap().retirement_period, 20);
+
+		// The global minimum has passed, but not the fund's own period
+		run_to_block(14);
+		assert_noop!(
+			Crowdfund::dissolve(Origin::signed(7), 0),
+			Error::<TestRuntime>::FundNotRetired
+		);
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::Failed));
+		run_to_block(28);
+		assert_noop!(
+			Crowdfund::dissolve(Origin::signed(7), 0),
+			Error::<TestRuntime>::FundNotRetired
+		);
+
+		run_to_block(29);
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::Retiring));
+		assert_ok!(Crowdfund::dissolve(Origin::signed(7), 0));
+	});
+}
+
+#[test]
+fn dissolve_requires_configured_origin() {
+	new_test_ext().execute_with(|| {
+		let _ = RewardBalances::deposit_creating(&1, 1000);
+		let _ = RewardBalances::deposit_creating(&3, 1000);
+		assert_ok!(RewardCrowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(RewardCrowdfund::contribute(Origin::signed(3), 0, 100));
+		run_to_block(50);
+
+		// This instance only lets the curator dissolve funds
+		assert_noop!(
+			RewardCrowdfund::dissolve(Origin::signed(3), 0),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(RewardCrowdfund::dissolve(Origin::signed(1), 0));
+		assert_eq!(RewardCrowdfund::funds(0), None);
+		// The owner gets the deposit back, and the leftover contribution is burned
+		assert_eq!(RewardBalances::free_balance(1), 1000);
+		assert_eq!(
+			RewardBalances::free_balance(RewardCrowdfund::fund_account_id(0)),
+			0
+		);
+		assert_eq!(RewardBalances::total_issuance(), 1900);
+	});
+}
+
+#[test]
+fn dissolve_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// Transfer fee is taken here
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+
+		// Cannot dissolve an invalid fund index
+		assert_noop!(
+			Crowdfund::dissolve(Origin::signed(1), 1),
+			Error::<TestRuntime>::InvalidIndex
+		);
+		// Cannot dissolve an active fund
+		assert_noop!(
+			Crowdfund::dissolve(Origin::signed(1), 0),
+			Error::<TestRuntime>::CannotDissolveActiveFund
+		);
+
+		run_to_block(10);
+
+		// Cannot disolve an ended but not yet retired fund
+		assert_noop!(
+			Crowdfund::dissolve(Origin::signed(1), 0),
+			Error::<TestRuntime>::FundNotRetired
+		);
+	});
+}
+
+#[test]
+fn dissolve_rejects_removed_funds() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 1000));
+
+		run_to_block(50);
+		assert_ok!(Crowdfund::dispense(Origin::signed(1), 0));
+
+		// A dispensed fund cannot be dissolved
+		assert_noop!(
+			Crowdfund::dissolve(Origin::signed(1), 0),
+			Error::<TestRuntime>::AlreadyDissolved
+		);
+		// Nor can one that has never existed
+		assert_noop!(
+			Crowdfund::dissolve(Origin::signed(1), 1),
+			Error::<TestRuntime>::InvalidIndex
+		);
+	});
+}
+
+#[test]
+fn dispense_works() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			20,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// Transfer fee is taken here
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 400));
+
+		// Skip to the retirement period
+		// Crowdfund is successful 100 + 200 + 300 + 400  >= 1000
+		run_to_block(10);
+
+		// Check initiator's balance.
+		assert_eq!(Balances::free_balance(1), 899);
+		// Check current funds (contributions + deposit)
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 1001);
+
+		// Account 7 dispenses the crowdfund
+		assert_ok!(Crowdfund::dispense(Origin::signed(7), 0));
+
+		// Fund account is emptied
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 0);
+		// Beneficiary account is funded
+		assert_eq!(Balances::free_balance(20), 1000);
+		// Dispensor account is rewarded deposit
+		assert_eq!(Balances::free_balance(7), 1);
+
+		// Storage trie is removed
+		assert_eq!(Crowdfund::contribution_get(0, &0), 0);
+		// Fund storage is removed
+		assert_eq!(Crowdfund::funds(0), None);
+	});
+}
+
+#[test]
+fn claim_works() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			20,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 400));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 600));
+
+		// Only the owner may claim, and only once the fund has ended
+		assert_noop!(
+			Crowdfund::claim(Origin::signed(1), 0),
+			Error::<TestRuntime>::FundStillActive
+		);
+		run_to_block(10);
+		assert_noop!(
+			Crowdfund::claim(Origin::signed(20), 0),
+			Error::<TestRuntime>::NotOwner
+		);
+
+		// The owner collects the proceeds and the deposit
+		assert_ok!(Crowdfund::claim(Origin::signed(1), 0));
+		assert_eq!(Balances::free_balance(1), 2000);
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 0);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Claimed(0, 1000, 1))
+		);
+		assert_eq!(Crowdfund::funds(0), None);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 0);
+
+		// The fund is gone, so it cannot be claimed again
+		assert_noop!(
+			Crowdfund::claim(Origin::signed(1), 0),
+			Error::<TestRuntime>::InvalidIndex
+		);
+	});
+}
+
+#[test]
+fn claim_rejects_failed_fund() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			20,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 999));
+
+		run_to_block(10);
+		assert_noop!(
+			Crowdfund::claim(Origin::signed(1), 0),
+			Error::<TestRuntime>::UnsuccessfulFund
+		);
+		// Backers can still get their money back
+		assert_ok!(Crowdfund::withdraw(Origin::signed(2), 0));
+	});
+}
+
+#[test]
+fn dispense_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// Transfer fee is taken here
+		assert_ok!(Crowdfund::contribute(Origin::signed(1), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+
+		// Cannot dispense an invalid fund index
+		assert_noop!(
+			Crowdfund::dispense(Origin::signed(1), 1),
+			Error::<TestRuntime>::InvalidIndex
+		);
+		// Cannot dispense an active fund
+		assert_noop!(
+			Crowdfund::dispense(Origin::signed(1), 0),
+			Error::<TestRuntime>::FundStillActive
+		);
+
+		// Skip to the retirement period
+		// Crowdfund is unsuccessful 100 + 200 + 300 < 1000
+		run_to_block(10);
+
+		// Cannot disopens an ended but unsuccessful fund
+		assert_noop!(
+			Crowdfund::dispense(Origin::signed(1), 0),
+			Error::<TestRuntime>::UnsuccessfulFund
+		);
+	});
+}
+
+#[test]
+fn genesis_funds_work() {
+	new_test_ext_with_funds(
+		vec![(1, 2, 1000, 2000, 0, 9), (3, 4, 500, 500, 0, 20)],
+		Vec::new(),
+	)
+	.execute_with(|| {
+		assert_eq!(Crowdfund::fund_count(), 2);
+		assert_eq!(Crowdfund::funds(0).unwrap().owner, 1);
+		assert_eq!(Crowdfund::funds(1).unwrap().goal, 500);
+		// Owners paid the deposit
+		assert_eq!(Balances::free_balance(1), 999);
+		assert_eq!(Balances::free_balance(3), 2999);
+
+		// New funds are indexed after the genesis ones
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Crowdfund::fund_count(), 3);
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+	});
+}
+
+#[test]
+fn genesis_contributions_are_seeded() {
+	new_test_ext_with_funds(
+		vec![(1, 2, 1000, 2000, 0, 9), (3, 4, 500, 500, 0, 20)],
+		vec![(0, 10, 100), (0, 11, 250), (1, 10, 40), (0, 10, 50)],
+	)
+	.execute_with(|| {
+		assert_eq!(Crowdfund::contribution_get(0, &10), 150);
+		assert_eq!(Crowdfund::contribution_get(0, &11), 250);
+		assert_eq!(Crowdfund::contribution_get(1, &10), 40);
+		let fund = Crowdfund::funds(0).unwrap();
+		assert_eq!(fund.raised, 400);
+		assert_eq!(fund.contributors, 2);
+		assert_eq!(Crowdfund::funds(1).unwrap().raised, 40);
+		assert_eq!(Crowdfund::total_raised(), 440);
+		assert_eq!(Crowdfund::backed_funds(10), vec![0, 1]);
+
+		// Seeded contributions are topped up like any other
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 500);
+	});
+}
+
+#[test]
+fn register_intent_works() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			5,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// Contributions are not accepted before the start
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(2), 0, 100),
+			Error::<TestRuntime>::FundNotStarted
+		);
+
+		assert_ok!(Crowdfund::register_intent(Origin::signed(2), 0, 100));
+		assert_ok!(Crowdfund::register_intent(Origin::signed(3), 0, 300));
+		// A later intent replaces the earlier one
+		assert_ok!(Crowdfund::register_intent(Origin::signed(2), 0, 150));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::IntentRegistered(2, 0, 150))
+		);
+
+		// Intents are readable but move no funds
+		assert_eq!(Crowdfund::intent_get(0, &2), 150);
+		assert_eq!(Crowdfund::intent_get(0, &3), 300);
+		assert_eq!(Crowdfund::intent_get(0, &4), 0);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 0);
+		assert_eq!(Balances::free_balance(2), 2000);
+
+		// Once started, intents are closed and contributions open
+		run_to_block(5);
+		assert_noop!(
+			Crowdfund::register_intent(Origin::signed(4), 0, 100),
+			Error::<TestRuntime>::FundAlreadyStarted
+		);
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 100);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 100);
+		assert_eq!(Crowdfund::intent_get(0, &2), 150);
+	});
+}
+
+#[test]
+fn register_intent_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Crowdfund::register_intent(Origin::signed(2), 0, 100),
+			Error::<TestRuntime>::InvalidIndex
+		);
+		// A fund must end after it starts
+		assert_noop!(
+			Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				9,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			),
+			Error::<TestRuntime>::EndTooEarly
+		);
+	});
+}
+
+#[test]
+fn timed_retirement_works() {
+	new_test_ext().execute_with(|| {
+		RetirementDuration::set(Some(1_000));
+		run_to_block(1);
+		Timestamp::set_timestamp(6_000);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+
+		// Cannot start retiring while the fund is running
+		assert_noop!(
+			Crowdfund::retire(Origin::signed(3), 0),
+			Error::<TestRuntime>::FundStillActive
+		);
+
+		// The moment the fund ends is recorded as it ends, without anyone calling `retire`
+		run_to_block(8);
+		Timestamp::set_timestamp(54_000);
+		run_to_block(9);
+		assert_eq!(Crowdfund::retire_after(0), Some(55_000));
+
+		// Past `RetirementDuration`, but not the fund's own retirement period
+		Timestamp::set_timestamp(60_000);
+		run_to_block(13);
+		assert_noop!(
+			Crowdfund::dissolve(Origin::signed(3), 0),
+			Error::<TestRuntime>::FundNotRetired
+		);
+		assert_noop!(
+			Crowdfund::retire(Origin::signed(3), 0),
+			Error::<TestRuntime>::FundNotRetired
+		);
+
+		// Many blocks later, the fund is not retiring if the clock has not moved far enough
+		Timestamp::set_timestamp(54_999);
+		run_to_block(100);
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::Failed));
+		assert_noop!(
+			Crowdfund::dissolve(Origin::signed(3), 0),
+			Error::<TestRuntime>::FundNotRetired
+		);
+
+		// Once it has, anyone may report that the fund is retiring
+		Timestamp::set_timestamp(55_000);
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::Retiring));
+		assert_ok!(Crowdfund::retire(Origin::signed(3), 0));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Retiring(0, 100))
+		);
+		assert_noop!(
+			Crowdfund::retire(Origin::signed(3), 0),
+			Error::<TestRuntime>::AlreadyRetiring
+		);
+
+		assert_ok!(Crowdfund::dissolve(Origin::signed(3), 0));
+		assert_eq!(Crowdfund::retire_after(0), None);
+		RetirementDuration::set(None);
+	});
+}
+
+#[test]
+fn retire_records_missing_retire_after() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		run_to_block(12);
+		assert_eq!(Crowdfund::retire_after(0), None);
+
+		// Retirement became timed after the fund ended, so its end was never recorded
+		RetirementDuration::set(Some(1_000));
+		Timestamp::set_timestamp(30_000);
+		let result = Crowdfund::retire(Origin::signed(3), 0);
+		assert!(matches!(result, Err(e) if e.error == Error::<TestRuntime>::FundNotRetired.into()));
+		assert_eq!(Crowdfund::retire_after(0), Some(31_000));
+
+		run_to_block(20);
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::Failed));
+		Timestamp::set_timestamp(31_000);
+		assert_ok!(Crowdfund::retire(Origin::signed(3), 0));
+		assert_eq!(Crowdfund::status(0), Some(FundPhase::Retiring));
+
+		RetirementDuration::set(None);
+	});
+}
+
+#[test]
+fn retire_requires_timed_retirement() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		run_to_block(10);
+		assert_noop!(
+			Crowdfund::retire(Origin::signed(3), 0),
+			Error::<TestRuntime>::RetirementNotTimed
+		);
+	});
+}
+
+#[test]
+fn withdraw_is_rate_limited_per_block() {
+	new_test_ext().execute_with(|| {
+		MaxWithdrawPerBlock::set(Some(250));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 300));
+
+		run_to_block(50);
+
+		// Withdrawals up to the cap go through
+		assert_ok!(Crowdfund::withdraw(Origin::signed(2), 0));
+		assert_ok!(Crowdfund::withdraw_partial(Origin::signed(3), 0, 150));
+		assert_eq!(Crowdfund::withdrawn_this_block(0), 250);
+
+		// Anything beyond it must wait for the next block
+		assert_noop!(
+			Crowdfund::withdraw_partial(Origin::signed(3), 0, 50),
+			Error::<TestRuntime>::WithdrawRateLimited
+		);
+		assert_noop!(
+			Crowdfund::withdraw(Origin::signed(4), 0),
+			Error::<TestRuntime>::WithdrawRateLimited
+		);
+
+		run_to_block(51);
+		assert_eq!(Crowdfund::withdrawn_this_block(0), 0);
+		assert_ok!(Crowdfund::withdraw_partial(Origin::signed(3), 0, 50));
+		assert_noop!(
+			Crowdfund::withdraw(Origin::signed(4), 0),
+			Error::<TestRuntime>::WithdrawRateLimited
+		);
+
+		// Refunds in bulk stop at the cap too
+		run_to_block(52);
+		assert_ok!(Crowdfund::refund_all(Origin::signed(7), 0, 10));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Refunded(0, 0))
+		);
+		MaxWithdrawPerBlock::set(Some(300));
+		assert_ok!(Crowdfund::refund_all(Origin::signed(7), 0, 10));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Refunded(0, 1))
+		);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 0);
+		MaxWithdrawPerBlock::set(None);
+	});
+}
+
+#[test]
+fn create_and_contribute_works() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create_and_contribute(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new(),
+			100
+		));
+		assert_eq!(Crowdfund::fund_count(), 1);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Contributed(
+				1,
+				0,
+				100,
+				0,
+				0,
+				100,
+				Perbill::from_percent(5),
+				1
+			))
+		);
+
+		let fund = Crowdfund::funds(0).unwrap();
+		assert_eq!(fund.owner, 1);
+		assert_eq!(fund.raised, 100);
+		assert_eq!(fund.contributors, 1);
+		assert_eq!(Crowdfund::contribution_get(0, &1), 100);
+		// Deposit and contribution both came from the owner
+		assert_eq!(Balances::free_balance(1), 899);
+
+		// A fund that has not started records the value as an intent instead
+		assert_ok!(Crowdfund::create_and_contribute(
+			Origin::signed(3),
+			4,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			5,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new(),
+			300
+		));
+		assert_eq!(Crowdfund::funds(1).unwrap().raised, 0);
+		assert_eq!(Crowdfund::intent_get(1, &3), 300);
+		assert_eq!(Balances::free_balance(3), 2999);
+	});
+}
+
+#[test]
+fn create_and_contribute_rolls_back_on_failure() {
+	new_test_ext().execute_with(|| {
+		// The contribution is too small, so the fund is not created either
+		assert_noop!(
+			Crowdfund::create_and_contribute(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new(),
+				5
+			),
+			Error::<TestRuntime>::ContributionTooSmall
+		);
+		// The contribution would exceed the cap
+		assert_noop!(
+			Crowdfund::create_and_contribute(
+				Origin::signed(1),
+				2,
+				100,
+				CapMode::Absolute(500),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new(),
+				600
+			),
+			Error::<TestRuntime>::CapExceeded
+		);
+		// The owner can pay the deposit but not the contribution
+		assert_noop!(
+			Crowdfund::create_and_contribute(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new(),
+				1000
+			),
+			BalancesError::<TestRuntime, ()>::InsufficientBalance
+		);
+		assert_eq!(Crowdfund::fund_count(), 0);
+		assert_eq!(Balances::free_balance(1), 1000);
+	});
+}
+
+#[test]
+fn claim_vested_releases_linearly_after_cliff() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::set_vesting(
+			Origin::signed(1),
+			0,
+			Some(VestingSchedule {
+				cliff: 20,
+				per_block: 100
+			})
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 1000));
+
+		// Nothing has vested before the cliff
+		run_to_block(20);
+		assert_noop!(
+			Crowdfund::claim_vested(Origin::signed(1), 0),
+			Error::<TestRuntime>::NothingToClaim
+		);
+
+		// The claimable amount grows by `per_block` each block
+		run_to_block(23);
+		assert_ok!(Crowdfund::claim_vested(Origin::signed(1), 0));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::VestedClaimed(0, 300, 23))
+		);
+		assert_eq!(Balances::free_balance(2), 2300);
+
+		run_to_block(25);
+		assert_ok!(Crowdfund::claim_vested(Origin::signed(1), 0));
+		assert_eq!(Balances::free_balance(2), 2500);
+		assert_eq!(Crowdfund::funds(0).unwrap().claimed, 500);
+
+		// The fund cannot be dispensed until it has fully vested
+		assert_noop!(
+			Crowdfund::dispense(Origin::signed(7), 0),
+			Error::<TestRuntime>::VestingIncomplete
+		);
+
+		// Vesting stops at `raised`
+		run_to_block(100);
+		assert_ok!(Crowdfund::claim_vested(Origin::signed(1), 0));
+		assert_eq!(Balances::free_balance(2), 3000);
+		assert_noop!(
+			Crowdfund::claim_vested(Origin::signed(1), 0),
+			Error::<TestRuntime>::NothingToClaim
+		);
+
+		// Dispensing now only returns the deposit
+		assert_ok!(Crowdfund::dispense(Origin::signed(7), 0));
+		assert_eq!(Balances::free_balance(2), 3000);
+		assert_eq!(Balances::free_balance(7), 1);
+	});
+}
+
+#[test]
+fn claim_vested_without_schedule_claims_everything() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 1000));
+
+		assert_noop!(
+			Crowdfund::claim_vested(Origin::signed(1), 0),
+			Error::<TestRuntime>::FundStillActive
+		);
+
+		run_to_block(10);
+		assert_noop!(
+			Crowdfund::claim_vested(Origin::signed(2), 0),
+			Error::<TestRuntime>::NotOwner
+		);
+		assert_ok!(Crowdfund::claim_vested(Origin::signed(1), 0));
+		assert_eq!(Balances::free_balance(2), 3000);
+
+		// Dispensing afterwards does not pay the beneficiary twice
+		assert_ok!(Crowdfund::dispense(Origin::signed(7), 0));
+		assert_eq!(Balances::free_balance(2), 3000);
+	});
+}
+
+#[test]
+fn set_vesting_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		let schedule = Some(VestingSchedule {
+			cliff: 20,
+			per_block: 100,
+		});
+		assert_noop!(
+			Crowdfund::set_vesting(Origin::signed(1), 0, schedule.clone()),
+			Error::<TestRuntime>::InvalidIndex
+		);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_noop!(
+			Crowdfund::set_vesting(Origin::signed(2), 0, schedule.clone()),
+			Error::<TestRuntime>::NotOwner
+		);
+
+		// Terms are fixed once the fund is backed
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 100));
+		assert_noop!(
+			Crowdfund::set_vesting(Origin::signed(1), 0, schedule),
+			Error::<TestRuntime>::FundAlreadyBacked
+		);
+	});
+}
+
+#[test]
+fn contribution_per_account_is_capped() {
+	new_test_ext().execute_with(|| {
+		MaxContributionPerAccount::set(500);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// Below, then exactly at the cap
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 200));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 300));
+		assert_eq!(Crowdfund::contribution_get(0, &2), 500);
+
+		// Anything above it is rejected, however it is split up
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(2), 0, 10),
+			Error::<TestRuntime>::ContributionCapExceeded
+		);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 400));
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(3), 0, 101),
+			Error::<TestRuntime>::ContributionCapExceeded
+		);
+
+		// Zero means no cap
+		MaxContributionPerAccount::set(0);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 1000));
+		assert_eq!(Crowdfund::contribution_get(0, &3), 1400);
+	});
+}
+
+#[test]
+fn contributions_per_block_are_limited() {
+	new_test_ext().execute_with(|| {
+		MaxContributionsPerBlock::set(2);
+		run_to_block(1);
+		for _ in 0..2 {
+			assert_ok!(Crowdfund::create(
+				Origin::signed(1),
+				2,
+				1000,
+				CapMode::Absolute(2000),
+				10,
+				0,
+				9,
+				5,
+				FundCategory::Other,
+				Vec::new()
+			));
+		}
+
+		// Up to the limit in one block, across funds
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 1, 100));
+		assert_eq!(Crowdfund::contributions_this_block(2), 2);
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(2), 0, 100),
+			Error::<TestRuntime>::TooManyContributionsThisBlock
+		);
+		// Other accounts are unaffected
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 100));
+
+		// The count starts again in the next block, and clearing it is paid for per account
+		let entry = <TestRuntime as frame_system::Config>::DbWeight::get().reads_writes(1, 1);
+		Crowdfund::on_initialize(1);
+		assert_eq!(Crowdfund::contributions_this_block(2), 0);
+		let idle = Crowdfund::on_initialize(1);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 1, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 1, 100));
+		assert_eq!(Crowdfund::on_initialize(1), idle + 2 * entry);
+		run_to_block(2);
+		assert_eq!(Crowdfund::contributions_this_block(2), 0);
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+		assert_eq!(Crowdfund::contribution_get(0, &2), 200);
+
+		MaxContributionsPerBlock::set(0);
+	});
+}
+
+#[test]
+fn release_milestones_in_order() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			5,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::set_milestones(
+			Origin::signed(1),
+			0,
+			vec![
+				(20, Perbill::from_percent(30)),
+				(30, Perbill::from_percent(50))
+			]
+		));
+		run_to_block(5);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 1000));
+
+		// The first milestone is due as soon as the fund ends, the second is not
+		run_to_block(20);
+		assert_noop!(
+			Crowdfund::release_milestone(Origin::signed(1), 0, 1),
+			Error::<TestRuntime>::MilestoneNotReached
+		);
+		assert_ok!(Crowdfund::release_milestone(Origin::signed(1), 0, 0));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::MilestoneReleased(0, 0, 300))
+		);
+		assert_eq!(Balances::free_balance(2), 2300);
+		assert_noop!(
+			Crowdfund::release_milestone(Origin::signed(1), 0, 0),
+			Error::<TestRuntime>::MilestoneAlreadyReleased
+		);
+
+		// The remainder waits for the outstanding milestone
+		assert_noop!(
+			Crowdfund::dispense(Origin::signed(7), 0),
+			Error::<TestRuntime>::MilestonesPending
+		);
+		assert_noop!(
+			Crowdfund::claim_vested(Origin::signed(1), 0),
+			Error::<TestRuntime>::MilestonesPending
+		);
+
+		run_to_block(30);
+		assert_ok!(Crowdfund::release_milestone(Origin::signed(1), 0, 1));
+		assert_eq!(Balances::free_balance(2), 2800);
+		assert_eq!(Crowdfund::funds(0).unwrap().claimed, 800);
+		assert_noop!(
+			Crowdfund::release_milestone(Origin::signed(1), 0, 1),
+			Error::<TestRuntime>::MilestoneAlreadyReleased
+		);
+
+		// Whatever the milestones leave over is dispensed as usual
+		assert_ok!(Crowdfund::dispense(Origin::signed(7), 0));
+		assert_eq!(Balances::free_balance(2), 3000);
+		assert!(Crowdfund::milestones(0).is_empty());
+		assert!(Crowdfund::released_milestones(0).is_empty());
+	});
+}
+
+#[test]
+fn milestones_handle_basic_errors() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			5,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		let half = Perbill::from_percent(50);
+
+		// Only the owner may set milestones
+		assert_noop!(
+			Crowdfund::set_milestones(Origin::signed(2), 0, vec![(20, half)]),
+			Error::<TestRuntime>::NotOwner
+		);
+		// The milestones may not release more than was raised
+		assert_noop!(
+			Crowdfund::set_milestones(
+				Origin::signed(1),
+				0,
+				vec![(20, half), (25, Perbill::from_percent(51))]
+			),
+			Error::<TestRuntime>::MilestonesExceedRaised
+		);
+		// Nor may there be too many of them
+		assert_noop!(
+			Crowdfund::set_milestones(Origin::signed(1), 0, vec![(20, Perbill::zero()); 4]),
+			Error::<TestRuntime>::TooManyMilestones
+		);
+		assert_ok!(Crowdfund::set_milestones(
+			Origin::signed(1),
+			0,
+			vec![(20, half), (25, half)]
+		));
+
+		// Milestones are fixed once the fund starts
+		run_to_block(5);
+		assert_noop!(
+			Crowdfund::set_milestones(Origin::signed(1), 0, Vec::new()),
+			Error::<TestRuntime>::FundAlreadyStarted
+		);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 1000));
+
+		// Milestones are only released from a successful fund that has ended
+		assert_noop!(
+			Crowdfund::release_milestone(Origin::signed(1), 0, 0),
+			Error::<TestRuntime>::FundStillActive
+		);
+		run_to_block(20);
+		assert_noop!(
+			Crowdfund::release_milestone(Origin::signed(2), 0, 0),
+			Error::<TestRuntime>::NotOwner
+		);
+		assert_noop!(
+			Crowdfund::release_milestone(Origin::signed(1), 0, 2),
+			Error::<TestRuntime>::InvalidMilestone
+		);
+	});
+}
+
+#[test]
+fn fund_without_whitelist_is_open() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			5,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// An empty whitelist leaves the fund open
+		assert_ok!(Crowdfund::set_whitelist(Origin::signed(1), 0, Vec::new()));
+		run_to_block(5);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 100));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 100));
+	});
+}
+
+#[test]
+fn whitelist_restricts_contributors() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			5,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::set_whitelist(Origin::signed(1), 0, vec![3]));
+		// Setting the whitelist again replaces it, charging only for the account removed
+		let post = Crowdfund::set_whitelist(Origin::signed(1), 0, vec![2, 4]).unwrap();
+		assert_eq!(
+			post.actual_weight,
+			Some(RocksDbWeight::get().reads_writes(1, 3) + 10_000)
+		);
+		run_to_block(5);
+
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 100));
+		assert_eq!(Crowdfund::contribution_get(0, &4), 100);
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(3), 0, 100),
+			Error::<TestRuntime>::NotWhitelisted
+		);
+	});
+}
+
+#[test]
+fn set_whitelist_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			5,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_noop!(
+			Crowdfund::set_whitelist(Origin::signed(1), 1, vec![3]),
+			Error::<TestRuntime>::InvalidIndex
+		);
+		assert_noop!(
+			Crowdfund::set_whitelist(Origin::signed(2), 0, vec![3]),
+			Error::<TestRuntime>::NotOwner
+		);
+		assert_noop!(
+			Crowdfund::set_whitelist(Origin::signed(1), 0, vec![2, 3, 4]),
+			Error::<TestRuntime>::WhitelistTooLong
+		);
+		run_to_block(5);
+		assert_noop!(
+			Crowdfund::set_whitelist(Origin::signed(1), 0, vec![3]),
+			Error::<TestRuntime>::FundAlreadyStarted
+		);
+	});
+}
+
+#[test]
+fn bonus_tiers_credit_early_contributions() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			5,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::set_bonus_tiers(
+			Origin::signed(1),
+			0,
+			vec![
+				(10, Perbill::from_percent(10)),
+				(7, Perbill::from_percent(20))
+			]
+		));
+
+		// Each contribution earns the bonus of the tier it falls in
+		run_to_block(5);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 100));
+		run_to_block(8);
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 100));
+		run_to_block(11);
+		assert_ok!(Crowdfund::contribute(Origin::signed(2), 0, 100));
+
+		assert_eq!(Crowdfund::contribution_get(0, &3), 120);
+		assert_eq!(Crowdfund::contribution_get(0, &4), 110);
+		assert_eq!(Crowdfund::contribution_get(0, &2), 100);
+		assert_eq!(Crowdfund::bonus(0, 3), 20);
+		// Only what was paid in counts toward the fund
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 300);
+
+		// The fund fails, and only what was paid in is refunded
+		run_to_block(21);
+		assert_ok!(Crowdfund::withdraw(Origin::signed(3), 0));
+		assert_eq!(Balances::free_balance(3), 3000);
+		assert_eq!(Crowdfund::bonus(0, 3), 0);
+		assert_noop!(
+			Crowdfund::withdraw_partial(Origin::signed(4), 0, 101),
+			Error::<TestRuntime>::InsufficientContribution
+		);
+		assert_ok!(Crowdfund::withdraw_partial(Origin::signed(4), 0, 40));
+		assert_eq!(Crowdfund::contribution_get(0, &4), 70);
+		assert_ok!(Crowdfund::refund_all(Origin::signed(7), 0, 5));
+		assert_eq!(Balances::free_balance(4), 4000);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 0);
+	});
+}
+
+#[test]
+fn set_bonus_tiers_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			5,
+			20,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		let tier = (10, Perbill::from_percent(10));
+		assert_noop!(
+			Crowdfund::set_bonus_tiers(Origin::signed(2), 0, vec![tier]),
+			Error::<TestRuntime>::NotOwner
+		);
+		assert_noop!(
+			Crowdfund::set_bonus_tiers(Origin::signed(1), 0, vec![tier; 3]),
+			Error::<TestRuntime>::TooManyBonusTiers
+		);
+		run_to_block(5);
+		assert_noop!(
+			Crowdfund::set_bonus_tiers(Origin::signed(1), 0, vec![tier]),
+			Error::<TestRuntime>::FundAlreadyStarted
+		);
+	});
+}
+
+#[test]
+fn instances_are_independent() {
+	new_test_ext().execute_with(|| {
+		let _ = RewardBalances::deposit_creating(&1, 1000);
+		let _ = RewardBalances::deposit_creating(&3, 1000);
+
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(RewardCrowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_eq!(Crowdfund::fund_count(), 2);
+		assert_eq!(RewardCrowdfund::fund_count(), 1);
+
+		// Funds with the same index belong to different accounts and child tries
+		assert_ne!(
+			Crowdfund::fund_account_id(0),
+			RewardCrowdfund::fund_account_id(0)
+		);
+		assert_ne!(
+			Crowdfund::id_from_index(0),
+			RewardCrowdfund::id_from_index(0)
+		);
+
+		// Each instance takes contributions in its own currency
+		assert_ok!(RewardCrowdfund::contribute(Origin::signed(3), 0, 100));
+		assert_eq!(RewardBalances::free_balance(3), 900);
+		assert_eq!(Balances::free_balance(3), 3000);
+		assert_eq!(RewardCrowdfund::contribution_get(0, &3), 100);
+		assert_eq!(Crowdfund::contribution_get(0, &3), 0);
+		assert_eq!(
+			RewardBalances::free_balance(RewardCrowdfund::fund_account_id(0)),
+			101
+		);
+	});
+}
+
+#[test]
+fn runtime_upgrade_migrates_old_funds() {
+	new_test_ext().execute_with(|| {
+		// A fund stored in the layout used before storage was versioned, with one contribution
+		// in the child trie that layout used
+		let old = crate::migrations::OldFundInfo::<u64, u64, u64> {
+			beneficiary: 2,
+			deposit: 1,
+			raised: 100,
+			end: 9,
+			goal: 1000,
+		};
+		sp_io::storage::set(
+			&simple_crowdfund::Funds::<TestRuntime>::hashed_key_for(0),
+			&old.encode(),
+		);
+		let old_id = child::ChildInfo::new_default(
+			BlakeTwo256::hash(&[&b"crowdfnd"[..], &0u32.to_le_bytes()[..]].concat()).as_ref(),
+		);
+		3u64.using_encoded(|b| child::put(&old_id, b, &100u64));
+		assert_eq!(Crowdfund::pallet_version(), StorageVersion::V1);
+
+		Crowdfund::on_runtime_upgrade();
+
+		assert_eq!(Crowdfund::pallet_version(), StorageVersion::V3);
+		assert_eq!(
+			Crowdfund::funds(0),
+			Some(FundInfo {
+				owner: 2,
+				beneficiary: 2,
+				deposit: 1,
+				raised: 100,
+				contributors: 1,
+				start: 0,
+				end: 9,
+				retirement_period: 5,
+				goal: 1000,
+				cap: u64::MAX,
+				min_contribution: 10,
+				sponsor: None,
+				matched: 0,
+				vesting: None,
+				claimed: 0,
+				category: FundCategory::Other,
+				metadata: Vec::new(),
+				paused: false,
+				cancelled: false,
+			})
+		);
+		assert_eq!(Crowdfund::contribution_get(0, &3), 100);
+		assert_eq!(3u64.using_encoded(|b| child::get::<u64>(&old_id, b)), None);
+		assert_eq!(Crowdfund::backed_funds(3), vec![0]);
+		assert_eq!(Crowdfund::total_raised(), 100);
+		assert_eq!(Crowdfund::owner_fund_count(2), 1);
+		assert_eq!(Crowdfund::funds_of(&2), vec![0]);
+		// The phase of the fund is checked from the next block on
+		assert_eq!(Crowdfund::status_schedule(1), vec![0]);
+		assert_eq!(Crowdfund::status_schedule(9), vec![0]);
+		assert_eq!(Crowdfund::status_schedule(14), vec![0]);
+
+		// The migration only runs once
+		Crowdfund::on_runtime_upgrade();
+		assert_eq!(Crowdfund::backed_funds(3), vec![0]);
+		assert_eq!(Crowdfund::total_raised(), 100);
+		assert_eq!(Crowdfund::owner_fund_count(2), 1);
+	});
+}
+
+#[test]
+fn runtime_upgrade_moves_contributions_to_named_tries() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		// A contribution stored in the child trie used before trie ids included the pallet name
+		let v2_id = child::ChildInfo::new_default(
+			BlakeTwo256::hash(
+				&[&b"ex/cfund"[..], &b"crowdfnd"[..], &0u32.to_le_bytes()[..]].concat(),
+			)
+			.as_ref(),
+		);
+		3u64.using_encoded(|b| child::put(&v2_id, b, &100u64));
+		simple_crowdfund::PalletVersion::<TestRuntime>::put(StorageVersion::V2);
+
+		simple_crowdfund::StatusSchedule::<TestRuntime>::remove_all();
+
+		Crowdfund::on_runtime_upgrade();
+
+		assert_eq!(Crowdfund::pallet_version(), StorageVersion::V3);
+		assert_eq!(Crowdfund::contribution_get(0, &3), 100);
+		assert_eq!(3u64.using_encoded(|b| child::get::<u64>(&v2_id, b)), None);
+		assert_eq!(Crowdfund::status_schedule(9), vec![0]);
+	});
+}
+
+#[test]
+fn id_from_index_is_unique_and_stable() {
+	new_test_ext().execute_with(|| {
+		// The same index always gives the same trie
+		assert_eq!(Crowdfund::id_from_index(0), Crowdfund::id_from_index(0));
+		assert_eq!(Crowdfund::id_from_index(7), Crowdfund::id_from_index(7));
+		// Different indices give different tries
+		assert_ne!(Crowdfund::id_from_index(0), Crowdfund::id_from_index(1));
+		assert_ne!(Crowdfund::id_from_index(1), Crowdfund::id_from_index(256));
+		// As do different instances of the pallet
+		assert_ne!(
+			Crowdfund::id_from_index(0),
+			RewardCrowdfund::id_from_index(0)
+		);
+	});
+}
+
+#[test]
+fn contribute_respects_fund_minimum() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			50,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+
+		// Contributions below the fund's own minimum are rejected, even above the global one
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(3), 0, 49),
+			Error::<TestRuntime>::ContributionTooSmall
+		);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 50));
+		assert_eq!(Crowdfund::contribution_get(0, &3), 50);
+
+		// A fund at the global minimum still rejects anything below it
+		assert_noop!(
+			Crowdfund::contribute(Origin::signed(3), 1, 9),
+			Error::<TestRuntime>::ContributionTooSmall
+		);
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 1, 10));
+	});
+}
+
+#[test]
+fn reserved_contributions_are_unreserved_on_withdraw() {
+	new_test_ext().execute_with(|| {
+		ReserveContributions::set(true);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 200));
+
+		// Contributions stay in the backers' accounts, and only the deposit is in the pot
+		assert_eq!(Balances::reserved_balance(3), 300);
+		assert_eq!(Balances::free_balance(3), 2700);
+		assert_eq!(Balances::reserved_balance(4), 200);
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 1);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 500);
+		assert_eq!(Crowdfund::contribution_get(0, &3), 300);
+
+		// The fund fails, and withdrawing unreserves the contribution, or pays it back out of
+		// the pot once it has been collected
+		run_to_block(10);
+		assert_ok!(Crowdfund::collect_reserves(Origin::signed(7), 0, 1));
+		assert_eq!(Balances::reserved_balance(3), 0);
+		assert_eq!(Balances::reserved_balance(4), 200);
+		assert_ok!(Crowdfund::withdraw(Origin::signed(3), 0));
+		assert_eq!(Balances::reserved_balance(3), 0);
+		assert_eq!(Balances::free_balance(3), 3000);
+		assert_ok!(Crowdfund::withdraw_partial(Origin::signed(4), 0, 50));
+		assert_eq!(Balances::reserved_balance(4), 150);
+		assert_eq!(Balances::free_balance(4), 3850);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 150);
+		ReserveContributions::set(false);
+	});
+}
+
+#[test]
+fn withdraw_to_pays_another_account() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 200));
+
+		// Not until the fund has ended
+		assert_noop!(
+			Crowdfund::withdraw_to(Origin::signed(3), 0, 7),
+			Error::<TestRuntime>::FundStillActive
+		);
+
+		// The fund fails, and the refund opens an account that was empty
+		run_to_block(10);
+		assert_ok!(Crowdfund::withdraw_to(Origin::signed(3), 0, 7));
+		assert_eq!(Balances::free_balance(7), 300);
+		assert_eq!(Balances::free_balance(3), 2700);
+		assert_eq!(Crowdfund::contribution_get(0, &3), 0);
+		assert_eq!(Crowdfund::contribution_record(0, &3), None);
+		assert_eq!(Crowdfund::funds(0).unwrap().raised, 200);
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::Withdrew(3, 0, 300, 10, 7))
+		);
+
+		// A payment too small to open the destination is refused
+		ExistentialDeposit::set(500);
+		assert_noop!(
+			Crowdfund::withdraw_to(Origin::signed(4), 0, 8),
+			Error::<TestRuntime>::DestinationBelowMinimum
+		);
+		ExistentialDeposit::set(1);
+		assert_noop!(
+			Crowdfund::withdraw_to(Origin::signed(3), 0, 8),
+			Error::<TestRuntime>::NoContribution
+		);
+	});
+}
+
+#[test]
+fn withdraw_to_repatriates_reserved_contributions() {
+	new_test_ext().execute_with(|| {
+		ReserveContributions::set(true);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 300));
+
+		run_to_block(10);
+		assert_ok!(Crowdfund::withdraw_to(Origin::signed(3), 0, 4));
+		assert_eq!(Balances::reserved_balance(3), 0);
+		assert_eq!(Balances::free_balance(3), 2700);
+		assert_eq!(Balances::free_balance(4), 4300);
+		assert_eq!(Crowdfund::contribution_record(0, &3), None);
+		ReserveContributions::set(false);
+	});
+}
+
+#[test]
+fn reserved_contributions_are_collected_on_payout() {
+	new_test_ext().execute_with(|| {
+		ReserveContributions::set(true);
+		assert_ok!(Crowdfund::create(
+			Origin::signed(1),
+			2,
+			1000,
+			CapMode::Absolute(2000),
+			10,
+			0,
+			9,
+			5,
+			FundCategory::Other,
+			Vec::new()
+		));
+		assert_ok!(Crowdfund::contribute(Origin::signed(3), 0, 600));
+		assert_ok!(Crowdfund::contribute(Origin::signed(4), 0, 500));
+
+		// The fund succeeds, and the reserves have to be moved to the pot before the owner claims
+		run_to_block(10);
+		assert_noop!(
+			Crowdfund::claim(Origin::signed(1), 0),
+			Error::<TestRuntime>::ReservesNotCollected
+		);
+
+		// They are moved in batches, each charged for the contributions it visits
+		let post = Crowdfund::collect_reserves(Origin::signed(7), 0, 1).unwrap();
+		assert_eq!(post.actual_weight, Some(10_000));
+		assert_eq!(Balances::reserved_balance(3), 0);
+		assert_eq!(Balances::reserved_balance(4), 500);
+		assert_noop!(
+			Crowdfund::claim(Origin::signed(1), 0),
+			Error::<TestRuntime>::ReservesNotCollected
+		);
+		let post = Crowdfund::collect_reserves(Origin::signed(7), 0, 10).unwrap();
+		assert_eq!(post.actual_weight, Some(10_000));
+		assert_eq!(
+			System::events().last().unwrap().event,
+			Event::simple_crowdfund(crate::Event::ReservesCollected(0))
+		);
+		assert_noop!(
+			Crowdfund::collect_reserves(Origin::signed(7), 0, 10),
+			Error::<TestRuntime>::NoReservesToCollect
+		);
+
+		assert_ok!(Crowdfund::claim(Origin::signed(1), 0));
+		assert_eq!(Balances::reserved_balance(3), 0);
+		assert_eq!(Balances::free_balance(3), 2400);
+		assert_eq!(Balances::reserved_balance(4), 0);
+		assert_eq!(Balances::free_balance(4), 3500);
+		assert_eq!(Balances::free_balance(1), 2100);
+		assert_eq!(Balances::free_balance(Crowdfund::fund_account_id(0)), 0);
+		ReserveContributions::set(false);
 	});
 }
@@ -0,0 +1,80 @@
+//! Weights for simple_crowdfund
+//!
+//! These follow the layout produced by the Substrate benchmark CLI. The figures are estimates;
+//! regenerate them for your own hardware from a node built with the `runtime-benchmarks` feature:
+//!
+//! ```text
+//! ./target/release/node benchmark --chain dev --execution wasm --wasm-execution compiled \
+//!     --pallet simple_crowdfund --extrinsic '*' --steps 50 --repeat 20 \
+//!     --output ./pallets/simple-crowdfund/src/weights.rs
+//! ```
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, Weight},
+};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for simple_crowdfund.
+pub trait WeightInfo {
+	fn create() -> Weight;
+	fn contribute(c: u32) -> Weight;
+	fn withdraw(b: u32) -> Weight;
+	fn dissolve() -> Weight;
+}
+
+/// Weights for simple_crowdfund using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn create() -> Weight {
+		(52_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(3 as Weight))
+			.saturating_add(T::DbWeight::get().writes(4 as Weight))
+	}
+	fn contribute(c: u32) -> Weight {
+		(74_000_000 as Weight)
+			.saturating_add((12_000 as Weight).saturating_mul(c as Weight))
+			.saturating_add(T::DbWeight::get().reads(5 as Weight))
+			.saturating_add(T::DbWeight::get().writes(5 as Weight))
+	}
+	fn withdraw(b: u32) -> Weight {
+		(63_000_000 as Weight)
+			.saturating_add((95_000 as Weight).saturating_mul(b as Weight))
+			.saturating_add(T::DbWeight::get().reads(5 as Weight))
+			.saturating_add(T::DbWeight::get().writes(6 as Weight))
+	}
+	fn dissolve() -> Weight {
+		(71_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(3 as Weight))
+			.saturating_add(T::DbWeight::get().writes(6 as Weight))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn create() -> Weight {
+		(52_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn contribute(c: u32) -> Weight {
+		(74_000_000 as Weight)
+			.saturating_add((12_000 as Weight).saturating_mul(c as Weight))
+			.saturating_add(RocksDbWeight::get().reads(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(5 as Weight))
+	}
+	fn withdraw(b: u32) -> Weight {
+		(63_000_000 as Weight)
+			.saturating_add((95_000 as Weight).saturating_mul(b as Weight))
+			.saturating_add(RocksDbWeight::get().reads(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(6 as Weight))
+	}
+	fn dissolve() -> Weight {
+		(71_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(6 as Weight))
+	}
+}
@@ -1,6 +1,8 @@
 //! Helper module to build a genesis configuration for the Offchain Worker
 
-use super::{AccountId, BalancesConfig, GenesisConfig, Signature, SudoConfig, SystemConfig};
+use super::{
+	AccountId, BalancesConfig, GenesisConfig, OcwDemoConfig, Signature, SudoConfig, SystemConfig,
+};
 use sp_core::{sr25519, Pair};
 use sp_runtime::traits::{IdentifyAccount, Verify};
 
@@ -55,5 +57,9 @@ pub fn testnet_genesis(
 				.collect(),
 		}),
 		pallet_sudo: Some(SudoConfig { key: root_key }),
+		// The kitchen node gives its off-chain workers Alice's key
+		ocw_demo: Some(OcwDemoConfig {
+			authorities: vec![account_id_from_seed::<sr25519::Pair>("Alice")],
+		}),
 	}
 }
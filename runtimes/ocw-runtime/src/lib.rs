@@ -223,10 +223,48 @@ impl pallet_sudo::Config for Runtime {
 ///   inside `create_transaction` function.
 pub type SignedPayload = generic::SignedPayload<Call, SignedExtra>;
 
+parameter_types! {
+	pub const RoundAverage: bool = false;
+	pub const MaxFetchRetries: u32 = 3;
+	pub const MaxNumbers: u32 = 10;
+	pub const FetchInterval: BlockNumber = 4;
+	pub const CacheTtl: u64 = 60 * 60 * 1000;
+	pub const HttpTimeout: u64 = 3000;
+	pub const DedupNumbers: bool = false;
+	pub const SubmitThreshold: u64 = 0;
+	pub const FailureThreshold: u32 = 5;
+	// No batch source, so off-chain workers only submit one number at a time
+	pub const BatchSource: Option<&'static [u8]> = None;
+	// The longest login github allows
+	pub const MaxLoginLength: u32 = 39;
+	pub const MaxBlogLength: u32 = 256;
+	pub const MaxResponseBytes: u32 = 64 * 1024;
+	pub const EmaAlpha: Perbill = Perbill::from_percent(20);
+	pub const SignedBatchSize: u32 = 3;
+	// No price sources, so off-chain workers submit the block number
+	pub PriceSources: Vec<&'static [u8]> = Vec::new();
+}
+
 impl ocw_demo::Config for Runtime {
 	type AuthorityId = ocw_demo::crypto::TestAuthId;
 	type Call = Call;
 	type Event = Event;
+	type RoundAverage = RoundAverage;
+	type MaxFetchRetries = MaxFetchRetries;
+	type PriceSources = PriceSources;
+	type MaxNumbers = MaxNumbers;
+	type FetchInterval = FetchInterval;
+	type CacheTtl = CacheTtl;
+	type HttpTimeout = HttpTimeout;
+	type DedupNumbers = DedupNumbers;
+	type SubmitThreshold = SubmitThreshold;
+	type FailureThreshold = FailureThreshold;
+	type BatchSource = BatchSource;
+	type MaxLoginLength = MaxLoginLength;
+	type MaxBlogLength = MaxBlogLength;
+	type MaxResponseBytes = MaxResponseBytes;
+	type EmaAlpha = EmaAlpha;
+	type SignedBatchSize = SignedBatchSize;
 }
 
 impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Runtime
@@ -300,7 +338,7 @@ construct_runtime!(
 		Sudo: pallet_sudo::{Module, Call, Config<T>, Storage, Event<T>},
 		TransactionPayment: pallet_transaction_payment::{Module, Storage},
 		// The Recipe Pallets
-		OcwDemo: ocw_demo::{Module, Call, Storage, Event<T>, ValidateUnsigned},
+		OcwDemo: ocw_demo::{Module, Call, Storage, Config<T>, Event<T>, ValidateUnsigned},
 	}
 );
 
@@ -56,5 +56,6 @@ pub fn testnet_genesis(
 		}),
 		pallet_sudo: Some(SudoConfig { key: root_key }),
 		charity: Some(Default::default()),
+		simple_crowdfund: Some(Default::default()),
 	}
 }
@@ -19,11 +19,13 @@ use frame_system as system;
 use pallet_transaction_payment::CurrencyAdapter;
 use sp_api::impl_runtime_apis;
 use sp_core::{OpaqueMetadata, H256};
-use sp_runtime::traits::{BlakeTwo256, Block as BlockT, IdentifyAccount, IdentityLookup, Verify};
+use sp_runtime::traits::{
+	AccountIdConversion, BlakeTwo256, Block as BlockT, IdentifyAccount, IdentityLookup, Verify,
+};
 use sp_runtime::{
 	create_runtime_str, generic,
 	transaction_validity::{TransactionSource, TransactionValidity},
-	ApplyExtrinsicResult, MultiSignature,
+	ApplyExtrinsicResult, FixedU128, ModuleId, MultiSignature,
 };
 use sp_std::prelude::*;
 
@@ -33,7 +35,7 @@ use sp_version::RuntimeVersion;
 
 use frame_support::{
 	construct_runtime, parameter_types,
-	traits::Randomness,
+	traits::{Currency, OnUnbalanced, Randomness},
 	weights::{
 		constants::{RocksDbWeight, WEIGHT_PER_SECOND},
 		IdentityFee,
@@ -305,17 +307,88 @@ impl reservable_currency::Config for Runtime {
 }
 
 parameter_types! {
+	pub const CrowdfundModuleId: ModuleId = ModuleId(*b"ex/cfund");
+	// Whatever is left in dissolved funds is kept in this module's account
+	pub const DissolvedFundsModuleId: ModuleId = ModuleId(*b"ex/cfdis");
 	pub const SubmissionDeposit: u128 = 10;
+	pub const DepositPerCapUnit: u128 = 0;
 	pub const MinContribution: u128 = 10;
+	// Contributions of any amount are accepted
+	pub const ContributionIncrement: u128 = 0;
+	pub const ReserveContributions: bool = false;
+	pub const AllowOwnerContribution: bool = true;
 	pub const RetirementPeriod: u32 = 10;
+	// Set to e.g. `Some(7 * 24 * 60 * 60 * 1000)` to retire funds after a week of wall-clock time
+	pub const RetirementDuration: Option<u64> = None;
+	pub const MaxExtension: u32 = 100;
+	pub const MaxFundsPerContributor: u32 = 16;
+	pub const MaxFundsPerOwner: u32 = 16;
+	pub const MaxContributionPerAccount: u128 = 0;
+	pub const MaxWithdrawPerBlock: Option<u128> = None;
+	pub const MaxContributionsPerBlock: u32 = 0;
+	// No fee is taken, so there is nothing for the collector to receive
+	pub const FeePercent: Perbill = Perbill::zero();
+	pub const MaxMetadataLen: u32 = 256;
+	pub const MaxMilestones: u32 = 10;
+	pub const MaxBonusTiers: u32 = 10;
+	pub const MaxWhitelistLen: u32 = 100;
+	pub const MaxInstallmentsPerFund: u32 = 100;
+	pub const MaxInstallmentFundsPerBlock: u32 = 10;
+	// This runtime has no token of its own to reward contributors with
+	pub const RewardRate: FixedU128 = FixedU128::from_inner(0);
+	// Sponsors' match pools match contributions one to one
+	pub const MatchRatio: Perbill = Perbill::one();
+	// Whoever dissolves a fund is paid at least this much for the storage it frees
+	pub const MinKeeperReward: u128 = 1;
+}
+
+/// Keeps whatever is left in a dissolved fund in the account of `DissolvedFundsModuleId`, the
+/// way a treasury would, instead of burning it. Anyone may dissolve a fund in this runtime.
+pub struct DissolvedFunds;
+type NegativeImbalance = <Balances as Currency<AccountId>>::NegativeImbalance;
+impl OnUnbalanced<NegativeImbalance> for DissolvedFunds {
+	fn on_nonzero_unbalanced(leftover: NegativeImbalance) {
+		Balances::resolve_creating(&DissolvedFundsModuleId::get().into_account(), leftover);
+	}
 }
 
 impl simple_crowdfund::Config for Runtime {
 	type Event = Event;
 	type Currency = Balances;
+	type ReserveContributions = ReserveContributions;
+	type ModuleId = CrowdfundModuleId;
 	type SubmissionDeposit = SubmissionDeposit;
+	type DepositPerCapUnit = DepositPerCapUnit;
 	type MinContribution = MinContribution;
+	type ContributionIncrement = ContributionIncrement;
 	type RetirementPeriod = RetirementPeriod;
+	type Time = Timestamp;
+	type RetirementDuration = RetirementDuration;
+	type CreateOrigin = frame_system::EnsureSigned<AccountId>;
+	type DissolveOrigin = frame_system::EnsureSigned<AccountId>;
+	type DissolveDestination = DissolvedFunds;
+	type MinKeeperReward = MinKeeperReward;
+	type SlashOrigin = frame_system::EnsureRoot<AccountId>;
+	type SlashDestination = ();
+	type MaxExtension = MaxExtension;
+	type MaxFundsPerContributor = MaxFundsPerContributor;
+	type MaxFundsPerOwner = MaxFundsPerOwner;
+	type AllowOwnerContribution = AllowOwnerContribution;
+	type MaxContributionPerAccount = MaxContributionPerAccount;
+	type MaxWithdrawPerBlock = MaxWithdrawPerBlock;
+	type MaxContributionsPerBlock = MaxContributionsPerBlock;
+	type FeePercent = FeePercent;
+	type FeeCollector = ();
+	type RewardCurrency = Balances;
+	type RewardRate = RewardRate;
+	type MatchRatio = MatchRatio;
+	type MaxMetadataLen = MaxMetadataLen;
+	type MaxMilestones = MaxMilestones;
+	type MaxBonusTiers = MaxBonusTiers;
+	type MaxWhitelistLen = MaxWhitelistLen;
+	type MaxInstallmentsPerFund = MaxInstallmentsPerFund;
+	type MaxInstallmentFundsPerBlock = MaxInstallmentFundsPerBlock;
+	type WeightInfo = simple_crowdfund::weights::SubstrateWeight<Runtime>;
 }
 
 impl simple_event::Config for Runtime {
@@ -370,7 +443,7 @@ construct_runtime!(
 		RingbufferQueue: ringbuffer_queue::{Module, Call, Storage, Event<T>},
 		RandomnessDemo: randomness::{Module, Call, Storage, Event<T>},
 		ReservableCurrency: reservable_currency::{Module, Call, Event<T>},
-		SimpleCrowdfund: simple_crowdfund::{Module, Call, Storage, Event<T>},
+		SimpleCrowdfund: simple_crowdfund::{Module, Call, Storage, Config<T>, Event<T>},
 		SimpleMap: simple_map::{Module, Call, Storage, Event<T>},
 		StorageCache: storage_cache::{Module, Call, Storage, Event<T>},
 		StructStorage: struct_storage::{Module, Call, Storage, Event<T>},
@@ -468,6 +541,18 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl simple_crowdfund_runtime_api::CrowdfundApi<Block, AccountId, Balance> for Runtime {
+		fn contribution(index: simple_crowdfund::FundIndex, who: AccountId) -> Balance {
+			SimpleCrowdfund::contribution_get(index, &who)
+		}
+
+		fn backer_portfolio(
+			who: AccountId,
+		) -> Vec<(simple_crowdfund::FundIndex, Balance, simple_crowdfund::FundState)> {
+			SimpleCrowdfund::backer_portfolio(&who)
+		}
+	}
+
 	impl sp_session::SessionKeys<Block> for Runtime {
 		fn generate_session_keys(_seed: Option<Vec<u8>>) -> Vec<u8> {
 			Vec::new()